@@ -0,0 +1,52 @@
+use cocotools::coco::object_detection::Dataset;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+/// Json of a dataset with `nb_images` images of 10 annotations each.
+/// With `extra_fields`, every image and annotation also has fields that are not part of the COCO format.
+fn dataset_json(nb_images: u64, extra_fields: bool) -> String {
+    let img_extra = if extra_fields {
+        r#","occluded":false"#
+    } else {
+        ""
+    };
+    let ann_extra = if extra_fields {
+        r#","attributes":{"track_id":3,"keyframe":true,"color":"red"}"#
+    } else {
+        ""
+    };
+    let images: Vec<String> = (1..=nb_images)
+        .map(|id| {
+            format!(r#"{{"id":{id},"width":640,"height":480,"file_name":"{id}.jpg"{img_extra}}}"#)
+        })
+        .collect();
+    let annotations: Vec<String> = (0..nb_images * 10)
+        .map(|i| {
+            format!(
+                r#"{{"id":{},"image_id":{},"category_id":1,"segmentation":[[10.0,10.0,50.0,10.0,50.0,40.0,10.0,40.0]],"area":1200.0,"bbox":[10.0,10.0,40.0,30.0],"iscrowd":0{ann_extra}}}"#,
+                i + 1,
+                i / 10 + 1
+            )
+        })
+        .collect();
+    format!(
+        r#"{{"images":[{}],"annotations":[{}],"categories":[{{"id":1,"name":"car","supercategory":"vehicle"}}]}}"#,
+        images.join(","),
+        annotations.join(",")
+    )
+}
+
+#[allow(clippy::unwrap_used)]
+fn load_dataset(c: &mut Criterion) {
+    let mut group = c.benchmark_group("load_dataset");
+    group.sample_size(20);
+    for (name, extra_fields) in [("coco_fields", false), ("extra_fields", true)] {
+        let json = dataset_json(2000, extra_fields);
+        group.bench_function(name, |b| {
+            b.iter(|| serde_json::from_str::<Dataset>(black_box(&json)).unwrap());
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, load_dataset);
+criterion_main!(benches);
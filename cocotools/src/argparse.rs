@@ -2,7 +2,10 @@ use std::path::PathBuf;
 
 use clap::{Parser, Subcommand};
 
-use crate::mask::conversions::Segmentation;
+use cocotools::coco::merge::DuplicateImages;
+use cocotools::converters::semantic::Overlap;
+use cocotools::converters::yolo::YoloTask;
+use cocotools::mask::conversions::Segmentation;
 
 #[derive(Parser)]
 #[command(author, version, about)]
@@ -11,6 +14,8 @@ pub struct Cli {
     pub command: Commands,
 }
 
+// The doc comments are used as help messages, backticks would be displayed as-is.
+#[allow(clippy::doc_markdown)]
 #[derive(Subcommand)]
 pub enum Commands {
     /// Visualize COCO labels.
@@ -32,7 +37,379 @@ pub enum Commands {
         /// Path to where the output will be saved (for example "output/annotation_rle.json"). If not given, the conversion is done in place.
         #[arg(short, long)]
         output_path: Option<PathBuf>,
+        /// Order the entries by id in the output file, instead of keeping the order of the input file.
+        #[arg(long)]
+        sort_by_id: bool,
+    },
+
+    /// Check the consistency of a COCO annotation file (duplicated ids, missing categories, invalid segmentations, etc.).
+    ///
+    /// Exits with a non-zero status code if an error is found, or if a warning is found and --deny-warnings is given.
+    Validate {
+        /// Path to the COCO json annotation file.
+        annotations_file: PathBuf,
+        /// Print the report as json instead of as text.
+        #[arg(long)]
+        json: bool,
+        /// Also exit with a non-zero status code when there are only warnings.
+        #[arg(long)]
+        deny_warnings: bool,
+    },
+
+    /// Export a COCO dataset to another format.
+    #[command(subcommand)]
+    Export(ExportFormat),
+
+    /// Create a COCO annotation file from a dataset in another format.
+    #[command(subcommand)]
+    Import(ImportFormat),
+
+    /// Split a COCO dataset by image into several COCO datasets (train/val/test for example).
+    ///
+    /// Each split is saved as "<output_folder>/<name>.json", with all the categories of the dataset.
+    Split {
+        /// Path to the COCO json annotation file.
+        annotations_file: PathBuf,
+        /// Path to the folder where the COCO json annotation files of the splits will be saved.
+        output_folder: PathBuf,
+        /// Proportion of images in each split, they do not need to sum to 1.
+        #[arg(short, long, num_args = 1.., default_values_t = [0.8, 0.2])]
+        ratios: Vec<f64>,
+        /// Name of each split. Defaults to train, val and test (or split_1, split_2, etc. with more than 3 splits).
+        #[arg(short, long, num_args = 1..)]
+        names: Option<Vec<String>>,
+        /// Seed used to shuffle the images.
+        #[arg(short, long, default_value_t = 0)]
+        seed: u64,
+        /// Balance the annotations of each category across the splits.
+        #[arg(long)]
+        stratify: bool,
+        /// Order the entries by id in the output file, instead of keeping the order of the input file.
+        #[arg(long)]
+        sort_by_id: bool,
+    },
+
+    /// Split a COCO dataset by image into k folds for cross-validation.
+    ///
+    /// The train and val COCO json annotation files of the i-th fold are saved in "<output_folder>/fold_<i>/",
+    /// and the number of images and annotations of each category of every fold in "<output_folder>/summary.json".
+    KFold {
+        /// Path to the COCO json annotation file.
+        annotations_file: PathBuf,
+        /// Path to the folder where the folds will be saved.
+        output_folder: PathBuf,
+        /// Number of folds.
+        #[arg(short, long, default_value_t = 5)]
+        k: usize,
+        /// Seed used to shuffle the images.
+        #[arg(short, long, default_value_t = 0)]
+        seed: u64,
+        /// Balance the annotations of each category across the folds.
+        #[arg(long)]
+        stratify: bool,
+        /// Keep the images with the same value for this image field in the same fold (for example date_captured, or a capture session field).
+        #[arg(short, long)]
+        group_by: Option<String>,
+        /// Order the entries by id in the output file, instead of keeping the order of the input file.
+        #[arg(long)]
+        sort_by_id: bool,
+    },
+
+    /// Merge several COCO datasets into one.
+    ///
+    /// Colliding ids are reassigned, the categories are unified by name, and the images with the same path are detected.
+    /// When the image folders are given, the file_name of each image becomes its path relative to the deepest folder containing all of them,
+    /// and the images with the same file_name in different folders are different images.
+    Merge {
+        /// Paths to the COCO json annotation files to merge.
+        #[arg(required = true)]
+        annotations_files: Vec<PathBuf>,
+        /// Path to where the merged COCO json annotation file will be saved.
+        #[arg(short, long)]
+        output_path: PathBuf,
+        /// Path to the image folder of each dataset, in the same order as the annotation files.
+        #[arg(short, long, num_args = 1..)]
+        image_folders: Option<Vec<PathBuf>>,
+        /// Path to a json file mapping category names to the name they should be merged into, for example {"automobile": "car"}.
+        #[arg(short, long)]
+        mapping: Option<PathBuf>,
+        /// What to do with the images that appear several times.
+        #[arg(short, long, default_value = "merge")]
+        duplicates: DuplicateImages,
+        /// Order the entries by id in the output file, instead of keeping the order of the merged files.
+        #[arg(long)]
+        sort_by_id: bool,
+    },
+
+    /// Keep only the annotations matching an expression, for example 'category in [car, "traffic light"] and area >= 1024 and iscrowd == 0'.
+    ///
+    /// The clauses are joined with "and", the fields are category, category_id, supercategory and image_id (with == or in),
+    /// area (the bounding box area, with >= or <=) and iscrowd (with ==).
+    Filter {
+        /// Path to the COCO json annotation file.
+        annotations_file: PathBuf,
+        /// Expression selecting the annotations to keep.
+        expression: String,
+        /// Path to where the filtered COCO json annotation file will be saved.
+        output_path: PathBuf,
+        /// Remove the images without annotations after filtering.
+        #[arg(long)]
+        drop_empty_images: bool,
+        /// Remove the categories without annotations after filtering.
+        #[arg(long)]
+        prune_categories: bool,
+        /// Order the entries by id in the output file, instead of keeping the order of the input file.
+        #[arg(long)]
+        sort_by_id: bool,
+    },
+
+    /// Rename, merge and delete categories with a mapping file, and print the number of affected annotations.
+    ///
+    /// The mapping file is a json list of rules such as {"from": "automobile", "to": "car"}, where the categories are given by
+    /// name or id, and "to" can also be a new category {"id": 3, "name": "bicycle", "supercategory": "vehicle"} or null to delete the category.
+    Remap {
+        /// Path to the COCO json annotation file.
+        annotations_file: PathBuf,
+        /// Path to the json file with the mapping rules.
+        mapping_file: PathBuf,
+        /// Path to where the output will be saved. If not given, the remapping is done in place.
+        #[arg(short, long)]
+        output_path: Option<PathBuf>,
+        /// Order the entries by id in the output file, instead of keeping the order of the input file.
+        #[arg(long)]
+        sort_by_id: bool,
     },
-    // Split a COCO dataset in two.
     // Convert to/from PascalVOC, SOLO.
 }
+
+#[allow(clippy::doc_markdown)]
+#[derive(Subcommand)]
+pub enum ExportFormat {
+    /// Export to the YOLO (Ultralytics) format, with a data.yaml file and one label file per image.
+    Yolo {
+        /// Path to the COCO json annotation file.
+        annotations_file: PathBuf,
+        /// Path to the folder with the images.
+        image_folder: PathBuf,
+        /// Folder where the YOLO dataset will be written.
+        output_folder: PathBuf,
+        /// Export the bounding boxes (detect) or the segmentation masks as polygons (segment).
+        #[arg(short, long, value_enum, default_value_t = YoloTask::Detect)]
+        task: YoloTask,
+        /// Copy the images to the "images" folder of the output folder.
+        #[arg(long)]
+        copy_images: bool,
+    },
+    /// Export to the Pascal VOC format, with one xml annotation file per image.
+    Voc {
+        /// Path to the COCO json annotation file.
+        annotations_file: PathBuf,
+        /// Path to the folder with the images.
+        image_folder: PathBuf,
+        /// Root folder of the VOC dataset.
+        output_folder: PathBuf,
+        /// Name of the image set file listing the images, in the ImageSets/Main folder.
+        #[arg(short, long, default_value = "train")]
+        split: String,
+        /// Copy the images to the JPEGImages folder of the output folder.
+        #[arg(long)]
+        copy_images: bool,
+    },
+    /// Export to the CVAT for images 1.1 xml format, for example to upload pre-annotations.
+    Cvat {
+        /// Path to the COCO json annotation file.
+        annotations_file: PathBuf,
+        /// Path to where the xml file will be saved.
+        output_path: PathBuf,
+    },
+    /// Export to a folder of LabelMe json files, one per image.
+    Labelme {
+        /// Path to the COCO json annotation file.
+        annotations_file: PathBuf,
+        /// Path to the folder with the images.
+        image_folder: PathBuf,
+        /// Folder where the json files will be written, with the same structure as the image folder.
+        output_folder: PathBuf,
+        /// Copy the images next to their json file.
+        #[arg(long)]
+        copy_images: bool,
+        /// Embed the images in the json files (imageData field).
+        #[arg(long)]
+        embed_images: bool,
+    },
+    /// Export to Label Studio pre-annotation tasks, the annotations becoming predictions with their scores.
+    Labelstudio {
+        /// Path to the COCO json annotation file.
+        annotations_file: PathBuf,
+        /// Path to where the json file with the tasks will be saved.
+        output_path: PathBuf,
+        /// Beginning of the image urls, followed by the file names of the images.
+        #[arg(short, long, default_value = "/data/local-files/?d=")]
+        image_root: String,
+        /// Model version given to the predictions.
+        #[arg(short, long, default_value = "cocotools")]
+        model_version: String,
+    },
+    /// Export to semantic segmentation label maps, PNG images in which each pixel value is a category index.
+    Semantic {
+        /// Path to the COCO json annotation file.
+        annotations_file: PathBuf,
+        /// Folder where the label maps and the labels.txt file will be written.
+        output_folder: PathBuf,
+        /// Value of the pixels without any category.
+        #[arg(short, long, default_value_t = 255)]
+        ignore_value: u8,
+        /// Which annotation gives its category to the pixels where annotations overlap.
+        #[arg(short, long, value_enum, default_value_t = Overlap::Last)]
+        overlap: Overlap,
+    },
+    /// Export to the KITTI object label format, with one label file per image. Crowd annotations become DontCare objects.
+    Kitti {
+        /// Path to the COCO json annotation file.
+        annotations_file: PathBuf,
+        /// Path to the folder with the images.
+        image_folder: PathBuf,
+        /// Folder where the label_2 (and image_2) folders will be written.
+        output_folder: PathBuf,
+        /// Copy the images to the image_2 folder of the output folder.
+        #[arg(long)]
+        copy_images: bool,
+    },
+}
+
+#[allow(clippy::doc_markdown)]
+#[derive(Subcommand)]
+pub enum ImportFormat {
+    /// Import a YOLO (Ultralytics) dataset, the images without label file have no annotation.
+    Yolo {
+        /// Path to the folder with the images.
+        image_folder: PathBuf,
+        /// Path to the folder with the label files, with the same structure as the image folder.
+        labels_folder: PathBuf,
+        /// Path to the data.yaml file, or to a text file with one class name per line.
+        class_names: PathBuf,
+        /// Path to where the COCO json annotation file will be saved.
+        output_path: PathBuf,
+        /// Order the entries by id in the output file, instead of the order in which they were imported.
+        #[arg(long)]
+        sort_by_id: bool,
+    },
+    /// Import a Pascal VOC dataset (Annotations and JPEGImages folders).
+    Voc {
+        /// Root folder of the VOC dataset.
+        voc_folder: PathBuf,
+        /// Path to where the COCO json annotation file will be saved.
+        output_path: PathBuf,
+        /// Only import the images of this split (listed in ImageSets/Main/<split>.txt), instead of all the annotation files.
+        #[arg(short, long)]
+        split: Option<String>,
+        /// Order the entries by id in the output file, instead of the order in which they were imported.
+        #[arg(long)]
+        sort_by_id: bool,
+    },
+    /// Import a CVAT for images 1.1 xml file (boxes, polygons and masks).
+    Cvat {
+        /// Path to the CVAT xml annotation file.
+        xml_file: PathBuf,
+        /// Path to where the COCO json annotation file will be saved.
+        output_path: PathBuf,
+        /// Order the entries by id in the output file, instead of the order in which they were imported.
+        #[arg(long)]
+        sort_by_id: bool,
+    },
+    /// Import a folder of LabelMe json files.
+    Labelme {
+        /// Path to the folder with the LabelMe json files.
+        labelme_folder: PathBuf,
+        /// Path to where the COCO json annotation file will be saved.
+        output_path: PathBuf,
+        /// Extract the images embedded in the json files to this folder.
+        #[arg(short, long)]
+        extract_images: Option<PathBuf>,
+        /// Order the entries by id in the output file, instead of the order in which they were imported.
+        #[arg(long)]
+        sort_by_id: bool,
+    },
+    /// Import a Label Studio json export (rectangle, polygon and brush labels).
+    Labelstudio {
+        /// Path to the Label Studio json file.
+        json_file: PathBuf,
+        /// Path to the folder with the images, used to get the size of the images of the tasks without regions.
+        image_folder: PathBuf,
+        /// Path to where the COCO json annotation file will be saved.
+        output_path: PathBuf,
+        /// Import the predictions of the tasks instead of their annotations.
+        #[arg(long)]
+        predictions: bool,
+        /// Order the entries by id in the output file, instead of the order in which they were imported.
+        #[arg(long)]
+        sort_by_id: bool,
+    },
+    /// Import the bounding boxes of an Open Images style dataset, the boxes of the images not in the image folder are skipped.
+    Openimages {
+        /// Path to the csv file with the bounding boxes.
+        boxes_csv: PathBuf,
+        /// Path to the csv file with the name of each class MID.
+        class_descriptions_csv: PathBuf,
+        /// Path to the folder with the images, named after their ImageID.
+        image_folder: PathBuf,
+        /// Path to where the COCO json annotation file will be saved.
+        output_path: PathBuf,
+        /// Order the entries by id in the output file, instead of the order in which they were imported.
+        #[arg(long)]
+        sort_by_id: bool,
+    },
+    /// Import semantic segmentation label maps, each connected region of a category becomes an annotation.
+    Semantic {
+        /// Path to the folder with the images.
+        image_folder: PathBuf,
+        /// Path to the folder with the label maps, with the same structure as the image folder.
+        label_maps_folder: PathBuf,
+        /// Path to a text file with one class name per line (labels.txt), or to a YOLO data.yaml file.
+        class_names: PathBuf,
+        /// Path to where the COCO json annotation file will be saved.
+        output_path: PathBuf,
+        /// Value of the pixels without any category.
+        #[arg(short, long, default_value_t = 255)]
+        ignore_value: u8,
+        /// Order the entries by id in the output file, instead of the order in which they were imported.
+        #[arg(long)]
+        sort_by_id: bool,
+    },
+    /// Import a dataset annotated with instance PNG images (label_id * 1000 + instance index), like Cityscapes or ADE20K.
+    Cityscapes {
+        /// Path to the folder with the instance PNG images.
+        instances_folder: PathBuf,
+        /// Path to the folder with the images, with the same structure as the instances folder.
+        image_folder: PathBuf,
+        /// Path to where the COCO json annotation file will be saved.
+        output_path: PathBuf,
+        /// Path to a json label table (list of {"id", "name", "category", "hasInstances", "ignoreInEval"}), defaults to the Cityscapes labels.
+        #[arg(short, long)]
+        labels: Option<PathBuf>,
+        /// End of the names of the instance PNG images.
+        #[arg(long, default_value = "_gtFine_instanceIds.png")]
+        instances_suffix: String,
+        /// End of the names of the images, replacing the instances suffix.
+        #[arg(long, default_value = "_leftImg8bit.png")]
+        image_suffix: String,
+        /// Also import the stuff regions (labels without instances), except the ones ignored in evaluation.
+        #[arg(long)]
+        include_stuff: bool,
+        /// Order the entries by id in the output file, instead of the order in which they were imported.
+        #[arg(long)]
+        sort_by_id: bool,
+    },
+    /// Import a KITTI object dataset, DontCare objects become crowd annotations.
+    Kitti {
+        /// Path to the folder with the images (image_2).
+        image_folder: PathBuf,
+        /// Path to the folder with the label files (label_2).
+        labels_folder: PathBuf,
+        /// Path to where the COCO json annotation file will be saved.
+        output_path: PathBuf,
+        /// Order the entries by id in the output file, instead of the order in which they were imported.
+        #[arg(long)]
+        sort_by_id: bool,
+    },
+}
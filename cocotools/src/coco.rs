@@ -1,7 +1,17 @@
 //! Module containing the data annotation formats.
 
+pub mod eval;
+pub mod filter;
+#[cfg(test)]
+pub(crate) mod fixtures;
+pub mod merge;
 pub mod object_detection;
+pub mod panoptic;
+pub mod panoptic_eval;
 #[cfg(feature = "pyo3")]
 pub mod pyo3;
+pub mod remap;
+pub mod split;
+pub mod validate;
 
 pub use crate::coco::object_detection::HashmapDataset as COCO;
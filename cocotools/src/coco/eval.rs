@@ -0,0 +1,1099 @@
+//! Module implementing the COCO evaluation metrics.
+//!
+//! The implementation follows the reference [`COCOeval`](https://github.com/cocodataset/cocoapi/blob/master/PythonAPI/pycocotools/cocoeval.py)
+//! from pycocotools closely (including its tie breaking rules) so that the results are numerically identical.
+use std::collections::HashMap;
+use std::fmt;
+
+use ndarray::{Array4, Array5};
+use serde::{Deserialize, Serialize};
+
+use crate::coco::object_detection::{
+    self, Annotation, Bbox, HashmapDataset, Image, PolygonsRS, Rle, Segmentation,
+};
+use crate::errors::{CocoError, EvaluationError, MaskError};
+use crate::mask::{self, conversions, utils::Area};
+
+/// Scored prediction, as found in a COCO results file.
+///
+/// Examples of what a detection looks like in the JSON file:
+/// - Bounding box: `{"image_id": 42, "category_id": 18, "bbox": [258.15, 41.29, 348.26, 243.78], "score": 0.236}`
+/// - Segmentation: `{"image_id": 42, "category_id": 18, "segmentation": {"size": [480, 640], "counts": "aUh2b0X...BgRU4"}, "score": 0.236}`
+/// - Keypoints: `{"image_id": 42, "category_id": 1, "keypoints": [259.0, 84.0, 1.0, ...], "score": 0.236}`
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Detection {
+    pub image_id: u64,
+    pub category_id: u32,
+    /// Only required for the bounding box evaluation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bbox: Option<Bbox>,
+    /// Only required for the segmentation evaluation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub segmentation: Option<Segmentation>,
+    /// Only required for the keypoints evaluation, see [`Annotation::keypoints`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keypoints: Option<Vec<f64>>,
+    pub score: f64,
+}
+
+/// Type of IoU used to match the detections with the ground truth annotations.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum IouType {
+    /// IoU between the bounding boxes.
+    Bbox,
+    /// IoU between the segmentation masks.
+    Segm,
+    /// Object keypoint similarity (OKS) between the keypoints.
+    Keypoints,
+}
+
+/// Range of object areas to evaluate on.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AreaRange {
+    /// Name used when displaying the results (`all`, `small`, `medium` or `large` by default).
+    pub label: String,
+    pub min: f64,
+    pub max: f64,
+}
+
+/// Parameters of the evaluation.
+///
+/// Use [`Params::new`] to get the default parameters used by pycocotools.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Params {
+    pub iou_type: IouType,
+    /// Ids of the images to evaluate on.
+    pub img_ids: Vec<u64>,
+    /// Ids of the categories to evaluate on.
+    pub cat_ids: Vec<u32>,
+    /// IoU thresholds used to decide whether a detection matches a ground truth annotation.
+    pub iou_thrs: Vec<f64>,
+    /// Recall thresholds at which the precision is sampled.
+    pub rec_thrs: Vec<f64>,
+    /// Maximum number of detections per image, in increasing order.
+    pub max_dets: Vec<usize>,
+    pub area_rngs: Vec<AreaRange>,
+    /// If false, the categories are ignored and all the annotations of an image are evaluated together.
+    pub use_cats: bool,
+    /// Per keypoint standard deviations used to compute the OKS, only used for the keypoints evaluation.
+    pub kpt_oks_sigmas: Vec<f64>,
+}
+
+/// Result of an evaluation, before summarization.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Evaluation {
+    pub params: Params,
+    /// Precision for each `[iou threshold, recall threshold, category, area range, max detections]`.\
+    /// The value is -1 when there is no ground truth annotation for the category.
+    pub precision: Array5<f64>,
+    /// Recall for each `[iou threshold, category, area range, max detections]`.\
+    /// The value is -1 when there is no ground truth annotation for the category.
+    pub recall: Array4<f64>,
+    /// Score of the detection at which each precision value is reached.
+    pub scores: Array5<f64>,
+}
+
+/// One of the summary metrics, for example the AP for IoU=0.50:0.95, all areas and 100 detections.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Metric {
+    /// True if the metric is an average precision, false if it is an average recall.
+    pub ap: bool,
+    /// IoU threshold used, `None` if the metric is averaged over all the thresholds.
+    pub iou_thr: Option<f64>,
+    pub area_rng: String,
+    pub max_dets: usize,
+    pub value: f64,
+}
+
+/// Summary of an evaluation, as printed by pycocotools' `COCOeval.summarize`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Summary {
+    pub metrics: Vec<Metric>,
+    iou_thrs: (f64, f64),
+}
+
+/// Per image, category and area range evaluation results.
+struct ImgEval {
+    /// Scores of the detections, in decreasing order.
+    dt_scores: Vec<f64>,
+    /// For each IoU threshold, whether each detection was matched.
+    dt_matches: Vec<Vec<bool>>,
+    /// For each IoU threshold, whether each detection should be ignored.
+    dt_ignore: Vec<Vec<bool>>,
+    gt_ignore: Vec<bool>,
+}
+
+impl Params {
+    /// Default parameters, evaluating on all the images and categories of the ground truth dataset.
+    #[must_use]
+    pub fn new(gt: &HashmapDataset, iou_type: IouType) -> Self {
+        let mut img_ids: Vec<u64> = gt.get_imgs().iter().map(|img| img.id).collect();
+        img_ids.sort_unstable();
+        let mut cat_ids: Vec<u32> = gt.get_cats().iter().map(|cat| cat.id).collect();
+        cat_ids.sort_unstable();
+
+        let area_rng = |label: &str, min: f64, max: f64| AreaRange {
+            label: label.to_string(),
+            min,
+            max,
+        };
+
+        let (max_dets, area_rngs) = if iou_type == IouType::Keypoints {
+            (
+                vec![20],
+                vec![
+                    area_rng("all", 0.0, 1e10),
+                    area_rng("medium", 32.0 * 32.0, 96.0 * 96.0),
+                    area_rng("large", 96.0 * 96.0, 1e10),
+                ],
+            )
+        } else {
+            (
+                vec![1, 10, 100],
+                vec![
+                    area_rng("all", 0.0, 1e10),
+                    area_rng("small", 0.0, 32.0 * 32.0),
+                    area_rng("medium", 32.0 * 32.0, 96.0 * 96.0),
+                    area_rng("large", 96.0 * 96.0, 1e10),
+                ],
+            )
+        };
+
+        Self {
+            iou_type,
+            img_ids,
+            cat_ids,
+            iou_thrs: linspace(0.5, 0.95, 10),
+            rec_thrs: linspace(0.0, 1.0, 101),
+            max_dets,
+            area_rngs,
+            use_cats: true,
+            // Sigmas of the 17 COCO person keypoints.
+            kpt_oks_sigmas: [
+                0.26, 0.25, 0.25, 0.35, 0.35, 0.79, 0.79, 0.72, 0.72, 0.62, 0.62, 1.07, 1.07, 0.87,
+                0.87, 0.89, 0.89,
+            ]
+            .iter()
+            .map(|sigma| sigma / 10.0)
+            .collect(),
+        }
+    }
+}
+
+/// Evaluate the detections against the ground truth dataset.
+///
+/// ## Args
+/// - `gt`: The ground truth dataset.
+/// - `dts`: The detections to evaluate.
+/// - `params`: The evaluation parameters, see [`Params::new`] for the default ones.
+///
+/// ## Example
+///
+/// ```rust
+/// # use std::path::PathBuf;
+/// use cocotools::COCO;
+/// use cocotools::coco::eval::{self, Detection, IouType, Params};
+///
+/// let gt = COCO::new(PathBuf::from("../data_samples/coco_25k/annotations.json"), PathBuf::from("../data_samples/coco_25k/images"))?;
+/// // Use the ground truth as detections.
+/// let dts: Vec<Detection> = gt
+///     .get_anns()
+///     .into_iter()
+///     .map(|ann| Detection { image_id: ann.image_id, category_id: ann.category_id, bbox: Some(ann.bbox.clone()), segmentation: None, keypoints: None, score: 1.0 })
+///     .collect();
+/// let evaluation = eval::evaluate(&gt, &dts, &Params::new(&gt, IouType::Bbox))?;
+/// let summary = evaluation.summarize();
+/// println!("{summary}");
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// # Errors
+///
+/// Will return `Err` if:
+///   - A detection corresponds to an image that is not in the ground truth dataset.
+///   - A detection does not have the bounding box, segmentation or keypoints required by the evaluation type.
+///   - A segmentation cannot be converted to RLE, or has a size different from the one of its image.
+pub fn evaluate(
+    gt: &HashmapDataset,
+    dts: &[Detection],
+    params: &Params,
+) -> Result<Evaluation, CocoError> {
+    let cat_key = |cat_id: u32| if params.use_cats { cat_id } else { 0 };
+    let cat_ids = if params.use_cats {
+        params.cat_ids.clone()
+    } else {
+        vec![0]
+    };
+
+    let mut gts_per_key: HashMap<(u64, u32), Vec<&Annotation>> = HashMap::new();
+    for img_id in &params.img_ids {
+        for ann in gt.get_img_anns(*img_id)? {
+            if !params.use_cats || params.cat_ids.contains(&ann.category_id) {
+                gts_per_key
+                    .entry((ann.image_id, cat_key(ann.category_id)))
+                    .or_default()
+                    .push(ann);
+            }
+        }
+    }
+
+    let mut dts_per_key: HashMap<(u64, u32), Vec<&Detection>> = HashMap::new();
+    for dt in dts {
+        gt.get_img(dt.image_id)?;
+        if !params.use_cats || params.cat_ids.contains(&dt.category_id) {
+            dts_per_key
+                .entry((dt.image_id, cat_key(dt.category_id)))
+                .or_default()
+                .push(dt);
+        }
+    }
+
+    let max_det = params.max_dets.last().copied().unwrap_or(0);
+    // evals[k][a][i] contains the results for the kth category, ath area range and ith image.
+    let mut evals: Vec<Vec<Vec<Option<ImgEval>>>> = Vec::with_capacity(cat_ids.len());
+    for cat_id in &cat_ids {
+        let mut cat_evals: Vec<Vec<Option<ImgEval>>> = params
+            .area_rngs
+            .iter()
+            .map(|_| Vec::with_capacity(params.img_ids.len()))
+            .collect();
+        for img_id in &params.img_ids {
+            let gts = gts_per_key
+                .get(&(*img_id, *cat_id))
+                .map_or(&[][..], Vec::as_slice);
+            let mut dts = dts_per_key
+                .get(&(*img_id, *cat_id))
+                .cloned()
+                .unwrap_or_default();
+            dts.sort_by(|dt1, dt2| dt2.score.total_cmp(&dt1.score));
+            dts.truncate(max_det);
+
+            let (ious, dt_areas) = compute_iou(gt.get_img(*img_id)?, gts, &dts, params)?;
+            for (area_rng, area_evals) in params.area_rngs.iter().zip(cat_evals.iter_mut()) {
+                area_evals.push(evaluate_img(gts, &dts, &dt_areas, &ious, area_rng, params));
+            }
+        }
+        evals.push(cat_evals);
+    }
+
+    Ok(accumulate(&evals, params))
+}
+
+/// Compute the IoU between each detection and each ground truth annotation of an image.
+///
+/// Returns the IoU values indexed as `[detection][ground truth]`, and the area of each detection.
+fn compute_iou(
+    img: &Image,
+    gts: &[&Annotation],
+    dts: &[&Detection],
+    params: &Params,
+) -> Result<(Vec<Vec<f64>>, Vec<f64>), CocoError> {
+    match params.iou_type {
+        IouType::Bbox => {
+            let dt_bboxes = dts
+                .iter()
+                .map(|dt| {
+                    dt.bbox
+                        .as_ref()
+                        .ok_or(EvaluationError::MissingBbox(dt.image_id, dt.category_id))
+                })
+                .collect::<Result<Vec<&Bbox>, EvaluationError>>()?;
+            let ious = dt_bboxes
+                .iter()
+                .map(|dt| {
+                    gts.iter()
+                        .map(|gt| bbox_iou(dt, &gt.bbox, gt.iscrowd != 0))
+                        .collect()
+                })
+                .collect();
+            let dt_areas = dt_bboxes
+                .iter()
+                .map(|bbox| bbox.width * bbox.height)
+                .collect();
+            Ok((ious, dt_areas))
+        }
+        IouType::Segm => {
+            let gt_rles: Vec<Rle> = gts
+                .iter()
+                .map(|gt| segmentation_to_rle(&gt.segmentation, img))
+                .collect();
+            let dt_rles = dts
+                .iter()
+                .map(|dt| {
+                    dt.segmentation
+                        .as_ref()
+                        .map(|segmentation| segmentation_to_rle(segmentation, img))
+                        .ok_or(EvaluationError::MissingSegmentation(
+                            dt.image_id,
+                            dt.category_id,
+                        ))
+                })
+                .collect::<Result<Vec<Rle>, EvaluationError>>()?;
+            let ious = dt_rles
+                .iter()
+                .map(|dt| {
+                    gts.iter()
+                        .zip(&gt_rles)
+                        .map(|(gt, gt_rle)| mask::utils::iou(dt, gt_rle, gt.iscrowd != 0))
+                        .collect()
+                })
+                .collect::<Result<Vec<Vec<f64>>, MaskError>>()?;
+            let dt_areas = dt_rles.iter().map(|rle| f64::from(rle.area())).collect();
+            Ok((ious, dt_areas))
+        }
+        IouType::Keypoints => {
+            let dt_keypoints = dts
+                .iter()
+                .map(|dt| {
+                    dt.keypoints
+                        .as_deref()
+                        .ok_or(EvaluationError::MissingKeypoints(
+                            dt.image_id,
+                            dt.category_id,
+                        ))
+                })
+                .collect::<Result<Vec<&[f64]>, EvaluationError>>()?;
+            let ious = dt_keypoints
+                .iter()
+                .map(|dt| {
+                    gts.iter()
+                        .map(|gt| oks(dt, gt, &params.kpt_oks_sigmas))
+                        .collect()
+                })
+                .collect();
+            let dt_areas = dt_keypoints
+                .iter()
+                .map(|keypoints| {
+                    let bbox = object_detection::keypoints_bbox(keypoints);
+                    bbox.width * bbox.height
+                })
+                .collect();
+            Ok((ious, dt_areas))
+        }
+    }
+}
+
+/// Object keypoint similarity between detected keypoints and a ground truth annotation.
+///
+/// If the ground truth has no visible keypoint, the distance is measured to a box twice as large as its bounding box.
+/// See [pycocotools' computeOks](https://github.com/cocodataset/cocoapi/blob/master/PythonAPI/pycocotools/cocoeval.py#L192).
+// Do not use mul_add, it would give slightly different values from pycocotools.
+#[allow(clippy::cast_precision_loss, clippy::suboptimal_flops)]
+fn oks(dt: &[f64], gt: &Annotation, sigmas: &[f64]) -> f64 {
+    let gt_keypoints = gt.keypoints.as_deref().unwrap_or_default();
+    let visible = |k: usize| gt_keypoints.get(3 * k + 2).map_or(false, |v| *v > 0.0);
+    let nb_visible = (0..sigmas.len()).filter(|&k| visible(k)).count();
+
+    let bbox = &gt.bbox;
+    let (x0, x1) = (bbox.left - bbox.width, bbox.left + bbox.width * 2.0);
+    let (y0, y1) = (bbox.top - bbox.height, bbox.top + bbox.height * 2.0);
+
+    let mut total = 0.0;
+    let mut count = 0;
+    for (k, sigma) in sigmas.iter().enumerate() {
+        if nb_visible > 0 && !visible(k) {
+            continue;
+        }
+        let xd = dt.get(3 * k).copied().unwrap_or(0.0);
+        let yd = dt.get(3 * k + 1).copied().unwrap_or(0.0);
+        let (dx, dy) = if nb_visible > 0 {
+            (
+                xd - gt_keypoints.get(3 * k).copied().unwrap_or(0.0),
+                yd - gt_keypoints.get(3 * k + 1).copied().unwrap_or(0.0),
+            )
+        } else {
+            (
+                0f64.max(x0 - xd) + 0f64.max(xd - x1),
+                0f64.max(y0 - yd) + 0f64.max(yd - y1),
+            )
+        };
+        let var = (sigma * 2.0).powi(2);
+        let e = (dx * dx + dy * dy) / var / (gt.area + f64::EPSILON) / 2.0;
+        total += (-e).exp();
+        count += 1;
+    }
+    if count == 0 {
+        0.0
+    } else {
+        total / count as f64
+    }
+}
+
+/// Convert a segmentation to RLE, without going through a dense mask.
+///
+/// Polygons are rasterized the same way as pycocotools does.
+fn segmentation_to_rle(segmentation: &Segmentation, img: &Image) -> Rle {
+    match segmentation {
+        Segmentation::Rle(rle) => rle.clone(),
+        Segmentation::CocoRle(coco_rle) => Rle::from(coco_rle),
+        Segmentation::PolygonsRS(poly) => conversions::rle_from_poly(poly),
+        Segmentation::Polygons(poly) => conversions::rle_from_poly(&PolygonsRS {
+            size: vec![img.height, img.width],
+            counts: poly.clone(),
+        }),
+    }
+}
+
+/// IoU between two bounding boxes.
+///
+/// If the ground truth is a crowd annotation, the intersection is divided by the detection's area instead of the union.
+/// See [maskApi.c's bbIou](https://github.com/cocodataset/cocoapi/blob/master/common/maskApi.c#L117).
+fn bbox_iou(dt: &Bbox, gt: &Bbox, iscrowd: bool) -> f64 {
+    let width = (dt.left + dt.width).min(gt.left + gt.width) - dt.left.max(gt.left);
+    if width <= 0.0 {
+        return 0.0;
+    }
+    let height = (dt.top + dt.height).min(gt.top + gt.height) - dt.top.max(gt.top);
+    if height <= 0.0 {
+        return 0.0;
+    }
+    let intersection = width * height;
+    let dt_area = dt.width * dt.height;
+    let gt_area = gt.width * gt.height;
+    let union = if iscrowd {
+        dt_area
+    } else {
+        dt_area + gt_area - intersection
+    };
+    intersection / union
+}
+
+/// Number of labeled keypoints of an annotation, computed from its keypoints if `num_keypoints` is not set.
+fn num_keypoints(ann: &Annotation) -> u32 {
+    ann.num_keypoints.unwrap_or_else(|| {
+        let labeled = ann
+            .keypoints
+            .as_deref()
+            .unwrap_or_default()
+            .iter()
+            .skip(2)
+            .step_by(3)
+            .filter(|v| **v > 0.0)
+            .count();
+        u32::try_from(labeled).unwrap_or(u32::MAX)
+    })
+}
+
+/// Match the detections of an image with its ground truth annotations for a single category and area range.
+///
+/// `dts` must be sorted by decreasing score, `dt_areas` must contain their areas and `ious` must be indexed as `[detection][ground truth]`.
+/// Returns `None` if there is neither ground truth nor detection.
+fn evaluate_img(
+    gts: &[&Annotation],
+    dts: &[&Detection],
+    dt_areas: &[f64],
+    ious: &[Vec<f64>],
+    area_rng: &AreaRange,
+    params: &Params,
+) -> Option<ImgEval> {
+    if gts.is_empty() && dts.is_empty() {
+        return None;
+    }
+
+    let outside_rng = |area: f64| area < area_rng.min || area > area_rng.max;
+
+    // Sort the ground truths so that the ignored ones are last.
+    let gt_ignore: Vec<bool> = gts
+        .iter()
+        .map(|gt| {
+            gt.iscrowd != 0
+                || outside_rng(gt.area)
+                || (params.iou_type == IouType::Keypoints && num_keypoints(gt) == 0)
+        })
+        .collect();
+    let mut gt_order: Vec<usize> = (0..gts.len()).collect();
+    gt_order.sort_by_key(|&gind| gt_ignore[gind]);
+    let gt_ignore: Vec<bool> = gt_order.iter().map(|&gind| gt_ignore[gind]).collect();
+    let gt_crowd: Vec<bool> = gt_order
+        .iter()
+        .map(|&gind| gts[gind].iscrowd != 0)
+        .collect();
+
+    let nb_thrs = params.iou_thrs.len();
+    let mut gt_matched = vec![vec![false; gts.len()]; nb_thrs];
+    let mut dt_matches = vec![vec![false; dts.len()]; nb_thrs];
+    let mut dt_ignore = vec![vec![false; dts.len()]; nb_thrs];
+
+    if !gts.is_empty() {
+        for (tind, thr) in params.iou_thrs.iter().enumerate() {
+            for (dind, dt_ious) in ious.iter().enumerate() {
+                // Information about the best match so far (m=-1 -> unmatched).
+                let mut best_iou = thr.min(1.0 - 1e-10);
+                let mut best_match: Option<usize> = None;
+                for (gind, &orig_gind) in gt_order.iter().enumerate() {
+                    // If this gt is already matched, and not a crowd, continue.
+                    if gt_matched[tind][gind] && !gt_crowd[gind] {
+                        continue;
+                    }
+                    // If the detection is matched to a regular gt, and we are on an ignored gt, stop.
+                    if let Some(m) = best_match {
+                        if !gt_ignore[m] && gt_ignore[gind] {
+                            break;
+                        }
+                    }
+                    if dt_ious[orig_gind] < best_iou {
+                        continue;
+                    }
+                    best_iou = dt_ious[orig_gind];
+                    best_match = Some(gind);
+                }
+                if let Some(m) = best_match {
+                    dt_ignore[tind][dind] = gt_ignore[m];
+                    dt_matches[tind][dind] = true;
+                    gt_matched[tind][m] = true;
+                }
+            }
+        }
+    }
+
+    // Unmatched detections outside of the area range are ignored.
+    for (dind, dt_area) in dt_areas.iter().enumerate() {
+        if outside_rng(*dt_area) {
+            for tind in 0..nb_thrs {
+                dt_ignore[tind][dind] |= !dt_matches[tind][dind];
+            }
+        }
+    }
+
+    Some(ImgEval {
+        dt_scores: dts.iter().map(|dt| dt.score).collect(),
+        dt_matches,
+        dt_ignore,
+        gt_ignore,
+    })
+}
+
+/// Accumulate the per image evaluations into precision and recall arrays.
+#[allow(clippy::cast_precision_loss)]
+fn accumulate(evals: &[Vec<Vec<Option<ImgEval>>>], params: &Params) -> Evaluation {
+    let nb_thrs = params.iou_thrs.len();
+    let nb_rec_thrs = params.rec_thrs.len();
+    let nb_cats = evals.len();
+    let nb_area_rngs = params.area_rngs.len();
+    let nb_max_dets = params.max_dets.len();
+
+    let mut precision = Array5::from_elem(
+        (nb_thrs, nb_rec_thrs, nb_cats, nb_area_rngs, nb_max_dets),
+        -1.0,
+    );
+    let mut recall = Array4::from_elem((nb_thrs, nb_cats, nb_area_rngs, nb_max_dets), -1.0);
+    let mut scores = Array5::from_elem(
+        (nb_thrs, nb_rec_thrs, nb_cats, nb_area_rngs, nb_max_dets),
+        -1.0,
+    );
+
+    for (k, cat_evals) in evals.iter().enumerate() {
+        for (a, area_evals) in cat_evals.iter().enumerate() {
+            let img_evals: Vec<&ImgEval> = area_evals.iter().flatten().collect();
+            if img_evals.is_empty() {
+                continue;
+            }
+            let nb_gts = img_evals
+                .iter()
+                .flat_map(|e| &e.gt_ignore)
+                .filter(|ignore| !**ignore)
+                .count();
+            if nb_gts == 0 {
+                continue;
+            }
+
+            for (m, &max_det) in params.max_dets.iter().enumerate() {
+                // (image index, detection index) of all the detections kept, sorted by score.
+                let mut dt_idx: Vec<(usize, usize)> = img_evals
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(i, e)| (0..e.dt_scores.len().min(max_det)).map(move |d| (i, d)))
+                    .collect();
+                dt_idx.sort_by(|&(i1, d1), &(i2, d2)| {
+                    img_evals[i2].dt_scores[d2].total_cmp(&img_evals[i1].dt_scores[d1])
+                });
+                let dt_scores: Vec<f64> = dt_idx
+                    .iter()
+                    .map(|&(i, d)| img_evals[i].dt_scores[d])
+                    .collect();
+
+                for t in 0..nb_thrs {
+                    let mut tp_sum = 0.0;
+                    let mut fp_sum = 0.0;
+                    let mut rc: Vec<f64> = Vec::with_capacity(dt_idx.len());
+                    let mut pr: Vec<f64> = Vec::with_capacity(dt_idx.len());
+                    for &(i, d) in &dt_idx {
+                        if img_evals[i].dt_ignore[t][d] {
+                            // Ignored detections are neither true nor false positives.
+                        } else if img_evals[i].dt_matches[t][d] {
+                            tp_sum += 1.0;
+                        } else {
+                            fp_sum += 1.0;
+                        }
+                        rc.push(tp_sum / nb_gts as f64);
+                        pr.push(tp_sum / (fp_sum + tp_sum + f64::EPSILON));
+                    }
+
+                    recall[[t, k, a, m]] = rc.last().copied().unwrap_or(0.0);
+
+                    // Make the precision monotonically decreasing.
+                    for i in (1..pr.len()).rev() {
+                        if pr[i] > pr[i - 1] {
+                            pr[i - 1] = pr[i];
+                        }
+                    }
+
+                    for (r, rec_thr) in params.rec_thrs.iter().enumerate() {
+                        // Equivalent to numpy's searchsorted with side="left".
+                        let pi = rc.partition_point(|rc_value| rc_value < rec_thr);
+                        if pi < pr.len() {
+                            precision[[t, r, k, a, m]] = pr[pi];
+                            scores[[t, r, k, a, m]] = dt_scores[pi];
+                        } else {
+                            precision[[t, r, k, a, m]] = 0.0;
+                            scores[[t, r, k, a, m]] = 0.0;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    Evaluation {
+        params: params.clone(),
+        precision,
+        recall,
+        scores,
+    }
+}
+
+impl Evaluation {
+    /// Compute the standard summary metrics (AP@[.5:.95], AP50, AP75, AP small/medium/large, AR@1/10/100 and AR small/medium/large).
+    #[must_use]
+    pub fn summarize(&self) -> Summary {
+        let max_dets = |i: usize| self.params.max_dets.get(i).copied().unwrap_or(0);
+        let last_max_dets = self.params.max_dets.last().copied().unwrap_or(0);
+        let metrics = if self.params.iou_type == IouType::Keypoints {
+            vec![
+                self.metric(true, None, "all", last_max_dets),
+                self.metric(true, Some(0.5), "all", last_max_dets),
+                self.metric(true, Some(0.75), "all", last_max_dets),
+                self.metric(true, None, "medium", last_max_dets),
+                self.metric(true, None, "large", last_max_dets),
+                self.metric(false, None, "all", last_max_dets),
+                self.metric(false, Some(0.5), "all", last_max_dets),
+                self.metric(false, Some(0.75), "all", last_max_dets),
+                self.metric(false, None, "medium", last_max_dets),
+                self.metric(false, None, "large", last_max_dets),
+            ]
+        } else {
+            vec![
+                self.metric(true, None, "all", last_max_dets),
+                self.metric(true, Some(0.5), "all", max_dets(2)),
+                self.metric(true, Some(0.75), "all", max_dets(2)),
+                self.metric(true, None, "small", max_dets(2)),
+                self.metric(true, None, "medium", max_dets(2)),
+                self.metric(true, None, "large", max_dets(2)),
+                self.metric(false, None, "all", max_dets(0)),
+                self.metric(false, None, "all", max_dets(1)),
+                self.metric(false, None, "all", max_dets(2)),
+                self.metric(false, None, "small", max_dets(2)),
+                self.metric(false, None, "medium", max_dets(2)),
+                self.metric(false, None, "large", max_dets(2)),
+            ]
+        };
+        Summary {
+            metrics,
+            iou_thrs: (
+                self.params.iou_thrs.first().copied().unwrap_or(0.0),
+                self.params.iou_thrs.last().copied().unwrap_or(0.0),
+            ),
+        }
+    }
+
+    /// Average the precision (or recall) over the values matching the given IoU threshold, area range and maximum number of detections.
+    ///
+    /// Returns -1 if there is no valid value to average.
+    #[allow(clippy::cast_precision_loss)]
+    fn metric(&self, ap: bool, iou_thr: Option<f64>, area_rng: &str, max_dets: usize) -> Metric {
+        let a = self
+            .params
+            .area_rngs
+            .iter()
+            .position(|rng| rng.label == area_rng);
+        let m = self.params.max_dets.iter().position(|&det| det == max_dets);
+        let t = iou_thr.map(|iou_thr| {
+            self.params
+                .iou_thrs
+                .iter()
+                .position(|thr| (thr - iou_thr).abs() < 1e-9)
+        });
+
+        let value = match (a, m, t) {
+            (Some(a), Some(m), None | Some(Some(_))) => {
+                let values: Vec<f64> = if ap {
+                    self.precision
+                        .indexed_iter()
+                        .filter(|((ti, _, _, ai, mi), _)| {
+                            *ai == a && *mi == m && t.map_or(true, |t| t == Some(*ti))
+                        })
+                        .map(|(_, value)| *value)
+                        .filter(|value| *value > -1.0)
+                        .collect()
+                } else {
+                    self.recall
+                        .indexed_iter()
+                        .filter(|((ti, _, ai, mi), _)| {
+                            *ai == a && *mi == m && t.map_or(true, |t| t == Some(*ti))
+                        })
+                        .map(|(_, value)| *value)
+                        .filter(|value| *value > -1.0)
+                        .collect()
+                };
+                if values.is_empty() {
+                    -1.0
+                } else {
+                    values.iter().sum::<f64>() / values.len() as f64
+                }
+            }
+            _ => -1.0,
+        };
+
+        Metric {
+            ap,
+            iou_thr,
+            area_rng: area_rng.to_string(),
+            max_dets,
+            value,
+        }
+    }
+}
+
+impl Summary {
+    /// Return the values of the metrics, in the same order as pycocotools' `COCOeval.stats`.
+    #[must_use]
+    pub fn stats(&self) -> Vec<f64> {
+        self.metrics.iter().map(|metric| metric.value).collect()
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for metric in &self.metrics {
+            let (title, kind) = if metric.ap {
+                ("Average Precision", "(AP)")
+            } else {
+                ("Average Recall", "(AR)")
+            };
+            let iou = metric.iou_thr.map_or_else(
+                || format!("{:0.2}:{:0.2}", self.iou_thrs.0, self.iou_thrs.1),
+                |iou_thr| format!("{iou_thr:0.2}"),
+            );
+            writeln!(
+                f,
+                " {title:<18} {kind} @[ IoU={iou:<9} | area={:>6} | maxDets={:>3} ] = {:0.3}",
+                metric.area_rng, metric.max_dets, metric.value
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Evenly spaced numbers over the interval `[start, stop]`, computed the same way as numpy's linspace.
+// Do not use mul_add, it would give slightly different thresholds from numpy.
+#[allow(clippy::cast_precision_loss, clippy::suboptimal_flops)]
+fn linspace(start: f64, stop: f64, num: usize) -> Vec<f64> {
+    if num == 1 {
+        return vec![start];
+    }
+    let delta = (stop - start) / (num - 1) as f64;
+    let mut values: Vec<f64> = (0..num).map(|i| i as f64 * delta + start).collect();
+    if let Some(last) = values.last_mut() {
+        *last = stop;
+    }
+    values
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::float_cmp)]
+mod tests {
+    use super::*;
+    use crate::coco::object_detection::{Category, Dataset, ExtraFields};
+    use rstest::rstest;
+
+    fn bbox(left: f64, top: f64, width: f64, height: f64) -> Bbox {
+        Bbox {
+            left,
+            top,
+            width,
+            height,
+        }
+    }
+
+    fn gt_ann(id: u64, image_id: u64, bbox: Bbox, iscrowd: u32) -> Annotation {
+        Annotation {
+            id,
+            image_id,
+            category_id: 1,
+            segmentation: Segmentation::Polygons(vec![]),
+            area: bbox.width * bbox.height,
+            bbox,
+            iscrowd,
+            score: None,
+            keypoints: None,
+            num_keypoints: None,
+            extra: ExtraFields::default(),
+        }
+    }
+
+    fn dt(image_id: u64, bbox: Bbox, score: f64) -> Detection {
+        Detection {
+            image_id,
+            category_id: 1,
+            bbox: Some(bbox),
+            segmentation: None,
+            keypoints: None,
+            score,
+        }
+    }
+
+    fn gt_dataset(annotations: Vec<Annotation>) -> HashmapDataset {
+        let dataset = Dataset {
+            images: vec![
+                Image {
+                    id: 1,
+                    width: 200,
+                    height: 200,
+                    file_name: "1.jpg".to_string(),
+                    ..Default::default()
+                },
+                Image {
+                    id: 2,
+                    width: 200,
+                    height: 200,
+                    file_name: "2.jpg".to_string(),
+                    ..Default::default()
+                },
+            ],
+            annotations,
+            categories: vec![Category {
+                id: 1,
+                name: "object".to_string(),
+                supercategory: "object".to_string(),
+                keypoints: None,
+                skeleton: None,
+                extra: ExtraFields::default(),
+            }],
+            ..Default::default()
+        };
+        HashmapDataset::from_dataset(dataset, "N/A").unwrap()
+    }
+
+    fn assert_stats_eq(stats: &[f64], expected_stats: &[f64]) {
+        assert_eq!(stats.len(), expected_stats.len());
+        for (value, expected_value) in stats.iter().zip(expected_stats) {
+            assert!(
+                (value - expected_value).abs() < 1e-9,
+                "{stats:?} != {expected_stats:?}"
+            );
+        }
+    }
+
+    #[rstest]
+    #[case::identical(&bbox(0.0, 0.0, 10.0, 10.0), &bbox(0.0, 0.0, 10.0, 10.0), false, 1.0)]
+    #[case::half_overlap(&bbox(0.0, 0.0, 10.0, 10.0), &bbox(5.0, 0.0, 10.0, 10.0), false, 50.0 / 150.0)]
+    #[case::no_overlap(&bbox(0.0, 0.0, 10.0, 10.0), &bbox(10.0, 0.0, 10.0, 10.0), false, 0.0)]
+    #[case::crowd(&bbox(0.0, 0.0, 10.0, 10.0), &bbox(5.0, 0.0, 100.0, 100.0), true, 0.5)]
+    fn iou_between_bboxes(
+        #[case] dt: &Bbox,
+        #[case] gt: &Bbox,
+        #[case] iscrowd: bool,
+        #[case] expected_iou: f64,
+    ) {
+        assert!((bbox_iou(dt, gt, iscrowd) - expected_iou).abs() < 1e-12);
+    }
+
+    #[test]
+    fn default_thresholds() {
+        let thrs = linspace(0.5, 0.95, 10);
+        assert_eq!(thrs.len(), 10);
+        assert_eq!(thrs[0], 0.5);
+        assert!((thrs[5] - 0.75).abs() < 1e-12);
+        assert_eq!(thrs[9], 0.95);
+        assert_eq!(linspace(0.0, 1.0, 101).len(), 101);
+    }
+
+    #[test]
+    fn perfect_detections() {
+        let gt = gt_dataset(vec![
+            gt_ann(1, 1, bbox(10.0, 10.0, 50.0, 50.0), 0),
+            gt_ann(2, 2, bbox(20.0, 20.0, 120.0, 100.0), 0),
+        ]);
+        let dts = vec![
+            dt(1, bbox(10.0, 10.0, 50.0, 50.0), 0.9),
+            dt(2, bbox(20.0, 20.0, 120.0, 100.0), 0.8),
+        ];
+        let stats = evaluate(&gt, &dts, &Params::new(&gt, IouType::Bbox))
+            .unwrap()
+            .summarize()
+            .stats();
+        assert_stats_eq(
+            &stats,
+            &[1.0, 1.0, 1.0, -1.0, 1.0, 1.0, 1.0, 1.0, 1.0, -1.0, 1.0, 1.0],
+        );
+    }
+
+    #[test]
+    fn false_positive_with_higher_score() {
+        let gt = gt_dataset(vec![gt_ann(1, 1, bbox(10.0, 10.0, 50.0, 50.0), 0)]);
+        let dts = vec![
+            dt(1, bbox(100.0, 100.0, 50.0, 50.0), 0.9),
+            dt(1, bbox(10.0, 10.0, 50.0, 50.0), 0.8),
+        ];
+        let evaluation = evaluate(&gt, &dts, &Params::new(&gt, IouType::Bbox)).unwrap();
+        let stats = evaluation.summarize().stats();
+        // Precision is 0.5 at all the recall thresholds.
+        assert_stats_eq(&stats[..3], &[0.5, 0.5, 0.5]);
+        // With a single detection, only the false positive is kept.
+        assert_stats_eq(&stats[6..9], &[0.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn ground_truths_keep_dataset_order() {
+        // The first detection has an IoU of 0.5 with both ground truths, the last one in dataset order is matched.
+        let gt = gt_dataset(vec![
+            gt_ann(2, 1, bbox(0.0, 0.0, 40.0, 20.0), 0),
+            gt_ann(1, 1, bbox(0.0, 0.0, 80.0, 40.0), 0),
+        ]);
+        let dts = vec![
+            dt(1, bbox(0.0, 0.0, 40.0, 40.0), 0.9),
+            dt(1, bbox(0.0, 0.0, 40.0, 20.0), 0.8),
+        ];
+        let stats = evaluate(&gt, &dts, &Params::new(&gt, IouType::Bbox))
+            .unwrap()
+            .summarize()
+            .stats();
+        // Both detections are true positives at an IoU threshold of 0.5.
+        assert_stats_eq(&stats[1..2], &[1.0]);
+    }
+
+    #[test]
+    fn crowd_annotations_are_ignored() {
+        let gt = gt_dataset(vec![
+            gt_ann(1, 1, bbox(10.0, 10.0, 50.0, 50.0), 0),
+            gt_ann(2, 1, bbox(100.0, 100.0, 90.0, 90.0), 1),
+        ]);
+        // The second detection is inside the crowd region, it should neither be a true nor a false positive.
+        let dts = vec![
+            dt(1, bbox(120.0, 120.0, 20.0, 20.0), 0.9),
+            dt(1, bbox(10.0, 10.0, 50.0, 50.0), 0.8),
+        ];
+        let stats = evaluate(&gt, &dts, &Params::new(&gt, IouType::Bbox))
+            .unwrap()
+            .summarize()
+            .stats();
+        assert_stats_eq(&stats[..3], &[1.0, 1.0, 1.0]);
+        assert_stats_eq(&stats[8..9], &[1.0]);
+    }
+
+    #[test]
+    fn segmentation_evaluation() {
+        // 10x10 square in the top left corner of the 200x200 image.
+        let square = Rle {
+            size: vec![200, 200],
+            counts: std::iter::once(0)
+                .chain((0..9).flat_map(|_| [10, 190]))
+                .chain([10, 190 + 190 * 200])
+                .collect::<Vec<u32>>(),
+        };
+        let mut gt_square = gt_ann(1, 1, bbox(0.0, 0.0, 10.0, 10.0), 0);
+        gt_square.segmentation = Segmentation::Rle(square.clone());
+        let gt_poly = gt_ann(2, 2, bbox(20.0, 20.0, 40.0, 40.0), 0);
+        let mut gt = gt_dataset(vec![gt_square, gt_poly]);
+        gt.anns.get_mut(&2).unwrap().segmentation = Segmentation::PolygonsRS(PolygonsRS {
+            size: vec![200, 200],
+            counts: vec![vec![20.0, 20.0, 60.0, 20.0, 60.0, 60.0, 20.0, 60.0]],
+        });
+
+        let mut dt_square = dt(1, bbox(0.0, 0.0, 1.0, 1.0), 0.9);
+        dt_square.segmentation = Some(Segmentation::CocoRle(
+            crate::coco::object_detection::CocoRle::try_from(&square).unwrap(),
+        ));
+        // Only half of the ground truth polygon is covered.
+        let mut dt_poly = dt(2, bbox(0.0, 0.0, 1.0, 1.0), 0.8);
+        dt_poly.segmentation = Some(Segmentation::Polygons(vec![vec![
+            20.0, 20.0, 40.0, 20.0, 40.0, 60.0, 20.0, 60.0,
+        ]]));
+
+        let params = Params::new(&gt, IouType::Segm);
+        let stats = evaluate(&gt, &[dt_square.clone(), dt_poly], &params)
+            .unwrap()
+            .summarize()
+            .stats();
+        // At the IoU thresholds above 0.5, the precision drops to 0 for the recall thresholds above 0.5.
+        let half_ap = 51.0 / 101.0;
+        assert_stats_eq(&stats[..3], &[0.9f64.mul_add(half_ap, 0.1), 1.0, half_ap]);
+
+        dt_square.segmentation = None;
+        assert!(evaluate(&gt, &[dt_square], &params).is_err());
+    }
+
+    fn person_keypoints(visible: &[(f64, f64)]) -> Vec<f64> {
+        let mut keypoints = vec![0.0; 17 * 3];
+        for (k, (x, y)) in visible.iter().enumerate() {
+            keypoints[3 * k] = *x;
+            keypoints[3 * k + 1] = *y;
+            keypoints[3 * k + 2] = 2.0;
+        }
+        keypoints
+    }
+
+    #[test]
+    fn object_keypoint_similarity() {
+        let mut gt = gt_ann(1, 1, bbox(50.0, 50.0, 100.0, 100.0), 0);
+        gt.keypoints = Some(person_keypoints(&[(60.0, 60.0)]));
+        let sigmas = Params::new(&gt_dataset(vec![]), IouType::Keypoints).kpt_oks_sigmas;
+
+        // Only the visible keypoints of the ground truth are compared.
+        let dt = person_keypoints(&[(61.0, 60.0), (0.0, 0.0)]);
+        let expected = (-1.0 / (sigmas[0] * 2.0).powi(2) / (10000.0 + f64::EPSILON) / 2.0).exp();
+        assert!((oks(&dt, &gt, &sigmas) - expected).abs() < 1e-12);
+
+        // Without visible keypoint, the detections inside the enlarged bounding box are perfect matches.
+        gt.keypoints = Some(person_keypoints(&[]));
+        let dt: Vec<f64> = (0..17).flat_map(|_| [0.0, 0.0, 1.0]).collect();
+        assert_eq!(oks(&dt, &gt, &sigmas), 1.0);
+    }
+
+    #[test]
+    fn keypoints_evaluation() {
+        let mut gt_person = gt_ann(1, 1, bbox(50.0, 50.0, 100.0, 100.0), 0);
+        gt_person.keypoints = Some(person_keypoints(&[(60.0, 60.0), (80.0, 90.0)]));
+        gt_person.num_keypoints = Some(2);
+        // Annotations without labeled keypoints are ignored.
+        let mut gt_unlabeled = gt_ann(2, 2, bbox(50.0, 50.0, 100.0, 100.0), 0);
+        gt_unlabeled.keypoints = Some(person_keypoints(&[]));
+        let gt = gt_dataset(vec![gt_person, gt_unlabeled]);
+
+        let mut dt_person = dt(1, bbox(0.0, 0.0, 1.0, 1.0), 0.9);
+        dt_person.keypoints = Some(person_keypoints(&[(60.0, 60.0), (80.0, 90.0)]));
+        let mut dt_unlabeled = dt(2, bbox(0.0, 0.0, 1.0, 1.0), 0.95);
+        dt_unlabeled.keypoints = Some(person_keypoints(&[(100.0, 100.0)]));
+
+        let params = Params::new(&gt, IouType::Keypoints);
+        let summary = evaluate(&gt, &[dt_person.clone(), dt_unlabeled], &params)
+            .unwrap()
+            .summarize();
+        assert!(summary.metrics.iter().all(|metric| metric.max_dets == 20));
+        // The ground truth is a large object, there is nothing to evaluate for medium objects.
+        assert_stats_eq(
+            &summary.stats(),
+            &[1.0, 1.0, 1.0, -1.0, 1.0, 1.0, 1.0, 1.0, -1.0, 1.0],
+        );
+
+        dt_person.keypoints = None;
+        assert!(evaluate(&gt, &[dt_person], &params).is_err());
+    }
+
+    #[test]
+    fn detection_on_unknown_image() {
+        let gt = gt_dataset(vec![gt_ann(1, 1, bbox(10.0, 10.0, 50.0, 50.0), 0)]);
+        let dts = vec![dt(3, bbox(10.0, 10.0, 50.0, 50.0), 0.9)];
+        assert!(evaluate(&gt, &dts, &Params::new(&gt, IouType::Bbox)).is_err());
+    }
+}
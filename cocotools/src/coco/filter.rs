@@ -0,0 +1,419 @@
+//! Module to restrict a COCO dataset to some categories, images or annotations.
+use std::collections::HashSet;
+use std::hash::Hash;
+use std::str::FromStr;
+
+use crate::coco::object_detection::{Annotation, HashmapDataset};
+use crate::errors::FilterError;
+
+/// Condition on the annotations, see [`Filter::predicate`].
+pub type AnnotationPredicate = Box<dyn Fn(&Annotation) -> bool>;
+
+/// Which entries of a dataset to keep, see [`HashmapDataset::filter`].
+///
+/// An annotation is kept if it matches all the criteria that are given (the `None` criteria match all the annotations).
+///
+/// A filter can also be parsed from an expression made of clauses joined by `and`, each clause being a field,
+/// an operator and a value (or a list of values in brackets, with quotes around the values with spaces):
+/// - `category == car`, `category in [car, "traffic light"]`: the name of the annotation's category.
+/// - `category_id == 3`, `category_id in [1, 3]`.
+/// - `supercategory == vehicle`, `supercategory in [vehicle, animal]`.
+/// - `image_id == 42`, `image_id in [42, 43]`.
+/// - `area >= 32`, `area <= 1024`: the area of the annotation's bounding box (limits included).
+/// - `iscrowd == 0`.
+///
+/// ```
+/// # use std::str::FromStr;
+/// use cocotools::coco::filter::Filter;
+///
+/// let filter = Filter::from_str(r#"category in [car, "traffic light"] and area >= 1024 and iscrowd == 0"#)?;
+/// assert_eq!(filter.min_area, Some(1024.0));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Default)]
+pub struct Filter {
+    pub category_ids: Option<HashSet<u32>>,
+    pub category_names: Option<HashSet<String>>,
+    pub supercategories: Option<HashSet<String>>,
+    /// The images not in this set are removed, with their annotations.
+    pub image_ids: Option<HashSet<u64>>,
+    /// Minimum area of the annotations' bounding box.
+    pub min_area: Option<f64>,
+    /// Maximum area of the annotations' bounding box.
+    pub max_area: Option<f64>,
+    pub iscrowd: Option<u32>,
+    /// Arbitrary condition on the annotations.
+    pub predicate: Option<AnnotationPredicate>,
+    /// Remove the images which do not have any annotation left after filtering.
+    pub drop_empty_images: bool,
+    /// Remove the categories which do not have any annotation left after filtering.
+    pub prune_categories: bool,
+}
+
+impl Filter {
+    /// Returns true if the annotation of the dataset matches all the criteria of the filter, besides the image ids.
+    fn matches(&self, dataset: &HashmapDataset, ann: &Annotation) -> bool {
+        let cat = dataset.get_cat(ann.category_id).ok();
+        let area = ann.bbox.width * ann.bbox.height;
+        self.category_ids
+            .as_ref()
+            .map_or(true, |ids| ids.contains(&ann.category_id))
+            && self.category_names.as_ref().map_or(true, |names| {
+                cat.map_or(false, |cat| names.contains(&cat.name))
+            })
+            && self.supercategories.as_ref().map_or(true, |names| {
+                cat.map_or(false, |cat| names.contains(&cat.supercategory))
+            })
+            && self.min_area.map_or(true, |min_area| area >= min_area)
+            && self.max_area.map_or(true, |max_area| area <= max_area)
+            && self.iscrowd.map_or(true, |iscrowd| ann.iscrowd == iscrowd)
+            && self
+                .predicate
+                .as_ref()
+                .map_or(true, |predicate| predicate(ann))
+    }
+}
+
+impl HashmapDataset {
+    /// Return a new dataset with only the entries kept by the filter.
+    ///
+    /// The entries keep their ids and order. Unless [`Filter::drop_empty_images`] or [`Filter::prune_categories`] are set,
+    /// all the images (in [`Filter::image_ids`] if given) and all the categories are kept, even without annotations.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::path::PathBuf;
+    /// use cocotools::COCO;
+    /// use cocotools::coco::filter::Filter;
+    ///
+    /// let dataset = COCO::new(
+    ///     PathBuf::from("../data_samples/coco_25k/annotations.json"),
+    ///     PathBuf::from("../data_samples/coco_25k/images"),
+    /// )?;
+    /// let tall_objects = dataset.filter(&Filter {
+    ///     predicate: Some(Box::new(|ann| ann.bbox.height > 2.0 * ann.bbox.width)),
+    ///     drop_empty_images: true,
+    ///     prune_categories: true,
+    ///     ..Default::default()
+    /// });
+    /// assert!(tall_objects.get_anns().len() < dataset.get_anns().len());
+    /// assert!(tall_objects.get_cats().iter().any(|cat| cat.name == "person"));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[must_use]
+    pub fn filter(&self, filter: &Filter) -> Self {
+        let mut dataset = self.clone();
+        if let Some(image_ids) = &filter.image_ids {
+            dataset.retain_imgs(|img| image_ids.contains(&img.id));
+        }
+        dataset.retain_anns(|ann| filter.matches(self, ann));
+
+        if filter.drop_empty_images {
+            let img_ids: HashSet<u64> = dataset.get_anns().iter().map(|ann| ann.image_id).collect();
+            dataset.retain_imgs(|img| img_ids.contains(&img.id));
+        }
+        if filter.prune_categories {
+            let cat_ids: HashSet<u32> = dataset
+                .get_anns()
+                .iter()
+                .map(|ann| ann.category_id)
+                .collect();
+            dataset.retain_cats(|cat| cat_ids.contains(&cat.id));
+        }
+        dataset
+    }
+}
+
+impl FromStr for Filter {
+    type Err = FilterError;
+
+    fn from_str(expression: &str) -> Result<Self, Self::Err> {
+        let invalid = |msg: String| FilterError::InvalidExpression(expression.to_string(), msg);
+        let mut tokens = tokenize(expression).map_err(invalid)?.into_iter();
+        let mut filter = Self::default();
+
+        loop {
+            let field = match tokens.next() {
+                Some(Token::Value(field)) => field,
+                token => return Err(invalid(format!("expected a field, got {token:?}."))),
+            };
+            let operator = match tokens.next() {
+                Some(Token::Value(operator) | Token::Symbol(operator)) => operator,
+                token => return Err(invalid(format!("expected an operator, got {token:?}."))),
+            };
+            let values = match operator.as_str() {
+                "in" => parse_list(&mut tokens).map_err(invalid)?,
+                "==" | ">=" | "<=" => match tokens.next() {
+                    Some(Token::Value(value) | Token::Quoted(value)) => vec![value],
+                    token => return Err(invalid(format!("expected a value, got {token:?}."))),
+                },
+                _ => return Err(invalid(format!("unknown operator `{operator}`."))),
+            };
+
+            match (field.as_str(), operator.as_str()) {
+                ("category", "in" | "==") => intersect(&mut filter.category_names, values),
+                ("category_id", "in" | "==") => {
+                    intersect(
+                        &mut filter.category_ids,
+                        parse_values(&values).map_err(invalid)?,
+                    );
+                }
+                ("supercategory", "in" | "==") => intersect(&mut filter.supercategories, values),
+                ("image_id", "in" | "==") => {
+                    intersect(
+                        &mut filter.image_ids,
+                        parse_values(&values).map_err(invalid)?,
+                    );
+                }
+                // Repeated bounds narrow the range, like the other clauses.
+                ("area", ">=") => {
+                    let min_area: f64 = parse_value(&values[0]).map_err(invalid)?;
+                    filter.min_area =
+                        Some(filter.min_area.map_or(min_area, |area| area.max(min_area)));
+                }
+                ("area", "<=") => {
+                    let max_area: f64 = parse_value(&values[0]).map_err(invalid)?;
+                    filter.max_area =
+                        Some(filter.max_area.map_or(max_area, |area| area.min(max_area)));
+                }
+                ("iscrowd", "==") => {
+                    filter.iscrowd = Some(parse_value(&values[0]).map_err(invalid)?)
+                }
+                _ => {
+                    return Err(invalid(format!(
+                        "the operator `{operator}` cannot be used with the field `{field}`."
+                    )))
+                }
+            }
+
+            match tokens.next() {
+                None => return Ok(filter),
+                Some(Token::Value(and)) if and.eq_ignore_ascii_case("and") => {}
+                token => return Err(invalid(format!("expected `and`, got {token:?}."))),
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Token {
+    /// A field, a keyword (`in`, `and`) or a value without quotes.
+    Value(String),
+    /// A value in quotes.
+    Quoted(String),
+    /// Brackets, commas and comparison operators.
+    Symbol(String),
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = expression.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '[' | ']' | ',' => tokens.push(Token::Symbol(c.to_string())),
+            '=' | '<' | '>' => {
+                if chars.next_if_eq(&'=').is_none() {
+                    return Err(format!(
+                        "unknown operator `{c}`, the comparison operators are `==`, `>=` and `<=`."
+                    ));
+                }
+                tokens.push(Token::Symbol(format!("{c}=")));
+            }
+            '"' | '\'' => {
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some(next) if next == c => break,
+                        Some(next) => value.push(next),
+                        None => return Err(format!("unterminated quote `{c}{value}`.")),
+                    }
+                }
+                tokens.push(Token::Quoted(value));
+            }
+            _ => {
+                let mut value = c.to_string();
+                while let Some(next) =
+                    chars.next_if(|next| !next.is_whitespace() && !"[],=<>\"'".contains(*next))
+                {
+                    value.push(next);
+                }
+                tokens.push(Token::Value(value));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// Parse a list of values in brackets, such as `[car, "traffic light"]`.
+fn parse_list<I: Iterator<Item = Token>>(tokens: &mut I) -> Result<Vec<String>, String> {
+    if tokens.next() != Some(Token::Symbol("[".to_string())) {
+        return Err("expected a list of values in brackets after `in`.".to_string());
+    }
+    let mut values = Vec::new();
+    loop {
+        match tokens.next() {
+            Some(Token::Value(value) | Token::Quoted(value)) => values.push(value),
+            Some(Token::Symbol(symbol)) if symbol == "]" && values.is_empty() => return Ok(values),
+            token => return Err(format!("expected a value in the list, got {token:?}.")),
+        }
+        match tokens.next() {
+            Some(Token::Symbol(symbol)) if symbol == "," => {}
+            Some(Token::Symbol(symbol)) if symbol == "]" => return Ok(values),
+            token => return Err(format!("expected `,` or `]` in the list, got {token:?}.")),
+        }
+    }
+}
+
+fn parse_value<T: FromStr>(value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("`{value}` is not a valid number."))
+}
+
+fn parse_values<T: FromStr>(values: &[String]) -> Result<Vec<T>, String> {
+    values.iter().map(|value| parse_value(value)).collect()
+}
+
+/// Restrict the set to the given values, or set it to these values if there was no restriction yet.
+fn intersect<T: Eq + Hash>(set: &mut Option<HashSet<T>>, values: Vec<T>) {
+    let values: HashSet<T> = values.into_iter().collect();
+    *set = Some(match set.take() {
+        Some(set) => set
+            .into_iter()
+            .filter(|value| values.contains(value))
+            .collect(),
+        None => values,
+    });
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+    use crate::coco::fixtures;
+    use crate::coco::object_detection::{Bbox, Category, Dataset, Image};
+
+    fn cat(id: u32, name: &str, supercategory: &str) -> Category {
+        Category {
+            supercategory: supercategory.to_string(),
+            ..fixtures::cat(id, name)
+        }
+    }
+
+    fn ann(id: u64, image_id: u64, category_id: u32, size: f64, iscrowd: u32) -> Annotation {
+        Annotation {
+            area: size * size,
+            bbox: Bbox {
+                left: 0.0,
+                top: 0.0,
+                width: size,
+                height: size,
+            },
+            iscrowd,
+            ..fixtures::ann(id, image_id, category_id)
+        }
+    }
+
+    fn dataset() -> HashmapDataset {
+        let dataset = Dataset {
+            images: (1..=3)
+                .map(|id| Image {
+                    width: 100,
+                    height: 100,
+                    ..fixtures::img(id, &format!("{id}.jpg"))
+                })
+                .collect(),
+            annotations: vec![
+                ann(1, 1, 1, 10.0, 0),
+                ann(2, 1, 2, 50.0, 0),
+                ann(3, 2, 3, 20.0, 1),
+                ann(4, 2, 1, 100.0, 0),
+            ],
+            categories: vec![
+                cat(1, "car", "vehicle"),
+                cat(2, "traffic light", "outdoor"),
+                cat(3, "bus", "vehicle"),
+            ],
+            ..Default::default()
+        };
+        HashmapDataset::from_dataset(dataset, "N/A").unwrap()
+    }
+
+    fn ids(dataset: &HashmapDataset) -> (Vec<u64>, Vec<u64>, Vec<u32>) {
+        (
+            dataset.get_imgs().iter().map(|img| img.id).collect(),
+            dataset.get_anns().iter().map(|ann| ann.id).collect(),
+            dataset.get_cats().iter().map(|cat| cat.id).collect(),
+        )
+    }
+
+    #[rstest]
+    #[case::category_names(r#"category in [car, "traffic light"]"#, vec![1, 2, 4])]
+    #[case::category_name("category == 'traffic light'", vec![2])]
+    #[case::category_ids("category_id in [2, 3]", vec![2, 3])]
+    #[case::supercategory("supercategory == vehicle", vec![1, 3, 4])]
+    #[case::image_ids("image_id in [2]", vec![3, 4])]
+    #[case::area("area >= 100 and area<=2500", vec![1, 2, 3])]
+    #[case::repeated_area("area >= 400 and area >= 10 and area <= 2500 and area <= 10000", vec![2, 3])]
+    #[case::iscrowd("iscrowd == 1", vec![3])]
+    #[case::intersection("category_id in [1, 2] AND category_id == 1", vec![1, 4])]
+    #[case::combined("supercategory == vehicle and iscrowd == 0 and area <= 100", vec![1])]
+    fn filter_expression(#[case] expression: &str, #[case] expected_ann_ids: Vec<u64>) {
+        let filter = Filter::from_str(expression).unwrap();
+        assert_eq!(ids(&dataset().filter(&filter)).1, expected_ann_ids);
+    }
+
+    #[rstest]
+    #[case::unknown_field("color == red")]
+    #[case::unknown_operator("area > 10")]
+    #[case::wrong_operator("category >= car")]
+    #[case::missing_value("iscrowd ==")]
+    #[case::not_a_number("category_id == car")]
+    #[case::unclosed_list("category in [car, bus")]
+    #[case::unterminated_quote(r#"category == "car and iscrowd == 0"#)]
+    #[case::missing_and("iscrowd == 0 area >= 10")]
+    fn invalid_expression(#[case] expression: &str) {
+        assert!(matches!(
+            Filter::from_str(expression),
+            Err(FilterError::InvalidExpression(_, _))
+        ));
+    }
+
+    #[rstest]
+    #[case::keep_all(false, false, (vec![1, 2, 3], vec![1, 4], vec![1, 2, 3]))]
+    #[case::drop_empty_images(true, false, (vec![1, 2], vec![1, 4], vec![1, 2, 3]))]
+    #[case::prune_categories(false, true, (vec![1, 2, 3], vec![1, 4], vec![1]))]
+    fn filter_options(
+        #[case] drop_empty_images: bool,
+        #[case] prune_categories: bool,
+        #[case] expected: (Vec<u64>, Vec<u64>, Vec<u32>),
+    ) {
+        let filter = Filter {
+            category_names: Some(HashSet::from(["car".to_string()])),
+            drop_empty_images,
+            prune_categories,
+            ..Default::default()
+        };
+        let filtered = dataset().filter(&filter);
+        assert_eq!(ids(&filtered), expected);
+        for img in filtered.get_imgs() {
+            assert!(filtered.get_img_anns(img.id).is_ok());
+        }
+    }
+
+    #[test]
+    fn filter_predicate() {
+        let filter = Filter {
+            predicate: Some(Box::new(|ann| ann.id % 2 == 0)),
+            image_ids: Some(HashSet::from([1])),
+            ..Default::default()
+        };
+        let filtered = dataset().filter(&filter);
+        assert_eq!(ids(&filtered), (vec![1], vec![2], vec![1, 2, 3]));
+        assert_eq!(filtered.get_img_anns(1).unwrap().len(), 1);
+    }
+}
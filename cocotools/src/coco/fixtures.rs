@@ -0,0 +1,51 @@
+//! Constructors of the dataset entries shared by the tests.
+//!
+//! The images are 10x10 and the annotations cover the whole image, the tests change the fields they need with the struct update syntax.
+use crate::coco::object_detection::{
+    Annotation, Bbox, Category, ExtraFields, Image, Rle, Segmentation,
+};
+
+pub(crate) fn cat(id: u32, name: &str) -> Category {
+    Category {
+        id,
+        name: name.to_string(),
+        supercategory: String::new(),
+        keypoints: None,
+        skeleton: None,
+        extra: ExtraFields::default(),
+    }
+}
+
+pub(crate) fn img(id: u64, file_name: &str) -> Image {
+    Image {
+        id,
+        width: 10,
+        height: 10,
+        file_name: file_name.to_string(),
+        ..Default::default()
+    }
+}
+
+pub(crate) fn ann(id: u64, image_id: u64, category_id: u32) -> Annotation {
+    Annotation {
+        id,
+        image_id,
+        category_id,
+        segmentation: Segmentation::Rle(Rle {
+            size: vec![10, 10],
+            counts: vec![0, 100],
+        }),
+        area: 100.0,
+        bbox: Bbox {
+            left: 0.0,
+            top: 0.0,
+            width: 10.0,
+            height: 10.0,
+        },
+        iscrowd: 0,
+        score: None,
+        keypoints: None,
+        num_keypoints: None,
+        extra: ExtraFields::default(),
+    }
+}
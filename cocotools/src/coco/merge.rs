@@ -0,0 +1,378 @@
+//! Module to merge several COCO datasets into one.
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use clap::ValueEnum;
+
+use crate::coco::object_detection::{
+    Annotation, Category, Dataset, HashmapDataset, Image, License,
+};
+use crate::errors::MergeError;
+
+/// What to do when the same image (the same path) appears several times in the datasets.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum DuplicateImages {
+    /// Keep the first image entry, and put the annotations of all the entries on it.
+    Merge,
+    /// Keep the first image entry and its annotations, the annotations of the other entries are dropped.
+    KeepFirst,
+    /// Return an error.
+    Error,
+}
+
+/// What was changed when merging the datasets.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct MergeReport {
+    /// Number of images which were given a new id because their id was already used.
+    pub reassigned_image_ids: usize,
+    /// Number of annotations which were given a new id because their id was already used.
+    pub reassigned_annotation_ids: usize,
+    /// Number of categories which were given a new id because their id was already used by another category.
+    pub reassigned_category_ids: usize,
+    /// Paths of the images which appeared several times, relative to the image folder of the merged dataset.
+    pub duplicate_images: Vec<String>,
+}
+
+impl fmt::Display for MergeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for file_name in &self.duplicate_images {
+            writeln!(f, "duplicate image: {file_name}")?;
+        }
+        write!(
+            f,
+            "{} duplicate image(s), reassigned ids: {} image(s), {} annotation(s), {} category(ies)",
+            self.duplicate_images.len(),
+            self.reassigned_image_ids,
+            self.reassigned_annotation_ids,
+            self.reassigned_category_ids
+        )
+    }
+}
+
+/// Give each entry its own id, or a new id after the largest id of all the datasets if its id is already used.
+struct IdAllocator<T> {
+    used: HashSet<T>,
+    next: T,
+    reassigned: usize,
+}
+
+impl<T: Copy + Eq + std::hash::Hash + std::ops::AddAssign + From<u8>> IdAllocator<T> {
+    fn new(max_id: T) -> Self {
+        let mut next = max_id;
+        next += T::from(1);
+        Self {
+            used: HashSet::new(),
+            next,
+            reassigned: 0,
+        }
+    }
+
+    fn allocate(&mut self, id: T) -> T {
+        if self.used.insert(id) {
+            return id;
+        }
+        let id = self.next;
+        self.next += T::from(1);
+        self.used.insert(id);
+        self.reassigned += 1;
+        id
+    }
+}
+
+/// Merge several datasets into one.
+///
+/// - The entries keep their ids when possible, the images, annotations and categories whose id is already used by a previous entry are given new ids.
+/// - The categories are unified by name, after replacing the names found in `synonyms` (see [`load_synonyms`]).
+///   The first category with a given name is kept, and the annotations of the other datasets are moved to it.
+/// - The images do not need to be in the same folder: the image folder of the merged dataset is the deepest folder
+///   containing the image folders of all the datasets, and the `file_name` of each image becomes its path relative to that folder.
+/// - An image is the same as a previous one if it has the same path relative to that folder, in which case `duplicates` decides what to do with it.
+///   Images with the same `file_name` in different image folders are therefore different images.
+/// - The `info` and extra fields are the ones of the first dataset, and the licenses are all the licenses with different ids.
+///
+/// ## Args
+/// - `datasets`: The datasets to merge.
+/// - `synonyms`: Mapping from category names to the name they should be merged into, for example `{"automobile": "car"}`.
+/// - `duplicates`: What to do when an image appears several times.
+///
+/// # Errors
+///
+/// Will return `Err` if an image appears several times and `duplicates` is [`DuplicateImages::Error`],
+/// or if an annotation has a category which is not in its dataset.
+pub fn merge(
+    datasets: &[HashmapDataset],
+    synonyms: &HashMap<String, String>,
+    duplicates: DuplicateImages,
+) -> Result<(HashmapDataset, MergeReport), MergeError> {
+    let image_folder = common_folder(datasets.iter().map(|dataset| &dataset.image_folder));
+    let mut merged = Dataset {
+        info: datasets
+            .first()
+            .and_then(|dataset| dataset.get_info().cloned()),
+        extra: datasets
+            .first()
+            .map(|dataset| dataset.get_extra().clone())
+            .unwrap_or_default(),
+        ..Default::default()
+    };
+    let mut report = MergeReport::default();
+
+    let mut img_ids = IdAllocator::new(max_id(datasets, |dataset| {
+        dataset.get_imgs().iter().map(|img| img.id).collect()
+    }));
+    let mut ann_ids = IdAllocator::new(max_id(datasets, |dataset| {
+        dataset.get_anns().iter().map(|ann| ann.id).collect()
+    }));
+    let mut cat_ids = IdAllocator::new(max_id(datasets, |dataset| {
+        dataset.get_cats().iter().map(|cat| cat.id).collect()
+    }));
+    // Index of each image and category of the merged dataset, using the merged file name and the category name as key.
+    let mut merged_imgs: HashMap<String, u64> = HashMap::new();
+    let mut merged_cats: HashMap<String, u32> = HashMap::new();
+    let mut license_ids: HashSet<u32> = HashSet::new();
+
+    for (dataset_idx, dataset) in datasets.iter().enumerate() {
+        let mut cat_mapping: HashMap<u32, u32> = HashMap::new();
+        for cat in dataset.get_cats() {
+            let name = synonyms.get(&cat.name).unwrap_or(&cat.name);
+            let merged_id = match merged_cats.get(name) {
+                Some(merged_id) => *merged_id,
+                None => {
+                    let merged_id = cat_ids.allocate(cat.id);
+                    merged_cats.insert(name.clone(), merged_id);
+                    merged.categories.push(Category {
+                        id: merged_id,
+                        name: name.clone(),
+                        ..cat.clone()
+                    });
+                    merged_id
+                }
+            };
+            cat_mapping.insert(cat.id, merged_id);
+        }
+
+        // `None` for the duplicated images whose annotations are dropped.
+        let mut img_mapping: HashMap<u64, Option<u64>> = HashMap::new();
+        for img in dataset.get_imgs() {
+            let img_path = dataset.image_folder.join(&img.file_name);
+            let file_name = img_path
+                .strip_prefix(&image_folder)
+                .unwrap_or(&img_path)
+                .to_string_lossy()
+                .into_owned();
+            let merged_id = match merged_imgs.get(&file_name) {
+                Some(merged_id) => {
+                    report.duplicate_images.push(file_name.clone());
+                    match duplicates {
+                        DuplicateImages::Merge => Some(*merged_id),
+                        DuplicateImages::KeepFirst => None,
+                        DuplicateImages::Error => {
+                            return Err(MergeError::DuplicateImage(file_name))
+                        }
+                    }
+                }
+                None => {
+                    let merged_id = img_ids.allocate(img.id);
+                    merged_imgs.insert(file_name.clone(), merged_id);
+                    merged.images.push(Image {
+                        id: merged_id,
+                        file_name,
+                        ..img.clone()
+                    });
+                    Some(merged_id)
+                }
+            };
+            img_mapping.insert(img.id, merged_id);
+        }
+
+        for ann in dataset.get_anns() {
+            let image_id = match img_mapping.get(&ann.image_id) {
+                Some(Some(image_id)) => *image_id,
+                _ => continue,
+            };
+            let category_id = *cat_mapping
+                .get(&ann.category_id)
+                .ok_or(MergeError::MissingCategory(ann.id, dataset_idx))?;
+            merged.annotations.push(Annotation {
+                id: ann_ids.allocate(ann.id),
+                image_id,
+                category_id,
+                ..ann.clone()
+            });
+        }
+
+        for license in dataset.get_licenses().unwrap_or_default() {
+            if license_ids.insert(license.id) {
+                merged
+                    .licenses
+                    .get_or_insert_with(Vec::<License>::new)
+                    .push(license.clone());
+            }
+        }
+    }
+
+    report.reassigned_image_ids = img_ids.reassigned;
+    report.reassigned_annotation_ids = ann_ids.reassigned;
+    report.reassigned_category_ids = cat_ids.reassigned;
+    Ok((HashmapDataset::from_dataset(merged, image_folder)?, report))
+}
+
+/// Load a json file mapping category names to the name they should be merged into, for example `{"automobile": "car", "bike": "bicycle"}`.
+///
+/// # Errors
+///
+/// Will return `Err` if the file cannot be read or is not a json object with string values.
+pub fn load_synonyms<P: AsRef<Path>>(path: P) -> Result<HashMap<String, String>, MergeError> {
+    let path = path.as_ref();
+    let content =
+        fs::read_to_string(path).map_err(|err| MergeError::Read(err, path.to_path_buf()))?;
+    serde_json::from_str(&content).map_err(|err| MergeError::Json(err, path.to_path_buf()))
+}
+
+/// Largest id of all the datasets, with `ids` giving the ids of a dataset.
+fn max_id<T: Ord + Default, F: Fn(&HashmapDataset) -> Vec<T>>(
+    datasets: &[HashmapDataset],
+    ids: F,
+) -> T {
+    datasets.iter().flat_map(ids).max().unwrap_or_default()
+}
+
+/// Deepest folder containing all the given folders.
+fn common_folder<'a, I: Iterator<Item = &'a PathBuf>>(folders: I) -> PathBuf {
+    let mut common: Option<PathBuf> = None;
+    for folder in folders {
+        common = Some(match common {
+            None => folder.clone(),
+            Some(common) => common
+                .components()
+                .zip(folder.components())
+                .take_while(|(a, b)| a == b)
+                .map(|(a, _)| a)
+                .collect(),
+        });
+    }
+    common.unwrap_or_default()
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+    use crate::coco::fixtures::{ann, cat, img};
+
+    /// Two vendor batches, with the same ids, a car category named differently and an image file name used by both vendors.
+    fn datasets() -> Vec<HashmapDataset> {
+        let vendor_a = Dataset {
+            images: vec![img(1, "a.jpg"), img(2, "b.jpg")],
+            annotations: vec![ann(1, 1, 1), ann(2, 2, 2)],
+            categories: vec![cat(1, "car"), cat(2, "person")],
+            ..Default::default()
+        };
+        let vendor_b = Dataset {
+            images: vec![img(1, "c.jpg"), img(2, "b.jpg")],
+            annotations: vec![ann(1, 1, 1), ann(2, 2, 2), ann(3, 2, 3)],
+            categories: vec![cat(1, "person"), cat(2, "automobile"), cat(3, "bike")],
+            ..Default::default()
+        };
+        vec![
+            HashmapDataset::from_dataset(vendor_a, "data/vendor_a/images").unwrap(),
+            HashmapDataset::from_dataset(vendor_b, "data/vendor_b").unwrap(),
+        ]
+    }
+
+    fn synonyms() -> HashMap<String, String> {
+        HashMap::from([("automobile".to_string(), "car".to_string())])
+    }
+
+    #[test]
+    fn merge_datasets() {
+        let (merged, report) = merge(&datasets(), &synonyms(), DuplicateImages::Merge).unwrap();
+
+        assert_eq!(merged.image_folder, PathBuf::from("data"));
+        let imgs: Vec<(u64, &str)> = merged
+            .get_imgs()
+            .iter()
+            .map(|img| (img.id, img.file_name.as_str()))
+            .collect();
+        assert_eq!(
+            imgs,
+            vec![
+                (1, "vendor_a/images/a.jpg"),
+                (2, "vendor_a/images/b.jpg"),
+                (3, "vendor_b/c.jpg"),
+                (4, "vendor_b/b.jpg")
+            ]
+        );
+        let cats: Vec<(u32, &str)> = merged
+            .get_cats()
+            .iter()
+            .map(|cat| (cat.id, cat.name.as_str()))
+            .collect();
+        assert_eq!(cats, vec![(1, "car"), (2, "person"), (3, "bike")]);
+        let anns: Vec<(u64, u64, u32)> = merged
+            .get_anns()
+            .iter()
+            .map(|ann| (ann.id, ann.image_id, ann.category_id))
+            .collect();
+        assert_eq!(
+            anns,
+            vec![(1, 1, 1), (2, 2, 2), (4, 3, 2), (5, 4, 1), (3, 4, 3)]
+        );
+        assert_eq!(
+            report,
+            MergeReport {
+                reassigned_image_ids: 2,
+                reassigned_annotation_ids: 2,
+                reassigned_category_ids: 0,
+                duplicate_images: Vec::new(),
+            }
+        );
+    }
+
+    #[rstest]
+    #[case::merge(DuplicateImages::Merge, Some(3))]
+    #[case::keep_first(DuplicateImages::KeepFirst, Some(2))]
+    #[case::error(DuplicateImages::Error, None)]
+    fn duplicate_images(#[case] duplicates: DuplicateImages, #[case] nb_anns: Option<usize>) {
+        // The same image as the b.jpg of vendor A, referenced from the parent folder.
+        let relabeled = Dataset {
+            images: vec![img(1, "vendor_a/images/b.jpg")],
+            annotations: vec![ann(1, 1, 1)],
+            categories: vec![cat(1, "car")],
+            ..Default::default()
+        };
+        let datasets = vec![
+            datasets().remove(0),
+            HashmapDataset::from_dataset(relabeled, "data").unwrap(),
+        ];
+        let merged = merge(&datasets, &synonyms(), duplicates);
+        match nb_anns {
+            Some(nb_anns) => {
+                let (merged, report) = merged.unwrap();
+                assert_eq!(merged.get_imgs().len(), 2);
+                assert_eq!(merged.get_anns().len(), nb_anns);
+                assert_eq!(report.duplicate_images, vec!["vendor_a/images/b.jpg"]);
+            }
+            None => assert!(matches!(merged, Err(MergeError::DuplicateImage(_)))),
+        }
+    }
+
+    #[test]
+    fn same_image_folder() {
+        let dataset = datasets().remove(0);
+        let (merged, report) = merge(
+            &[dataset.clone(), dataset.clone()],
+            &HashMap::new(),
+            DuplicateImages::Merge,
+        )
+        .unwrap();
+        assert_eq!(merged.image_folder, dataset.image_folder);
+        assert_eq!(merged.get_imgs(), dataset.get_imgs());
+        assert_eq!(report.duplicate_images.len(), 2);
+        assert_eq!(merged.get_anns().len(), 4);
+    }
+}
@@ -1,30 +1,59 @@
 //! Module containing the structs used to build a COCO format dataset.
-use std::collections::HashSet;
+use std::collections::BTreeMap;
 use std::error::Error;
 use std::fs;
-use std::path::Path;
-use std::{collections::HashMap, path::PathBuf};
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
 
+use indexmap::{IndexMap, IndexSet};
 #[cfg(feature = "pyo3")]
 use pyo3::prelude::*;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, Serializer};
 
+use crate::coco::eval::Detection;
 use crate::errors::{self, LoadingError, MissingIdError};
+use crate::mask::conversions::rle_from_poly;
+use crate::mask::utils::Area;
 use crate::utils::load_img;
 use crate::visualize::draw;
 
 /// COCO dataset as-is, without additionnal functionalities.
 #[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
 pub struct Dataset {
-    #[serde(default)]
-    pub info: Info,
+    /// Optional in the json file, it is only written back if it was present.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub info: Option<Info>,
     pub images: Vec<Image>,
     pub annotations: Vec<Annotation>,
     pub categories: Vec<Category>,
-    #[serde(default)]
-    pub licenses: Vec<License>,
+    /// Optional in the json file, it is only written back if it was present.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub licenses: Option<Vec<License>>,
+    /// Fields not defined by the COCO format, see [`ExtraFields`].
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
+impl Dataset {
+    /// Loads a COCO annotation file as-is, without checking the consistency of the entries.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the json file does not exist/cannot be read or if an error happens when deserializing it.
+    pub fn load<P: AsRef<Path>>(annotations_path: P) -> Result<Self, LoadingError> {
+        let annotations_path = annotations_path.as_ref().to_path_buf();
+        let annotations_file_content = fs::read_to_string(&annotations_path)
+            .map_err(|err| LoadingError::Read(err, annotations_path.clone()))?;
+
+        serde_json::from_str(&annotations_file_content)
+            .map_err(|err| LoadingError::Deserialize(err, annotations_path))
+    }
+}
+
+#[cfg_attr(
+    feature = "pyo3",
+    pyclass(get_all, set_all, module = "rpycocotools.anns")
+)]
 #[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
 pub struct Info {
     pub year: u32,
@@ -33,8 +62,15 @@ pub struct Info {
     pub contributor: String,
     pub url: String,
     pub date_created: String,
+    /// Fields not defined by the COCO format, see [`ExtraFields`].
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
+#[cfg_attr(
+    feature = "pyo3",
+    pyclass(get_all, set_all, module = "rpycocotools.anns")
+)]
 #[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
 pub struct License {
     pub id: u32,
@@ -42,6 +78,28 @@ pub struct License {
     pub url: String,
 }
 
+/// Fields of a json entry that are not part of the COCO format, for example `attributes`, `occluded` or tool specific metadata.
+///
+/// They are kept when loading a dataset so that saving it afterwards does not lose them.
+/// Only these fields are buffered by serde when loading, the `load_dataset` benchmark measures their cost.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct ExtraFields(pub BTreeMap<String, serde_json::Value>);
+
+impl Deref for ExtraFields {
+    type Target = BTreeMap<String, serde_json::Value>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for ExtraFields {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
 /// Stores information relating to one image.
 #[cfg_attr(
     feature = "pyo3",
@@ -61,6 +119,9 @@ pub struct Image {
     pub coco_url: String,
     #[serde(default)]
     pub date_captured: String,
+    /// Fields not defined by the COCO format, see [`ExtraFields`].
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 /// Object instance annotation for object detection.\
@@ -90,6 +151,20 @@ pub struct Annotation {
     pub bbox: Bbox,
     /// Either 1 or 0
     pub iscrowd: u32,
+    /// Confidence score of the annotation, only present for predictions (see [`HashmapDataset::load_results`]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<f64>,
+    /// Keypoints of the object, only present for the keypoint detection task.\
+    /// They are stored as `[x1, y1, v1, x2, y2, v2, ...]`, where v is the visibility flag: 0 if not labeled, 1 if labeled but not visible and 2 if labeled and visible.\
+    /// The keypoints follow the order given by the category's [`Category::keypoints`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keypoints: Option<Vec<f64>>,
+    /// Number of labeled keypoints (v > 0).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub num_keypoints: Option<u32>,
+    /// Fields not defined by the COCO format, see [`ExtraFields`].
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 // #[cfg_attr(feature = "pyo3", pyclass)]
@@ -100,10 +175,18 @@ pub enum Segmentation {
     Rle(Rle),
     CocoRle(CocoRle),
     Polygons(Polygons),
-    #[serde(skip)]
+    /// Written as [`Polygons`] in the json file, since the size is already given by the image.
+    #[serde(skip_deserializing, serialize_with = "serialize_polygons_rs")]
     PolygonsRS(PolygonsRS),
 }
 
+fn serialize_polygons_rs<S: Serializer>(
+    poly: &PolygonsRS,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    poly.counts.serialize(serializer)
+}
+
 /// Polygon(s) representing a segmentation mask.
 ///
 /// A Segmentation mask might require multiple polygons if the mask is in multiple parts (in case of partial occlusion for example).
@@ -172,7 +255,7 @@ pub struct CocoRle {
         module = "rpycocotools.anns"
     )
 )]
-#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
 pub struct Bbox {
     pub left: f64,
     pub top: f64,
@@ -180,6 +263,13 @@ pub struct Bbox {
     pub height: f64,
 }
 
+/// The bounding box is written in the COCO format: `[left, top, width, height]`.
+impl Serialize for Bbox {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        [self.left, self.top, self.width, self.height].serialize(serializer)
+    }
+}
+
 /// Category of an annotation.
 #[cfg_attr(
     feature = "pyo3",
@@ -190,20 +280,35 @@ pub struct Category {
     pub id: u32,
     pub name: String,
     pub supercategory: String,
+    /// Names of the keypoints, only present for the keypoint detection task.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keypoints: Option<Vec<String>>,
+    /// Links between the keypoints, as pairs of (1-based) indices in [`Category::keypoints`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skeleton: Option<Vec<(u32, u32)>>,
+    /// Fields not defined by the COCO format, see [`ExtraFields`].
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 /// COCO dataset represented as a hashmap where the hashmap's keys are the ids.
 ///
-/// This struct provides methods to make working with the dataset easier and more efficient.
+/// This struct provides methods to make working with the dataset easier and more efficient.\
+/// The entries are kept in the order in which they were inserted (the order of the json file when loading one),
+/// use [`HashmapDataset::sort_by_id`] to order them by id instead.
 #[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 pub struct HashmapDataset {
-    pub(crate) anns: HashMap<u64, Annotation>,
-    cats: HashMap<u32, Category>,
-    imgs: HashMap<u64, Image>,
+    pub(crate) anns: IndexMap<u64, Annotation>,
+    cats: IndexMap<u32, Category>,
+    imgs: IndexMap<u64, Image>,
     /// Hashmap that links an image id to the image's annotations
     // Use Rc to reference the annotations directly ?
-    img_to_anns: HashMap<u64, HashSet<u64>>,
+    img_to_anns: IndexMap<u64, IndexSet<u64>>,
     pub image_folder: PathBuf,
+    info: Option<Info>,
+    licenses: Option<Vec<License>>,
+    /// Top level fields of the json file not defined by the COCO format.
+    extra: ExtraFields,
 }
 
 impl HashmapDataset {
@@ -214,13 +319,7 @@ impl HashmapDataset {
     /// Will return `Err` if the json file does not exist/cannot be read or if an error happens when deserializing and parsing it.
     /// Will return `Err` if there is an annotation with an image id X, but no image entry has this id.
     pub fn new<P: AsRef<Path>>(annotations_path: P, image_folder: P) -> Result<Self, LoadingError> {
-        let annotations_path = annotations_path.as_ref().to_path_buf();
-        let annotations_file_content = fs::read_to_string(&annotations_path)
-            .map_err(|err| LoadingError::Read(err, annotations_path.clone()))?;
-
-        let dataset: Dataset = serde_json::from_str(&annotations_file_content)
-            .map_err(|err| LoadingError::Deserialize(err, annotations_path.clone()))?;
-        Self::from_dataset(dataset, image_folder)
+        Self::from_dataset(Dataset::load(annotations_path)?, image_folder)
     }
 
     /// Construct a hashmap COCO dataset from a "simple" dataset and the image folder.
@@ -238,19 +337,19 @@ impl HashmapDataset {
             .map(|category| (category.id, category))
             .collect();
 
-        let imgs: HashMap<u64, Image> = dataset
+        let imgs: IndexMap<u64, Image> = dataset
             .images
             .clone()
             .into_iter()
             .map(|image| (image.id, image))
             .collect();
 
-        let mut anns: HashMap<u64, Annotation> = HashMap::new();
+        let mut anns: IndexMap<u64, Annotation> = IndexMap::new();
         // Have (at least) an empty set for each image to avoid getting an error in the case where an image does not have any annotation.
-        let mut img_to_anns: HashMap<u64, HashSet<u64>> = dataset
+        let mut img_to_anns: IndexMap<u64, IndexSet<u64>> = dataset
             .images
             .into_iter()
-            .map(|image| (image.id, HashSet::new()))
+            .map(|image| (image.id, IndexSet::new()))
             .collect();
 
         for mut annotation in dataset.annotations {
@@ -264,17 +363,14 @@ impl HashmapDataset {
                     size: if let Some(img) = imgs.get(&img_id) {
                         vec![img.height, img.width]
                     } else {
-                        return Err(MissingIdError::Image(img_id)).map_err(LoadingError::Parsing);
+                        return Err(LoadingError::Parsing(MissingIdError::Image(img_id)));
                     },
                     counts,
                 });
-            };
+            }
 
             anns.insert(annotation.id, annotation);
-            img_to_anns
-                .entry(img_id)
-                .or_insert_with(HashSet::new)
-                .insert(ann_id);
+            img_to_anns.entry(img_id).or_default().insert(ann_id);
         }
 
         Ok(Self {
@@ -283,9 +379,130 @@ impl HashmapDataset {
             imgs,
             img_to_anns,
             image_folder: image_folder.as_ref().to_path_buf(),
+            info: dataset.info,
+            licenses: dataset.licenses,
+            extra: dataset.extra,
         })
     }
 
+    /// Load a COCO results file, the equivalent of pycocotools' `loadRes`.
+    ///
+    /// The file must contain a list of detections (see [`Detection`]), each with a bounding box, a segmentation and/or keypoints.
+    /// The returned dataset shares the images and categories of this dataset, its annotations are the detections (see [`HashmapDataset::load_detections`]).
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the json file does not exist/cannot be read or if an error happens when deserializing it.
+    /// Will return `Err` if a detection corresponds to an image not in the dataset, or has neither a bounding box, a segmentation nor keypoints.
+    pub fn load_results<P: AsRef<Path>>(&self, results_path: P) -> Result<Self, LoadingError> {
+        let results_path = results_path.as_ref().to_path_buf();
+        let results_file_content = fs::read_to_string(&results_path)
+            .map_err(|err| LoadingError::Read(err, results_path.clone()))?;
+
+        let detections: Vec<Detection> = serde_json::from_str(&results_file_content)
+            .map_err(|err| LoadingError::Deserialize(err, results_path.clone()))?;
+        self.load_detections(detections)
+    }
+
+    /// Create a dataset with the images and categories of this dataset, and the given detections as annotations.
+    ///
+    /// The annotations are given the ids 1 to n (in the order of the detections), and the detections' scores and keypoints are kept.
+    /// - If a detection has a bounding box, the area is the one of the bounding box. Its segmentation is kept, or is the rectangle corresponding to the bounding box if there is none.
+    /// - If a detection has a segmentation but no bounding box, the area and the bounding box are computed from the segmentation.
+    ///   Its polygons must have at least 3 points, and the counts of its RLE must add up to the number of pixels of the image.
+    /// - If a detection only has keypoints, its bounding box encloses the keypoints and its segmentation is empty.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if a detection corresponds to an image not in the dataset, has neither a bounding box, a segmentation nor keypoints,
+    /// or has an invalid segmentation and no bounding box.
+    pub fn load_detections(&self, detections: Vec<Detection>) -> Result<Self, LoadingError> {
+        let mut annotations = Vec::with_capacity(detections.len());
+        for (i, detection) in detections.into_iter().enumerate() {
+            let img = self
+                .get_img(detection.image_id)
+                .map_err(LoadingError::Parsing)?;
+            let (segmentation, area, bbox) =
+                match (detection.segmentation, detection.bbox, &detection.keypoints) {
+                    (segmentation, Some(bbox), _) => {
+                        let segmentation = match segmentation {
+                            Some(Segmentation::Polygons(counts)) => {
+                                Segmentation::PolygonsRS(PolygonsRS {
+                                    size: vec![img.height, img.width],
+                                    counts,
+                                })
+                            }
+                            Some(segmentation) => segmentation,
+                            None => {
+                                let (x1, y1) = (bbox.left, bbox.top);
+                                let (x2, y2) = (bbox.left + bbox.width, bbox.top + bbox.height);
+                                Segmentation::PolygonsRS(PolygonsRS {
+                                    size: vec![img.height, img.width],
+                                    counts: vec![vec![x1, y1, x1, y2, x2, y2, x2, y1]],
+                                })
+                            }
+                        };
+                        (segmentation, bbox.width * bbox.height, bbox)
+                    }
+                    (Some(segmentation), None, _) => {
+                        let segmentation = match segmentation {
+                            Segmentation::Polygons(counts) => {
+                                Segmentation::PolygonsRS(PolygonsRS {
+                                    size: vec![img.height, img.width],
+                                    counts,
+                                })
+                            }
+                            segmentation => segmentation,
+                        };
+                        let rle = match &segmentation {
+                            Segmentation::PolygonsRS(poly) if are_valid_polygons(poly) => {
+                                Some(rle_from_poly(poly))
+                            }
+                            Segmentation::Rle(rle) => Some(rle.clone()),
+                            Segmentation::CocoRle(coco_rle) => Some(Rle::from(coco_rle)),
+                            _ => None,
+                        }
+                        .filter(is_valid_rle)
+                        .ok_or(LoadingError::InvalidResult(i))?;
+                        (segmentation, f64::from(rle.area()), Bbox::from(&rle))
+                    }
+                    (None, None, Some(keypoints)) => {
+                        let bbox = keypoints_bbox(keypoints);
+                        let segmentation = Segmentation::PolygonsRS(PolygonsRS {
+                            size: vec![img.height, img.width],
+                            counts: vec![],
+                        });
+                        (segmentation, bbox.width * bbox.height, bbox)
+                    }
+                    (None, None, None) => return Err(LoadingError::InvalidResult(i)),
+                };
+
+            annotations.push(Annotation {
+                id: i as u64 + 1,
+                image_id: detection.image_id,
+                category_id: detection.category_id,
+                segmentation,
+                area,
+                bbox,
+                iscrowd: 0,
+                score: Some(detection.score),
+                keypoints: detection.keypoints,
+                num_keypoints: None,
+                extra: ExtraFields::default(),
+            });
+        }
+
+        let dataset = Dataset {
+            images: self.get_imgs().into_iter().cloned().collect(),
+            annotations,
+            categories: self.get_cats().into_iter().cloned().collect(),
+            info: self.info.clone(),
+            licenses: self.licenses.clone(),
+            extra: self.extra.clone(),
+        };
+        Self::from_dataset(dataset, &self.image_folder)
+    }
+
     /// Return the annotation corresponding to the given annotation id.
     ///
     /// # Errors
@@ -335,6 +552,124 @@ impl HashmapDataset {
         self.imgs.values().collect()
     }
 
+    /// Returns the `info` section of the dataset, if the dataset has one.
+    #[must_use]
+    pub const fn get_info(&self) -> Option<&Info> {
+        self.info.as_ref()
+    }
+
+    /// Set (or remove with `None`) the `info` section of the dataset.
+    pub fn set_info(&mut self, info: Option<Info>) {
+        self.info = info;
+    }
+
+    /// Returns the `licenses` section of the dataset, if the dataset has one.
+    #[must_use]
+    pub fn get_licenses(&self) -> Option<&[License]> {
+        self.licenses.as_deref()
+    }
+
+    /// Set (or remove with `None`) the `licenses` section of the dataset.
+    pub fn set_licenses(&mut self, licenses: Option<Vec<License>>) {
+        self.licenses = licenses;
+    }
+
+    /// Returns the top level fields of the json file that are not defined by the COCO format.
+    #[must_use]
+    pub const fn get_extra(&self) -> &ExtraFields {
+        &self.extra
+    }
+
+    /// Set the top level fields of the json file that are not defined by the COCO format.
+    pub fn set_extra(&mut self, extra: ExtraFields) {
+        self.extra = extra;
+    }
+
+    /// Order the images, annotations and categories by id, instead of insertion order.
+    ///
+    /// The order is used by the getters and when saving the dataset.
+    pub fn sort_by_id(&mut self) {
+        self.anns.sort_keys();
+        self.cats.sort_keys();
+        self.imgs.sort_keys();
+        self.img_to_anns.sort_keys();
+        for ann_ids in self.img_to_anns.values_mut() {
+            ann_ids.sort();
+        }
+    }
+
+    /// Return a dataset with only the given images and their annotations.
+    ///
+    /// The entries keep their ids and the order of this dataset. The categories, `info`, `licenses` and extra fields are all kept.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if one of the image ids is not in the dataset.
+    pub fn subset(&self, img_ids: &[u64]) -> Result<Self, MissingIdError> {
+        let img_ids = img_ids
+            .iter()
+            .map(|img_id| self.get_img(*img_id).map(|_| *img_id))
+            .collect::<Result<IndexSet<u64>, MissingIdError>>()?;
+
+        let imgs: IndexMap<u64, Image> = self
+            .imgs
+            .iter()
+            .filter(|(img_id, _)| img_ids.contains(*img_id))
+            .map(|(img_id, img)| (*img_id, img.clone()))
+            .collect();
+        let img_to_anns: IndexMap<u64, IndexSet<u64>> = self
+            .img_to_anns
+            .iter()
+            .filter(|(img_id, _)| img_ids.contains(*img_id))
+            .map(|(img_id, ann_ids)| (*img_id, ann_ids.clone()))
+            .collect();
+        let anns = self
+            .anns
+            .iter()
+            .filter(|(_, ann)| img_ids.contains(&ann.image_id))
+            .map(|(ann_id, ann)| (*ann_id, ann.clone()))
+            .collect();
+
+        Ok(Self {
+            anns,
+            cats: self.cats.clone(),
+            imgs,
+            img_to_anns,
+            image_folder: self.image_folder.clone(),
+            info: self.info.clone(),
+            licenses: self.licenses.clone(),
+            extra: self.extra.clone(),
+        })
+    }
+
+    /// Keep only the annotations for which `f` returns true.
+    pub(crate) fn retain_anns<F: FnMut(&Annotation) -> bool>(&mut self, mut f: F) {
+        self.anns.retain(|_, ann| f(ann));
+        let anns = &self.anns;
+        for ann_ids in self.img_to_anns.values_mut() {
+            ann_ids.retain(|ann_id| anns.contains_key(ann_id));
+        }
+    }
+
+    /// Keep only the images for which `f` returns true, and their annotations.
+    pub(crate) fn retain_imgs<F: FnMut(&Image) -> bool>(&mut self, mut f: F) {
+        self.imgs.retain(|_, img| f(img));
+        let imgs = &self.imgs;
+        self.img_to_anns
+            .retain(|img_id, _| imgs.contains_key(img_id));
+        self.anns.retain(|_, ann| imgs.contains_key(&ann.image_id));
+    }
+
+    /// Keep only the categories for which `f` returns true, the annotations are left untouched.
+    pub(crate) fn retain_cats<F: FnMut(&Category) -> bool>(&mut self, mut f: F) {
+        self.cats.retain(|_, cat| f(cat));
+    }
+
+    /// Replace the categories, the annotations are left untouched.
+    pub(crate) fn set_cats(&mut self, cats: Vec<Category>) {
+        self.cats = cats.into_iter().map(|cat| (cat.id, cat)).collect();
+    }
+
     /// Return the annotations for the given image id.
     ///
     /// # Errors
@@ -360,7 +695,12 @@ impl HashmapDataset {
     ) -> Result<image::ImageBuffer<image::Rgb<u8>, Vec<u8>>, errors::CocoError> {
         let img_path = self.image_folder.join(&self.get_img(img_id)?.file_name);
         let mut img = load_img(&img_path)?;
-        draw::anns(&mut img, &self.get_img_anns(img_id)?, draw_bbox)?;
+        draw::anns_with_skeletons(
+            &mut img,
+            &self.get_img_anns(img_id)?,
+            &self.get_cats(),
+            draw_bbox,
+        )?;
         Ok(img)
     }
 
@@ -378,7 +718,7 @@ impl HashmapDataset {
             .image_folder
             .join(&self.get_img(ann.image_id)?.file_name);
         let mut img = load_img(&img_path)?;
-        draw::anns(&mut img, &vec![ann], draw_bbox)?;
+        draw::anns_with_skeletons(&mut img, &vec![ann], &self.get_cats(), draw_bbox)?;
         Ok(())
     }
 
@@ -408,13 +748,57 @@ impl HashmapDataset {
     }
 }
 
+/// Whether each polygon has an even number of coordinates, and at least 3 points.
+fn are_valid_polygons(poly: &PolygonsRS) -> bool {
+    poly.size.len() == 2
+        && poly
+            .counts
+            .iter()
+            .all(|xy| xy.len() >= 6 && xy.len() % 2 == 0)
+}
+
+/// Whether the RLE has some counts and they add up to the number of pixels of the mask.
+fn is_valid_rle(rle: &Rle) -> bool {
+    rle.size.len() == 2
+        && !rle.counts.is_empty()
+        && rle
+            .counts
+            .iter()
+            .map(|count| u64::from(*count))
+            .sum::<u64>()
+            == u64::from(rle.size[0]) * u64::from(rle.size[1])
+}
+
+/// Bounding box enclosing all the keypoints (labeled or not), given as `[x1, y1, v1, x2, y2, v2, ...]`.
+pub(crate) fn keypoints_bbox(keypoints: &[f64]) -> Bbox {
+    if keypoints.len() < 3 {
+        return Bbox::default();
+    }
+    let xs = keypoints.iter().step_by(3);
+    let ys = keypoints.iter().skip(1).step_by(3);
+    let (left, right) = xs.fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), x| {
+        (min.min(*x), max.max(*x))
+    });
+    let (top, bot) = ys.fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), y| {
+        (min.min(*y), max.max(*y))
+    });
+    Bbox {
+        left,
+        top,
+        width: right - left,
+        height: bot - top,
+    }
+}
+
 impl From<&HashmapDataset> for Dataset {
     fn from(dataset: &HashmapDataset) -> Self {
         Self {
             images: dataset.get_imgs().into_iter().cloned().collect(),
             annotations: dataset.get_anns().into_iter().cloned().collect(),
             categories: dataset.get_cats().into_iter().cloned().collect(),
-            ..Default::default()
+            info: dataset.info.clone(),
+            licenses: dataset.licenses.clone(),
+            extra: dataset.extra.clone(),
         }
     }
 }
@@ -471,9 +855,265 @@ impl PartialEq for PolygonsRS {
 #[cfg(test)]
 #[allow(clippy::unwrap_used)]
 mod tests {
-    use super::PolygonsRS;
+    use super::*;
     use rstest::rstest;
 
+    fn dataset_with_one_image() -> HashmapDataset {
+        let dataset = Dataset {
+            images: vec![Image {
+                id: 1,
+                width: 20,
+                height: 10,
+                file_name: "1.jpg".to_string(),
+                ..Default::default()
+            }],
+            categories: vec![Category {
+                id: 1,
+                name: "object".to_string(),
+                supercategory: "object".to_string(),
+                keypoints: None,
+                skeleton: None,
+                extra: ExtraFields::default(),
+            }],
+            ..Default::default()
+        };
+        HashmapDataset::from_dataset(dataset, "N/A").unwrap()
+    }
+
+    #[rstest]
+    #[case::with_metadata(
+        r#"{"info":{"year":2017,"version":"1.0","description":"COCO 2017 Dataset","contributor":"COCO Consortium","url":"http://cocodataset.org","date_created":"2017/09/01"},"images":[],"annotations":[],"categories":[],"licenses":[{"id":1,"name":"Attribution License","url":"http://creativecommons.org/licenses/by/2.0/"}]}"#
+    )]
+    #[case::empty_licenses(r#"{"images":[],"annotations":[],"categories":[],"licenses":[]}"#)]
+    #[case::without_metadata(r#"{"images":[],"annotations":[],"categories":[]}"#)]
+    fn info_and_licenses_roundtrip(#[case] json: &str) {
+        let dataset: Dataset = serde_json::from_str(json).unwrap();
+        let dataset = HashmapDataset::from_dataset(dataset, "N/A").unwrap();
+        assert_eq!(dataset.json().unwrap(), json);
+    }
+
+    #[test]
+    fn extra_fields_roundtrip() {
+        let json = r##"{
+            "info": {"year": 2023, "version": "1", "description": "", "contributor": "", "url": "", "date_created": "", "tool": "cvat"},
+            "images": [{"id": 1, "width": 20, "height": 10, "file_name": "1.jpg", "license": 0, "flickr_url": "", "coco_url": "", "date_captured": "", "occluded": false}],
+            "annotations": [{"id": 1, "image_id": 1, "category_id": 1, "segmentation": {"size": [10, 20], "counts": [0, 200]}, "area": 200.0, "bbox": [0.0, 0.0, 20.0, 10.0], "iscrowd": 0, "attributes": {"track_id": 3, "ignore": null}},
+                            {"id": 2, "image_id": 1, "category_id": 1, "segmentation": [[0.0, 0.0, 5.0, 0.0, 5.0, 5.0]], "area": 12.5, "bbox": [0.0, 0.0, 5.0, 5.0], "iscrowd": 0}],
+            "categories": [{"id": 1, "name": "object", "supercategory": "object", "color": "#ff0000"}],
+            "type": "instances"
+        }"##;
+        let dataset: Dataset = serde_json::from_str(json).unwrap();
+        assert_eq!(dataset.extra["type"], "instances");
+        assert_eq!(dataset.images[0].extra["occluded"], false);
+        assert_eq!(dataset.annotations[0].extra["attributes"]["track_id"], 3);
+
+        let mut dataset = HashmapDataset::from_dataset(dataset, "N/A").unwrap();
+        dataset
+            .cats
+            .get_mut(&1)
+            .unwrap()
+            .extra
+            .insert("added".to_string(), serde_json::Value::from(1));
+        let saved: serde_json::Value = serde_json::from_str(&dataset.json().unwrap()).unwrap();
+        let mut expected: serde_json::Value = serde_json::from_str(json).unwrap();
+        expected["categories"][0]["added"] = serde_json::Value::from(1);
+        assert_eq!(saved, expected);
+    }
+
+    #[test]
+    fn entries_order() {
+        let image = |id: u64| Image {
+            id,
+            width: 10,
+            height: 10,
+            file_name: format!("{id}.jpg"),
+            ..Default::default()
+        };
+        let ann = |id: u64, image_id: u64| Annotation {
+            id,
+            image_id,
+            category_id: 1,
+            segmentation: Segmentation::Polygons(vec![]),
+            area: 0.0,
+            bbox: Bbox::default(),
+            iscrowd: 0,
+            score: None,
+            keypoints: None,
+            num_keypoints: None,
+            extra: ExtraFields::default(),
+        };
+        let dataset = Dataset {
+            images: vec![image(3), image(1), image(2)],
+            annotations: vec![ann(5, 1), ann(2, 3), ann(9, 1), ann(1, 1)],
+            ..Default::default()
+        };
+        let mut dataset = HashmapDataset::from_dataset(dataset, "N/A").unwrap();
+        let img_ids = |dataset: &HashmapDataset| -> Vec<u64> {
+            dataset.get_imgs().iter().map(|img| img.id).collect()
+        };
+        let ann_ids =
+            |anns: Vec<&Annotation>| -> Vec<u64> { anns.iter().map(|ann| ann.id).collect() };
+
+        assert_eq!(img_ids(&dataset), vec![3, 1, 2]);
+        assert_eq!(ann_ids(dataset.get_anns()), vec![5, 2, 9, 1]);
+        assert_eq!(ann_ids(dataset.get_img_anns(1).unwrap()), vec![5, 9, 1]);
+        let saved = Dataset::from(&dataset);
+        assert_eq!(saved.images[0].id, 3);
+
+        dataset.sort_by_id();
+        assert_eq!(img_ids(&dataset), vec![1, 2, 3]);
+        assert_eq!(ann_ids(dataset.get_anns()), vec![1, 2, 5, 9]);
+        assert_eq!(ann_ids(dataset.get_img_anns(1).unwrap()), vec![1, 5, 9]);
+        let saved = Dataset::from(&dataset);
+        assert_eq!(saved.annotations[0].id, 1);
+    }
+
+    #[test]
+    fn set_info_and_licenses() {
+        let mut dataset = dataset_with_one_image();
+        assert!(dataset.get_info().is_none());
+        let info = Info {
+            year: 2023,
+            description: "test".to_string(),
+            ..Default::default()
+        };
+        dataset.set_info(Some(info.clone()));
+        dataset.set_licenses(Some(vec![License::default()]));
+        assert_eq!(dataset.get_info(), Some(&info));
+        assert_eq!(dataset.get_licenses().map(<[License]>::len), Some(1));
+
+        let saved = Dataset::from(&dataset);
+        assert_eq!(saved.info, Some(info));
+        assert_eq!(saved.licenses, Some(vec![License::default()]));
+    }
+
+    #[test]
+    fn load_detections() {
+        let dataset = dataset_with_one_image();
+        let detections = vec![
+            Detection {
+                image_id: 1,
+                category_id: 1,
+                bbox: Some(Bbox {
+                    left: 2.0,
+                    top: 3.0,
+                    width: 4.0,
+                    height: 5.0,
+                }),
+                segmentation: None,
+                keypoints: None,
+                score: 0.9,
+            },
+            Detection {
+                image_id: 1,
+                category_id: 1,
+                bbox: None,
+                segmentation: Some(Segmentation::Rle(Rle {
+                    size: vec![10, 20],
+                    counts: vec![12, 3, 7, 3, 175],
+                })),
+                keypoints: None,
+                score: 0.5,
+            },
+        ];
+        let results = dataset.load_detections(detections).unwrap();
+
+        assert_eq!(results.get_imgs().len(), 1);
+        assert_eq!(results.get_cats().len(), 1);
+        let bbox_ann = results.get_ann(1).unwrap();
+        assert_eq!(bbox_ann.score, Some(0.9));
+        assert!((bbox_ann.area - 20.0).abs() < f64::EPSILON);
+        assert_eq!(
+            bbox_ann.segmentation,
+            Segmentation::PolygonsRS(PolygonsRS {
+                size: vec![10, 20],
+                counts: vec![vec![2.0, 3.0, 2.0, 8.0, 6.0, 8.0, 6.0, 3.0]],
+            })
+        );
+        let segmentation_ann = results.get_ann(2).unwrap();
+        assert!((segmentation_ann.area - 6.0).abs() < f64::EPSILON);
+        assert_eq!(
+            segmentation_ann.bbox,
+            Bbox {
+                left: 1.0,
+                top: 2.0,
+                width: 2.0,
+                height: 3.0,
+            }
+        );
+    }
+
+    #[test]
+    fn load_detections_with_bbox_and_segmentation() {
+        let dataset = dataset_with_one_image();
+        let bbox = Bbox {
+            left: 2.0,
+            top: 3.0,
+            width: 4.0,
+            height: 5.0,
+        };
+        let segmentation = Segmentation::Rle(Rle {
+            size: vec![10, 20],
+            counts: vec![12, 3, 7, 3, 175],
+        });
+        let detections = vec![Detection {
+            image_id: 1,
+            category_id: 1,
+            bbox: Some(bbox.clone()),
+            segmentation: Some(segmentation.clone()),
+            keypoints: None,
+            score: 0.9,
+        }];
+        let results = dataset.load_detections(detections).unwrap();
+
+        // Like pycocotools, the area is the one of the bounding box when there is one.
+        let ann = results.get_ann(1).unwrap();
+        assert!((ann.area - 20.0).abs() < f64::EPSILON);
+        assert_eq!(ann.bbox, bbox);
+        assert_eq!(ann.segmentation, segmentation);
+    }
+
+    #[test]
+    fn load_detections_unknown_image() {
+        let dataset = dataset_with_one_image();
+        let detections = vec![Detection {
+            image_id: 2,
+            category_id: 1,
+            bbox: None,
+            segmentation: Some(Segmentation::Polygons(vec![vec![
+                1.0, 1.0, 4.0, 1.0, 4.0, 4.0,
+            ]])),
+            keypoints: None,
+            score: 0.9,
+        }];
+        assert!(matches!(
+            dataset.load_detections(detections),
+            Err(LoadingError::Parsing(MissingIdError::Image(2)))
+        ));
+    }
+
+    #[rstest]
+    #[case::odd_polygon(Segmentation::Polygons(vec![vec![1.0, 1.0, 4.0, 1.0, 4.0, 4.0, 1.0]]))]
+    #[case::two_points_polygon(Segmentation::Polygons(vec![vec![1.0, 1.0, 4.0, 4.0]]))]
+    #[case::rle_without_counts(Segmentation::Rle(Rle {size: vec![10, 20], counts: vec![]}))]
+    #[case::rle_too_short(Segmentation::Rle(Rle {size: vec![10, 20], counts: vec![12, 3, 7]}))]
+    #[case::rle_too_long(Segmentation::Rle(Rle {size: vec![10, 20], counts: vec![12, 3, 7, 3, 176]}))]
+    fn load_detections_invalid_segmentation(#[case] segmentation: Segmentation) {
+        let dataset = dataset_with_one_image();
+        let detections = vec![Detection {
+            image_id: 1,
+            category_id: 1,
+            bbox: None,
+            segmentation: Some(segmentation),
+            keypoints: None,
+            score: 0.9,
+        }];
+        assert!(matches!(
+            dataset.load_detections(detections),
+            Err(LoadingError::InvalidResult(0))
+        ));
+    }
+
     #[rstest]
     #[case::single_polygon(
         &PolygonsRS {size: vec![20, 20], counts: vec![vec![1.1, 2.1, 3.2, 4.2, 5.3, 6.3]] },
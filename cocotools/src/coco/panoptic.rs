@@ -0,0 +1,486 @@
+//! Module containing the structs used to work with a COCO panoptic segmentation dataset.
+//!
+//! A panoptic dataset is made of a json file and of a folder of PNG files, one per image.
+//! In the PNG files, each segment is painted with a color encoding its id (`id = R + 256 * G + 256^2 * B`), pixels with the id 0 are unlabeled (void).
+//! The json file gives, for each image, the name of its PNG file and the information about each segment (category, bounding box, ...).
+//!
+//! See the [COCO panoptic format description](https://cocodataset.org/#format-data) and the [panopticapi](https://github.com/cocodataset/panopticapi).
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use image::RgbImage;
+use indexmap::IndexMap;
+use ndarray::{Array2, ShapeBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::coco::object_detection::{Bbox, Image, Info, License, Rle};
+use crate::errors::{self, LoadingError, MaskError, MissingIdError};
+use crate::utils::load_img;
+use crate::visualize::draw;
+
+/// Id of the segment each pixel belongs to, 0 for the unlabeled pixels. The array has the shape `(height, width)`.
+pub type SegmentIds = Array2<u32>;
+
+/// COCO panoptic dataset as-is, without additionnal functionalities.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct PanopticDataset {
+    /// Optional in the json file, it is only written back if it was present.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub info: Option<Info>,
+    pub images: Vec<Image>,
+    pub annotations: Vec<PanopticAnnotation>,
+    pub categories: Vec<PanopticCategory>,
+    /// Optional in the json file, it is only written back if it was present.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub licenses: Option<Vec<License>>,
+}
+
+/// Panoptic annotation of an image, there is exactly one per image.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct PanopticAnnotation {
+    pub image_id: u64,
+    /// Name of the PNG file with the segment ids, in the segmentation folder.
+    pub file_name: String,
+    pub segments_info: Vec<SegmentInfo>,
+}
+
+/// Information about one segment of a panoptic annotation.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct SegmentInfo {
+    /// Id of the segment, as encoded in the PNG file. It is unique within an image.
+    pub id: u32,
+    pub category_id: u32,
+    /// The area and bounding box are optional for predictions.
+    #[serde(default)]
+    pub area: f64,
+    #[serde(default)]
+    pub bbox: Bbox,
+    /// Either 1 or 0
+    #[serde(default)]
+    pub iscrowd: u32,
+}
+
+/// Category of a panoptic segment.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+pub struct PanopticCategory {
+    pub id: u32,
+    pub name: String,
+    pub supercategory: String,
+    /// 1 for countable objects (person, car, ...), 0 for amorphous regions (sky, grass, ...).
+    pub isthing: u32,
+    /// Color used when visualizing the category.
+    #[serde(default)]
+    pub color: [u8; 3],
+}
+
+/// COCO panoptic dataset represented as hashmaps where the keys are the ids.
+///
+/// Like [`crate::coco::object_detection::HashmapDataset`], the entries are kept in insertion order.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct HashmapPanopticDataset {
+    /// The annotations, indexed by image id.
+    anns: IndexMap<u64, PanopticAnnotation>,
+    cats: IndexMap<u32, PanopticCategory>,
+    imgs: IndexMap<u64, Image>,
+    pub image_folder: PathBuf,
+    /// Folder with the PNG files containing the segment ids.
+    pub segmentation_folder: PathBuf,
+    info: Option<Info>,
+    licenses: Option<Vec<License>>,
+}
+
+impl HashmapPanopticDataset {
+    /// Loads a COCO panoptic dataset from the annotation file, the image folder and the folder with the segmentation PNG files.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the json file does not exist/cannot be read or if an error happens when deserializing and parsing it.
+    /// Will return `Err` if there is an annotation with an image id X, but no image entry has this id.
+    pub fn new<P: AsRef<Path>>(
+        annotations_path: P,
+        image_folder: P,
+        segmentation_folder: P,
+    ) -> Result<Self, LoadingError> {
+        let annotations_path = annotations_path.as_ref().to_path_buf();
+        let annotations_file_content = fs::read_to_string(&annotations_path)
+            .map_err(|err| LoadingError::Read(err, annotations_path.clone()))?;
+
+        let dataset: PanopticDataset = serde_json::from_str(&annotations_file_content)
+            .map_err(|err| LoadingError::Deserialize(err, annotations_path.clone()))?;
+        Self::from_dataset(dataset, image_folder, segmentation_folder)
+    }
+
+    /// Construct a hashmap panoptic dataset from a "simple" dataset, the image folder and the segmentation folder.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if there is an annotation with an image id X, but no image entry has this id.
+    pub fn from_dataset<P: AsRef<Path>>(
+        dataset: PanopticDataset,
+        image_folder: P,
+        segmentation_folder: P,
+    ) -> Result<Self, LoadingError> {
+        let imgs: IndexMap<u64, Image> = dataset
+            .images
+            .into_iter()
+            .map(|image| (image.id, image))
+            .collect();
+        let cats = dataset
+            .categories
+            .into_iter()
+            .map(|category| (category.id, category))
+            .collect();
+
+        let mut anns = IndexMap::new();
+        for ann in dataset.annotations {
+            if !imgs.contains_key(&ann.image_id) {
+                return Err(LoadingError::Parsing(MissingIdError::Image(ann.image_id)));
+            }
+            anns.insert(ann.image_id, ann);
+        }
+
+        Ok(Self {
+            anns,
+            cats,
+            imgs,
+            image_folder: image_folder.as_ref().to_path_buf(),
+            segmentation_folder: segmentation_folder.as_ref().to_path_buf(),
+            info: dataset.info,
+            licenses: dataset.licenses,
+        })
+    }
+
+    /// Return a result containing the panoptic annotation of the given image.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if there is no annotation for `img_id`.
+    pub fn get_img_ann(&self, img_id: u64) -> Result<&PanopticAnnotation, MissingIdError> {
+        self.anns.get(&img_id).ok_or(MissingIdError::Image(img_id))
+    }
+
+    #[must_use]
+    pub fn get_anns(&self) -> Vec<&PanopticAnnotation> {
+        self.anns.values().collect()
+    }
+
+    /// Return a result containing the category entry corresponding to the category id.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if there is no entry in the dataset corresponding to `cat_id`.
+    pub fn get_cat(&self, cat_id: u32) -> Result<&PanopticCategory, MissingIdError> {
+        self.cats
+            .get(&cat_id)
+            .ok_or(MissingIdError::Category(cat_id))
+    }
+
+    #[must_use]
+    pub fn get_cats(&self) -> Vec<&PanopticCategory> {
+        self.cats.values().collect()
+    }
+
+    /// Return a result containing the image entry corresponding to the image id.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if there is no entry in the dataset corresponding to `img_id`.
+    pub fn get_img(&self, img_id: u64) -> Result<&Image, MissingIdError> {
+        self.imgs.get(&img_id).ok_or(MissingIdError::Image(img_id))
+    }
+
+    #[must_use]
+    pub fn get_imgs(&self) -> Vec<&Image> {
+        self.imgs.values().collect()
+    }
+
+    /// Load the segment ids of the given image from its PNG file.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if there is no annotation for `img_id` or if the PNG file cannot be read.
+    pub fn load_segment_ids(&self, img_id: u64) -> Result<SegmentIds, errors::CocoError> {
+        let ann = self.get_img_ann(img_id)?;
+        let png = load_img(&self.segmentation_folder.join(&ann.file_name))?;
+        Ok(segment_ids_from_png(&png))
+    }
+
+    /// Return the segments of the given image along with their masks.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if there is no annotation for `img_id` or if the PNG file cannot be read.
+    pub fn get_img_segments(
+        &self,
+        img_id: u64,
+    ) -> Result<Vec<(&SegmentInfo, Rle)>, errors::CocoError> {
+        let ann = self.get_img_ann(img_id)?;
+        let segment_ids = self.load_segment_ids(img_id)?;
+        let mut rles = rles_from_segment_ids(&segment_ids);
+        let (height, width) = segment_ids.dim();
+        #[allow(clippy::cast_possible_truncation)]
+        let empty = Rle {
+            size: vec![height as u32, width as u32],
+            counts: vec![(height * width) as u32],
+        };
+        Ok(ann
+            .segments_info
+            .iter()
+            .map(|segment| {
+                let rle = rles.remove(&segment.id).unwrap_or_else(|| empty.clone());
+                (segment, rle)
+            })
+            .collect())
+    }
+
+    /// Draw the segments of the given image on the image and return it.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if there is no image or annotation entry for `img_id`, or if the image or PNG file cannot be read.
+    pub fn draw_img_anns(
+        &self,
+        img_id: u64,
+        draw_bbox: bool,
+    ) -> Result<image::ImageBuffer<image::Rgb<u8>, Vec<u8>>, errors::CocoError> {
+        let img_path = self.image_folder.join(&self.get_img(img_id)?.file_name);
+        let mut img = load_img(&img_path)?;
+        let segments = self.get_img_segments(img_id)?;
+        draw::panoptic_segments(&mut img, &segments, &self.get_cats(), draw_bbox)?;
+        Ok(img)
+    }
+
+    /// Save the json part of the dataset to the given path, the PNG files are left untouched.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if:
+    ///   - The file cannot be created (if the full directory path does not exist for example).
+    ///   - The implementation of `Serialize` fails or the dataset contains non-string keys.
+    pub fn save_to<P: AsRef<Path>>(&self, output_path: P) -> Result<(), Box<dyn Error>> {
+        let dataset = PanopticDataset::from(self);
+        let f = fs::File::create(output_path)?;
+        serde_json::to_writer_pretty(&f, &dataset)?;
+
+        Ok(())
+    }
+}
+
+impl From<&HashmapPanopticDataset> for PanopticDataset {
+    fn from(dataset: &HashmapPanopticDataset) -> Self {
+        Self {
+            images: dataset.get_imgs().into_iter().cloned().collect(),
+            annotations: dataset.get_anns().into_iter().cloned().collect(),
+            categories: dataset.get_cats().into_iter().cloned().collect(),
+            info: dataset.info.clone(),
+            licenses: dataset.licenses.clone(),
+        }
+    }
+}
+
+/// Convert a color from a panoptic PNG file into a segment id.
+///
+/// ## Example
+///
+/// ```rust
+/// use cocotools::coco::panoptic::{id_to_rgb, rgb_to_id};
+/// assert_eq!(rgb_to_id([1, 2, 3]), 1 + 2 * 256 + 3 * 256 * 256);
+/// assert_eq!(id_to_rgb(rgb_to_id([1, 2, 3])), [1, 2, 3]);
+/// ```
+#[must_use]
+pub fn rgb_to_id(rgb: [u8; 3]) -> u32 {
+    u32::from(rgb[0]) + 256 * u32::from(rgb[1]) + 256 * 256 * u32::from(rgb[2])
+}
+
+/// Convert a segment id into the color used to encode it in a panoptic PNG file.
+///
+/// Only the 24 lowest bits of the id can be encoded.
+#[must_use]
+pub fn id_to_rgb(id: u32) -> [u8; 3] {
+    let [r, g, b, _] = id.to_le_bytes();
+    [r, g, b]
+}
+
+/// Decode the segment ids from a panoptic PNG image.
+#[must_use]
+pub fn segment_ids_from_png(png: &RgbImage) -> SegmentIds {
+    Array2::from_shape_fn(
+        (png.height() as usize, png.width() as usize),
+        #[allow(clippy::cast_possible_truncation)]
+        |(y, x)| rgb_to_id(png.get_pixel(x as u32, y as u32).0),
+    )
+}
+
+/// Encode segment ids into a panoptic PNG image.
+#[must_use]
+#[allow(clippy::cast_possible_truncation)]
+pub fn png_from_segment_ids(segment_ids: &SegmentIds) -> RgbImage {
+    let (height, width) = segment_ids.dim();
+    RgbImage::from_fn(width as u32, height as u32, |x, y| {
+        image::Rgb(id_to_rgb(segment_ids[[y as usize, x as usize]]))
+    })
+}
+
+/// Split the segment ids into one RLE mask per segment, without going through dense masks.
+///
+/// The unlabeled pixels (id 0) are not returned.
+#[must_use]
+#[allow(clippy::cast_possible_truncation)]
+pub fn rles_from_segment_ids(segment_ids: &SegmentIds) -> HashMap<u32, Rle> {
+    let (height, width) = segment_ids.dim();
+    let size = (height * width) as u32;
+    // For each segment, its RLE counts and the position right after its last pixel.
+    let mut runs: HashMap<u32, (Vec<u32>, u32)> = HashMap::new();
+    // The RLEs are in column-major order.
+    for (pos, id) in segment_ids.t().iter().enumerate() {
+        if *id == 0 {
+            continue;
+        }
+        let pos = pos as u32;
+        let (counts, end) = runs.entry(*id).or_insert_with(|| (vec![], 0));
+        if *end == pos && !counts.is_empty() {
+            if let Some(count) = counts.last_mut() {
+                *count += 1;
+            }
+        } else {
+            counts.push(pos - *end);
+            counts.push(1);
+        }
+        *end = pos + 1;
+    }
+    runs.into_iter()
+        .map(|(id, (mut counts, end))| {
+            if end < size {
+                counts.push(size - end);
+            }
+            let rle = Rle {
+                size: vec![height as u32, width as u32],
+                counts,
+            };
+            (id, rle)
+        })
+        .collect()
+}
+
+/// Paint the RLE masks into a segment ids array, the later segments overwriting the earlier ones where they overlap.
+///
+/// ## Args
+/// - `segments`: The id of each segment along with its mask.
+/// - `height`: The height of the image.
+/// - `width`: The width of the image.
+///
+/// # Errors
+///
+/// Will return `Err` if a mask does not have the size `[height, width]`, or if its counts do not add up to `height * width`.
+pub fn segment_ids_from_rles(
+    segments: &[(u32, &Rle)],
+    height: u32,
+    width: u32,
+) -> Result<SegmentIds, MaskError> {
+    let mut segment_ids = SegmentIds::zeros((height as usize, width as usize).f());
+    for (id, rle) in segments {
+        if rle.size != [height, width] {
+            return Err(MaskError::SizeMismatch(
+                rle.size.clone(),
+                vec![height, width],
+            ));
+        }
+        let nb_pixels = u64::from(height) * u64::from(width);
+        let counts_sum: u64 = rle.counts.iter().map(|count| u64::from(*count)).sum();
+        if counts_sum != nb_pixels {
+            return Err(MaskError::InvalidCounts(
+                counts_sum,
+                rle.size.clone(),
+                nb_pixels,
+            ));
+        }
+        let mut pos = 0usize;
+        for (i, count) in rle.counts.iter().enumerate() {
+            let count = *count as usize;
+            if i % 2 == 1 {
+                for p in pos..pos + count {
+                    segment_ids[[p % height as usize, p / height as usize]] = *id;
+                }
+            }
+            pos += count;
+        }
+    }
+    Ok(segment_ids)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use ndarray::array;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::zero([0, 0, 0], 0)]
+    #[case::red([255, 0, 0], 255)]
+    #[case::all([12, 34, 56], 12 + 34 * 256 + 56 * 256 * 256)]
+    fn rgb_id_conversions(#[case] rgb: [u8; 3], #[case] id: u32) {
+        assert_eq!(rgb_to_id(rgb), id);
+        assert_eq!(id_to_rgb(id), rgb);
+    }
+
+    #[test]
+    fn segment_ids_to_rles_and_back() {
+        let segment_ids = array![[0, 1, 1, 0], [2, 1, 1, 0], [2, 2, 0, 300]];
+        let rles = rles_from_segment_ids(&segment_ids);
+        assert_eq!(rles.len(), 3);
+        assert_eq!(rles[&1].counts, vec![3, 2, 1, 2, 4]);
+        assert_eq!(rles[&2].counts, vec![1, 2, 2, 1, 6]);
+        assert_eq!(rles[&300].counts, vec![11, 1]);
+        assert_eq!(rles[&1].size, vec![3, 4]);
+
+        let segments: Vec<(u32, &Rle)> = rles.iter().map(|(id, rle)| (*id, rle)).collect();
+        assert_eq!(segment_ids_from_rles(&segments, 3, 4).unwrap(), segment_ids);
+        assert!(segment_ids_from_rles(&segments, 4, 3).is_err());
+    }
+
+    #[rstest]
+    #[case::too_short(vec![3, 2, 1])]
+    #[case::too_long(vec![3, 2, 1, 2, 5])]
+    fn segment_ids_from_rles_invalid_counts(#[case] counts: Vec<u32>) {
+        let rle = Rle {
+            size: vec![3, 4],
+            counts,
+        };
+        assert!(matches!(
+            segment_ids_from_rles(&[(1, &rle)], 3, 4),
+            Err(MaskError::InvalidCounts(..))
+        ));
+    }
+
+    #[test]
+    fn png_roundtrip() {
+        let segment_ids = array![[0, 70_000], [5, 5]];
+        let png = png_from_segment_ids(&segment_ids);
+        assert_eq!(png.get_pixel(1, 0).0, [112, 17, 1]);
+        assert_eq!(segment_ids_from_png(&png), segment_ids);
+    }
+
+    #[test]
+    fn load_dataset() {
+        let json = r#"{
+            "images": [{"id": 1, "width": 4, "height": 3, "file_name": "1.jpg"}],
+            "annotations": [{"image_id": 1, "file_name": "1.png", "segments_info": [
+                {"id": 1, "category_id": 1, "area": 4, "bbox": [1, 0, 2, 2], "iscrowd": 0},
+                {"id": 2, "category_id": 2, "area": 3, "bbox": [0, 1, 2, 2], "iscrowd": 0}
+            ]}],
+            "categories": [
+                {"id": 1, "name": "person", "supercategory": "person", "isthing": 1, "color": [220, 20, 60]},
+                {"id": 2, "name": "grass", "supercategory": "plant", "isthing": 0, "color": [0, 255, 0]}
+            ]
+        }"#;
+        let dataset: PanopticDataset = serde_json::from_str(json).unwrap();
+        let dataset = HashmapPanopticDataset::from_dataset(dataset, "images", "panoptic").unwrap();
+        assert_eq!(dataset.get_img_ann(1).unwrap().segments_info.len(), 2);
+        assert_eq!(dataset.get_cat(2).unwrap().isthing, 0);
+        assert_eq!(dataset.get_cat(1).unwrap().color, [220, 20, 60]);
+        assert!(dataset.get_img_ann(2).is_err());
+    }
+}
@@ -0,0 +1,465 @@
+//! Module implementing the panoptic quality (PQ) metrics.
+//!
+//! The implementation follows the reference [`pq_compute`](https://github.com/cocodataset/panopticapi/blob/master/panopticapi/evaluation.py)
+//! from panopticapi closely, including the handling of the void (unlabeled) pixels and of the crowd regions.
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::coco::panoptic::{
+    segment_ids_from_png, HashmapPanopticDataset, PanopticAnnotation, PanopticCategory, SegmentIds,
+};
+use crate::errors::{CocoError, EvaluationError, LoadingError};
+use crate::utils::load_img;
+
+/// Id of the unlabeled pixels.
+const VOID: u32 = 0;
+
+/// Matching statistics of a category.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PqStat {
+    /// Sum of the IoUs of the true positives.
+    pub iou: f64,
+    pub tp: u32,
+    pub fp: u32,
+    pub fn_: u32,
+}
+
+/// Panoptic, segmentation and recognition qualities.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Quality {
+    pub pq: f64,
+    pub sq: f64,
+    pub rq: f64,
+}
+
+/// Qualities averaged over several categories.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AveragedQuality {
+    pub quality: Quality,
+    /// Number of categories the qualities are averaged over, the categories without any segment are not counted.
+    pub n: usize,
+}
+
+/// Result of a panoptic evaluation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PanopticEvaluation {
+    /// Matching statistics of each category.
+    pub stats: HashMap<u32, PqStat>,
+    pub all: AveragedQuality,
+    pub things: AveragedQuality,
+    pub stuff: AveragedQuality,
+    /// Qualities of each category, they are 0 for the categories without any segment.
+    pub per_category: HashMap<u32, Quality>,
+}
+
+/// The predictions json file, only its annotations are used.
+#[derive(Deserialize)]
+struct Predictions {
+    annotations: Vec<PanopticAnnotation>,
+}
+
+/// Load the annotations from a panoptic predictions json file.
+///
+/// # Errors
+///
+/// Will return `Err` if the json file does not exist/cannot be read or if an error happens when deserializing it.
+pub fn load_predictions<P: AsRef<Path>>(
+    predictions_path: P,
+) -> Result<Vec<PanopticAnnotation>, LoadingError> {
+    let predictions_path = predictions_path.as_ref().to_path_buf();
+    let predictions_file_content = fs::read_to_string(&predictions_path)
+        .map_err(|err| LoadingError::Read(err, predictions_path.clone()))?;
+
+    let predictions: Predictions = serde_json::from_str(&predictions_file_content)
+        .map_err(|err| LoadingError::Deserialize(err, predictions_path.clone()))?;
+    Ok(predictions.annotations)
+}
+
+/// Evaluate panoptic predictions against the ground truth dataset.
+///
+/// ## Args
+/// - `gt`: The ground truth dataset.
+/// - `preds`: The predicted annotations, there must be one for each image of the ground truth.
+/// - `pred_folder`: The folder with the predicted PNG files.
+///
+/// # Errors
+///
+/// Will return `Err` if:
+///   - There is no prediction for an image of the ground truth.
+///   - A PNG file cannot be read, or the predicted and ground truth PNG files do not have the same size.
+///   - A predicted segment is only in the PNG file or only in the json file, or has a category not in the ground truth.
+pub fn evaluate<P: AsRef<Path>>(
+    gt: &HashmapPanopticDataset,
+    preds: &[PanopticAnnotation],
+    pred_folder: P,
+) -> Result<PanopticEvaluation, CocoError> {
+    let preds: HashMap<u64, &PanopticAnnotation> =
+        preds.iter().map(|pred| (pred.image_id, pred)).collect();
+    let mut gt_anns = gt.get_anns();
+    gt_anns.sort_unstable_by_key(|ann| ann.image_id);
+
+    let mut stats: HashMap<u32, PqStat> = HashMap::new();
+    for gt_ann in gt_anns {
+        let pred_ann = preds
+            .get(&gt_ann.image_id)
+            .ok_or(EvaluationError::MissingPrediction(gt_ann.image_id))?;
+        let gt_ids = gt.load_segment_ids(gt_ann.image_id)?;
+        let pred_png = load_img(&pred_folder.as_ref().join(&pred_ann.file_name))?;
+        let pred_ids = segment_ids_from_png(&pred_png);
+        for (cat_id, img_stat) in evaluate_img(gt_ann, &gt_ids, pred_ann, &pred_ids, gt)? {
+            let stat = stats.entry(cat_id).or_default();
+            stat.iou += img_stat.iou;
+            stat.tp += img_stat.tp;
+            stat.fp += img_stat.fp;
+            stat.fn_ += img_stat.fn_;
+        }
+    }
+
+    let mut cats = gt.get_cats();
+    cats.sort_unstable_by_key(|cat| cat.id);
+    let (all, per_category) = average(&stats, &cats, None);
+    let (things, _) = average(&stats, &cats, Some(true));
+    let (stuff, _) = average(&stats, &cats, Some(false));
+    Ok(PanopticEvaluation {
+        stats,
+        all,
+        things,
+        stuff,
+        per_category,
+    })
+}
+
+/// Match the predicted segments of an image with the ground truth ones, and count the true positives, false positives and false negatives of each category.
+fn evaluate_img(
+    gt_ann: &PanopticAnnotation,
+    gt_ids: &SegmentIds,
+    pred_ann: &PanopticAnnotation,
+    pred_ids: &SegmentIds,
+    gt: &HashmapPanopticDataset,
+) -> Result<HashMap<u32, PqStat>, EvaluationError> {
+    let img_id = gt_ann.image_id;
+    if gt_ids.dim() != pred_ids.dim() {
+        return Err(EvaluationError::PanopticSizeMismatch(img_id));
+    }
+
+    // The predicted areas are computed from the PNG file, the ground truth ones come from the json file.
+    let mut pred_areas: HashMap<u32, u64> = HashMap::new();
+    for id in pred_ids {
+        *pred_areas.entry(*id).or_default() += 1;
+    }
+    let pred_segms: HashMap<u32, u32> = pred_ann
+        .segments_info
+        .iter()
+        .map(|segment| (segment.id, segment.category_id))
+        .collect();
+    let mut pred_labels: Vec<u32> = pred_areas.keys().copied().collect();
+    pred_labels.sort_unstable();
+    for label in pred_labels {
+        match pred_segms.get(&label) {
+            None if label == VOID => {}
+            None => return Err(EvaluationError::SegmentNotInJson(img_id, label)),
+            Some(cat_id) => {
+                if gt.get_cat(*cat_id).is_err() {
+                    return Err(EvaluationError::UnknownCategory(img_id, label, *cat_id));
+                }
+            }
+        }
+    }
+    let mut not_in_png: Vec<u32> = pred_segms
+        .keys()
+        .filter(|id| !pred_areas.contains_key(id))
+        .copied()
+        .collect();
+    if !not_in_png.is_empty() {
+        not_in_png.sort_unstable();
+        return Err(EvaluationError::SegmentNotInPng(img_id, not_in_png));
+    }
+
+    // Intersection between each pair of (ground truth, predicted) segments.
+    let mut gt_pred_map: HashMap<(u32, u32), u64> = HashMap::new();
+    for (gt_id, pred_id) in gt_ids.iter().zip(pred_ids) {
+        *gt_pred_map.entry((*gt_id, *pred_id)).or_default() += 1;
+    }
+    let mut pairs: Vec<(&(u32, u32), &u64)> = gt_pred_map.iter().collect();
+    pairs.sort_unstable();
+
+    let gt_segms: HashMap<u32, _> = gt_ann
+        .segments_info
+        .iter()
+        .map(|segment| (segment.id, segment))
+        .collect();
+    let mut stats: HashMap<u32, PqStat> = HashMap::new();
+
+    // Count the matched pairs, a match requires an IoU strictly above 0.5 so it is unique.
+    let mut gt_matched: HashSet<u32> = HashSet::new();
+    let mut pred_matched: HashSet<u32> = HashSet::new();
+    for ((gt_label, pred_label), intersection) in pairs {
+        let (gt_segm, pred_cat_id) = match (gt_segms.get(gt_label), pred_segms.get(pred_label)) {
+            (Some(gt_segm), Some(pred_cat_id)) => (gt_segm, pred_cat_id),
+            _ => continue,
+        };
+        if gt_segm.iscrowd == 1 || gt_segm.category_id != *pred_cat_id {
+            continue;
+        }
+        #[allow(clippy::cast_precision_loss)]
+        let (intersection, pred_area, pred_void) = (
+            *intersection as f64,
+            pred_areas[pred_label] as f64,
+            gt_pred_map.get(&(VOID, *pred_label)).copied().unwrap_or(0) as f64,
+        );
+        let union = pred_area + gt_segm.area - intersection - pred_void;
+        let iou = intersection / union;
+        if iou > 0.5 {
+            let stat = stats.entry(gt_segm.category_id).or_default();
+            stat.tp += 1;
+            stat.iou += iou;
+            gt_matched.insert(*gt_label);
+            pred_matched.insert(*pred_label);
+        }
+    }
+
+    // Count the false negatives, the crowd regions are ignored.
+    let mut crowd_labels: HashMap<u32, u32> = HashMap::new();
+    for gt_segm in &gt_ann.segments_info {
+        if gt_matched.contains(&gt_segm.id) {
+            continue;
+        }
+        if gt_segm.iscrowd == 1 {
+            crowd_labels.insert(gt_segm.category_id, gt_segm.id);
+            continue;
+        }
+        stats.entry(gt_segm.category_id).or_default().fn_ += 1;
+    }
+
+    // Count the false positives, a predicted segment is ignored if more than half of it is on void or crowd regions of its category.
+    for pred_segm in &pred_ann.segments_info {
+        if pred_matched.contains(&pred_segm.id) {
+            continue;
+        }
+        let mut intersection = gt_pred_map.get(&(VOID, pred_segm.id)).copied().unwrap_or(0);
+        if let Some(crowd_label) = crowd_labels.get(&pred_segm.category_id) {
+            intersection += gt_pred_map
+                .get(&(*crowd_label, pred_segm.id))
+                .copied()
+                .unwrap_or(0);
+        }
+        #[allow(clippy::cast_precision_loss)]
+        if intersection as f64 / pred_areas[&pred_segm.id] as f64 > 0.5 {
+            continue;
+        }
+        stats.entry(pred_segm.category_id).or_default().fp += 1;
+    }
+
+    Ok(stats)
+}
+
+/// Average the qualities over the categories, keeping only the thing (or stuff) categories if `isthing` is given.
+///
+/// Also returns the qualities of each of the categories averaged over.
+fn average(
+    stats: &HashMap<u32, PqStat>,
+    cats: &[&PanopticCategory],
+    isthing: Option<bool>,
+) -> (AveragedQuality, HashMap<u32, Quality>) {
+    let mut total = Quality::default();
+    let mut n = 0;
+    let mut per_category = HashMap::new();
+    for cat in cats {
+        if isthing.map_or(false, |isthing| isthing != (cat.isthing == 1)) {
+            continue;
+        }
+        let stat = stats.get(&cat.id).cloned().unwrap_or_default();
+        if stat.tp + stat.fp + stat.fn_ == 0 {
+            per_category.insert(cat.id, Quality::default());
+            continue;
+        }
+        n += 1;
+        let (tp, fp, fn_) = (f64::from(stat.tp), f64::from(stat.fp), f64::from(stat.fn_));
+        let quality = Quality {
+            pq: stat.iou / (tp + 0.5 * fp + 0.5 * fn_),
+            sq: if stat.tp == 0 { 0.0 } else { stat.iou / tp },
+            rq: tp / (tp + 0.5 * fp + 0.5 * fn_),
+        };
+        total.pq += quality.pq;
+        total.sq += quality.sq;
+        total.rq += quality.rq;
+        per_category.insert(cat.id, quality);
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    let quality = if n == 0 {
+        Quality::default()
+    } else {
+        Quality {
+            pq: total.pq / n as f64,
+            sq: total.sq / n as f64,
+            rq: total.rq / n as f64,
+        }
+    };
+    (AveragedQuality { quality, n }, per_category)
+}
+
+/// Display the results in the same format as panopticapi.
+impl fmt::Display for PanopticEvaluation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:10}| {:>5}  {:>5}  {:>5} {:>5}",
+            "", "PQ", "SQ", "RQ", "N"
+        )?;
+        writeln!(f, "{}", "-".repeat(10 + 7 * 4))?;
+        for (name, result) in [
+            ("All", &self.all),
+            ("Things", &self.things),
+            ("Stuff", &self.stuff),
+        ] {
+            writeln!(
+                f,
+                "{name:10}| {:5.1}  {:5.1}  {:5.1} {:5}",
+                100.0 * result.quality.pq,
+                100.0 * result.quality.sq,
+                100.0 * result.quality.rq,
+                result.n
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::float_cmp)]
+mod tests {
+    use super::*;
+    use crate::coco::object_detection::{Bbox, Image};
+    use crate::coco::panoptic::{PanopticDataset, SegmentInfo};
+    use ndarray::array;
+
+    fn segment(id: u32, category_id: u32, area: f64, iscrowd: u32) -> SegmentInfo {
+        SegmentInfo {
+            id,
+            category_id,
+            area,
+            bbox: Bbox::default(),
+            iscrowd,
+        }
+    }
+
+    fn ann(segments_info: Vec<SegmentInfo>) -> PanopticAnnotation {
+        PanopticAnnotation {
+            image_id: 1,
+            file_name: "1.png".to_string(),
+            segments_info,
+        }
+    }
+
+    fn gt_dataset(gt_ann: PanopticAnnotation) -> HashmapPanopticDataset {
+        let category = |id: u32, isthing: u32| PanopticCategory {
+            id,
+            name: id.to_string(),
+            supercategory: id.to_string(),
+            isthing,
+            color: [0, 0, 0],
+        };
+        let dataset = PanopticDataset {
+            images: vec![Image {
+                id: 1,
+                width: 4,
+                height: 4,
+                file_name: "1.jpg".to_string(),
+                ..Default::default()
+            }],
+            annotations: vec![gt_ann],
+            categories: vec![category(1, 1), category(2, 0), category(3, 1)],
+            ..Default::default()
+        };
+        HashmapPanopticDataset::from_dataset(dataset, "images", "panoptic").unwrap()
+    }
+
+    #[test]
+    fn matching() {
+        // Thing 1 (category 1), stuff 2 (category 2), crowd 3 (category 3) and void.
+        let gt_ids = array![[1, 1, 2, 2], [1, 1, 2, 2], [3, 3, 0, 0], [3, 3, 0, 0]];
+        let gt_ann = ann(vec![
+            segment(1, 1, 4.0, 0),
+            segment(2, 2, 4.0, 0),
+            segment(3, 3, 4.0, 1),
+        ]);
+        // Segment 5 matches the thing (IoU 3/4), segment 6 has the wrong category for the stuff,
+        // segment 7 is on the crowd region and segment 8 is mostly on void.
+        let pred_ids = array![[5, 5, 6, 6], [5, 0, 6, 6], [7, 7, 8, 8], [7, 7, 8, 8]];
+        let pred_ann = ann(vec![
+            segment(5, 1, 0.0, 0),
+            segment(6, 1, 0.0, 0),
+            segment(7, 3, 0.0, 0),
+            segment(8, 2, 0.0, 0),
+        ]);
+        let gt = gt_dataset(gt_ann.clone());
+
+        let stats = evaluate_img(&gt_ann, &gt_ids, &pred_ann, &pred_ids, &gt).unwrap();
+        assert_eq!(
+            stats[&1],
+            PqStat {
+                iou: 0.75,
+                tp: 1,
+                fp: 1,
+                fn_: 0
+            }
+        );
+        assert_eq!(
+            stats[&2],
+            PqStat {
+                iou: 0.0,
+                tp: 0,
+                fp: 0,
+                fn_: 1
+            }
+        );
+        assert!(!stats.contains_key(&3));
+
+        let mut cats = gt.get_cats();
+        cats.sort_unstable_by_key(|cat| cat.id);
+        let (all, per_category) = average(&stats, &cats, None);
+        assert_eq!(all.n, 2);
+        assert_eq!(per_category[&1].pq, 0.75 / 1.5);
+        assert_eq!(per_category[&1].sq, 0.75);
+        assert_eq!(per_category[&2].pq, 0.0);
+        assert_eq!(per_category[&3], Quality::default());
+        let (things, _) = average(&stats, &cats, Some(true));
+        assert_eq!(things.n, 1);
+        assert_eq!(things.quality.rq, 1.0 / 1.5);
+    }
+
+    #[test]
+    fn invalid_predictions() {
+        let gt_ids = array![[1, 1], [1, 1]];
+        let gt_ann = ann(vec![segment(1, 1, 4.0, 0)]);
+        let gt = gt_dataset(gt_ann.clone());
+        let pred_ids = array![[1, 1], [2, 2]];
+
+        let pred_ann = ann(vec![segment(1, 1, 0.0, 0)]);
+        assert!(matches!(
+            evaluate_img(&gt_ann, &gt_ids, &pred_ann, &pred_ids, &gt),
+            Err(EvaluationError::SegmentNotInJson(1, 2))
+        ));
+
+        let pred_ann = ann(vec![
+            segment(1, 1, 0.0, 0),
+            segment(2, 1, 0.0, 0),
+            segment(3, 1, 0.0, 0),
+        ]);
+        assert!(matches!(
+            evaluate_img(&gt_ann, &gt_ids, &pred_ann, &pred_ids, &gt),
+            Err(EvaluationError::SegmentNotInPng(1, ids)) if ids == vec![3]
+        ));
+
+        let pred_ann = ann(vec![segment(1, 1, 0.0, 0), segment(2, 42, 0.0, 0)]);
+        assert!(matches!(
+            evaluate_img(&gt_ann, &gt_ids, &pred_ann, &pred_ids, &gt),
+            Err(EvaluationError::UnknownCategory(1, 2, 42))
+        ));
+    }
+}
@@ -1,11 +1,17 @@
+use std::collections::BTreeMap;
+
 use pyo3::class::basic::CompareOp;
+use pyo3::exceptions::{PyTypeError, PyValueError};
 use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyDict, PyFloat, PyList, PyLong, PyString, PyTuple};
 
 use crate::coco::object_detection::*;
 
 #[pymethods]
 impl Annotation {
     #[new]
+    #[allow(clippy::too_many_arguments)]
+    #[pyo3(signature = (id, image_id, category_id, segmentation, area, bbox, iscrowd, score=None, keypoints=None, num_keypoints=None, extra=None))]
     fn new(
         id: u64,
         image_id: u64,
@@ -14,6 +20,10 @@ impl Annotation {
         area: f64,
         bbox: Bbox,
         iscrowd: u32,
+        score: Option<f64>,
+        keypoints: Option<Vec<f64>>,
+        num_keypoints: Option<u32>,
+        extra: Option<ExtraFields>,
     ) -> Self {
         Self {
             id,
@@ -23,13 +33,20 @@ impl Annotation {
             area,
             bbox,
             iscrowd,
+            score,
+            keypoints,
+            num_keypoints,
+            extra: extra.unwrap_or_default(),
         }
     }
 
     fn __repr__(&self) -> String {
+        let score = self
+            .score
+            .map_or_else(String::new, |score| format!(", score={score}"));
         format!(
-            "Annotation(id={}, image_id={}, category_id={}, segmentation={}, area={}, bbox={}, iscrowd={})",
-            self.id, self.image_id, self.category_id, &self.segmentation.__repr__(), self.area, &self.bbox.__repr__(), self.iscrowd
+            "Annotation(id={}, image_id={}, category_id={}, segmentation={}, area={}, bbox={}, iscrowd={}{})",
+            self.id, self.image_id, self.category_id, &self.segmentation.__repr__(), self.area, &self.bbox.__repr__(), self.iscrowd, score
         )
     }
 
@@ -41,7 +58,11 @@ impl Annotation {
                 && self.segmentation == other.segmentation
                 && self.area == other.area
                 && self.bbox == other.bbox
-                && self.iscrowd == other.iscrowd)
+                && self.iscrowd == other.iscrowd
+                && self.score == other.score
+                && self.keypoints == other.keypoints
+                && self.num_keypoints == other.num_keypoints
+                && self.extra == other.extra)
                 .into_py(py),
             CompareOp::Ne => (self.id != other.id
                 || self.image_id != other.image_id
@@ -49,7 +70,11 @@ impl Annotation {
                 || self.segmentation != other.segmentation
                 || self.area != other.area
                 || self.bbox != other.bbox
-                || self.iscrowd != other.iscrowd)
+                || self.iscrowd != other.iscrowd
+                || self.score != other.score
+                || self.keypoints != other.keypoints
+                || self.num_keypoints != other.num_keypoints
+                || self.extra != other.extra)
                 .into_py(py),
             _ => py.NotImplemented(),
         }
@@ -59,11 +84,22 @@ impl Annotation {
 #[pymethods]
 impl Category {
     #[new]
-    fn new(id: u32, name: String, supercategory: String) -> Self {
+    #[pyo3(signature = (id, name, supercategory, keypoints=None, skeleton=None, extra=None))]
+    fn new(
+        id: u32,
+        name: String,
+        supercategory: String,
+        keypoints: Option<Vec<String>>,
+        skeleton: Option<Vec<(u32, u32)>>,
+        extra: Option<ExtraFields>,
+    ) -> Self {
         Self {
             id,
             name,
             supercategory,
+            keypoints,
+            skeleton,
+            extra: extra.unwrap_or_default(),
         }
     }
 
@@ -78,26 +114,123 @@ impl Category {
         match op {
             CompareOp::Eq => (self.id == other.id
                 && self.name == other.name
-                && self.supercategory == other.supercategory)
+                && self.supercategory == other.supercategory
+                && self.keypoints == other.keypoints
+                && self.skeleton == other.skeleton
+                && self.extra == other.extra)
                 .into_py(py),
             CompareOp::Ne => (self.id != other.id
                 || self.name != other.name
-                || self.supercategory != other.supercategory)
+                || self.supercategory != other.supercategory
+                || self.keypoints != other.keypoints
+                || self.skeleton != other.skeleton
+                || self.extra != other.extra)
+                .into_py(py),
+            _ => py.NotImplemented(),
+        }
+    }
+}
+
+#[pymethods]
+impl Info {
+    #[new]
+    #[pyo3(signature = (year, version, description, contributor, url, date_created, extra=None))]
+    fn new(
+        year: u32,
+        version: String,
+        description: String,
+        contributor: String,
+        url: String,
+        date_created: String,
+        extra: Option<ExtraFields>,
+    ) -> Self {
+        Self {
+            year,
+            version,
+            description,
+            contributor,
+            url,
+            date_created,
+            extra: extra.unwrap_or_default(),
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Info(year={}, version='{}', description='{}', contributor='{}', url='{}', date_created='{}')",
+            self.year, self.version, self.description, self.contributor, self.url, self.date_created
+        )
+    }
+
+    fn __richcmp__(&self, other: &Self, op: CompareOp, py: Python<'_>) -> PyObject {
+        match op {
+            CompareOp::Eq => (self.year == other.year
+                && self.version == other.version
+                && self.description == other.description
+                && self.contributor == other.contributor
+                && self.url == other.url
+                && self.date_created == other.date_created
+                && self.extra == other.extra)
+                .into_py(py),
+            CompareOp::Ne => (self.year != other.year
+                || self.version != other.version
+                || self.description != other.description
+                || self.contributor != other.contributor
+                || self.url != other.url
+                || self.date_created != other.date_created
+                || self.extra != other.extra)
                 .into_py(py),
             _ => py.NotImplemented(),
         }
     }
 }
 
+#[pymethods]
+impl License {
+    #[new]
+    fn new(id: u32, name: String, url: String) -> Self {
+        Self { id, name, url }
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "License(id={}, name='{}', url='{}')",
+            self.id, self.name, self.url
+        )
+    }
+
+    fn __richcmp__(&self, other: &Self, op: CompareOp, py: Python<'_>) -> PyObject {
+        match op {
+            CompareOp::Eq => {
+                (self.id == other.id && self.name == other.name && self.url == other.url)
+                    .into_py(py)
+            }
+            CompareOp::Ne => {
+                (self.id != other.id || self.name != other.name || self.url != other.url)
+                    .into_py(py)
+            }
+            _ => py.NotImplemented(),
+        }
+    }
+}
+
 #[pymethods]
 impl Image {
     #[new]
-    fn new(id: u64, width: u32, height: u32, file_name: String) -> Self {
+    #[pyo3(signature = (id, width, height, file_name, extra=None))]
+    fn new(
+        id: u64,
+        width: u32,
+        height: u32,
+        file_name: String,
+        extra: Option<ExtraFields>,
+    ) -> Self {
         Self {
             id,
             width,
             height,
             file_name,
+            extra: extra.unwrap_or_default(),
             ..Default::default()
         }
     }
@@ -114,12 +247,14 @@ impl Image {
             CompareOp::Eq => (self.id == other.id
                 && self.width == other.width
                 && self.height == other.height
-                && self.file_name == other.file_name)
+                && self.file_name == other.file_name
+                && self.extra == other.extra)
                 .into_py(py),
             CompareOp::Ne => (self.id != other.id
                 || self.width != other.width
                 || self.height != other.height
-                || self.file_name != other.file_name)
+                || self.file_name != other.file_name
+                || self.extra != other.extra)
                 .into_py(py),
             _ => py.NotImplemented(),
         }
@@ -278,3 +413,86 @@ impl IntoPy<PyObject> for Segmentation {
         }
     }
 }
+
+/// The extra fields are exposed to python as a dict (a copy, modifying it does not modify the entry).
+impl IntoPy<PyObject> for ExtraFields {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        self.0
+            .into_iter()
+            .map(|(key, value)| (key, json_to_py(value, py)))
+            .collect::<BTreeMap<String, PyObject>>()
+            .into_py(py)
+    }
+}
+
+impl<'source> FromPyObject<'source> for ExtraFields {
+    fn extract(obj: &'source PyAny) -> PyResult<Self> {
+        match json_from_py(obj)? {
+            serde_json::Value::Object(map) => Ok(Self(map.into_iter().collect())),
+            _ => Err(PyTypeError::new_err("The extra fields must be a dict.")),
+        }
+    }
+}
+
+fn json_to_py(value: serde_json::Value, py: Python<'_>) -> PyObject {
+    match value {
+        serde_json::Value::Null => py.None(),
+        serde_json::Value::Bool(value) => value.into_py(py),
+        serde_json::Value::Number(number) => {
+            if let Some(value) = number.as_u64() {
+                value.into_py(py)
+            } else if let Some(value) = number.as_i64() {
+                value.into_py(py)
+            } else {
+                number.as_f64().unwrap_or(f64::NAN).into_py(py)
+            }
+        }
+        serde_json::Value::String(value) => value.into_py(py),
+        serde_json::Value::Array(values) => values
+            .into_iter()
+            .map(|value| json_to_py(value, py))
+            .collect::<Vec<PyObject>>()
+            .into_py(py),
+        serde_json::Value::Object(map) => map
+            .into_iter()
+            .map(|(key, value)| (key, json_to_py(value, py)))
+            .collect::<BTreeMap<String, PyObject>>()
+            .into_py(py),
+    }
+}
+
+fn json_from_py(obj: &PyAny) -> PyResult<serde_json::Value> {
+    // Check for booleans first since they are also ints in python.
+    if obj.is_none() {
+        Ok(serde_json::Value::Null)
+    } else if obj.is_instance_of::<PyBool>()? {
+        Ok(serde_json::Value::Bool(obj.extract()?))
+    } else if obj.is_instance_of::<PyLong>()? {
+        obj.extract::<u64>().map_or_else(
+            |_| Ok(serde_json::Value::from(obj.extract::<i64>()?)),
+            |value| Ok(serde_json::Value::from(value)),
+        )
+    } else if obj.is_instance_of::<PyFloat>()? {
+        serde_json::Number::from_f64(obj.extract()?)
+            .map(serde_json::Value::Number)
+            .ok_or_else(|| {
+                PyValueError::new_err("NaN and infinite values cannot be stored in json.")
+            })
+    } else if obj.is_instance_of::<PyString>()? {
+        Ok(serde_json::Value::String(obj.extract()?))
+    } else if obj.is_instance_of::<PyList>()? || obj.is_instance_of::<PyTuple>()? {
+        obj.iter()?
+            .map(|item| json_from_py(item?))
+            .collect::<PyResult<Vec<_>>>()
+            .map(serde_json::Value::Array)
+    } else if let Ok(dict) = obj.downcast::<PyDict>() {
+        dict.iter()
+            .map(|(key, value)| Ok((key.extract::<String>()?, json_from_py(value)?)))
+            .collect::<PyResult<serde_json::Map<String, serde_json::Value>>>()
+            .map(serde_json::Value::Object)
+    } else {
+        Err(PyTypeError::new_err(format!(
+            "Cannot convert {obj} to json."
+        )))
+    }
+}
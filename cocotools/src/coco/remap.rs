@@ -0,0 +1,472 @@
+//! Module to rename, merge and delete the categories of a COCO dataset.
+//!
+//! The mapping is a list of rules, usually loaded from a json file (see [`load_mapping`]) such as:
+//! ```json
+//! [
+//!     {"from": "automobile", "to": "car"},
+//!     {"from": "truck", "to": "car"},
+//!     {"from": 12, "to": {"id": 3, "name": "bicycle", "supercategory": "vehicle"}},
+//!     {"from": "trash", "to": null}
+//! ]
+//! ```
+//! The categories are referred to by id or by name. The target of a rule can be a name, an id,
+//! a new category with an id, a name and/or a supercategory, or `null` to delete the category and its annotations.
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::coco::object_detection::{Category, HashmapDataset};
+use crate::errors::RemapError;
+
+/// Category of the dataset, given by its id or its name.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum CategoryRef {
+    Id(u32),
+    Name(String),
+}
+
+impl CategoryRef {
+    fn matches(&self, cat: &Category) -> bool {
+        match self {
+            Self::Id(id) => cat.id == *id,
+            Self::Name(name) => cat.name == *name,
+        }
+    }
+}
+
+impl fmt::Display for CategoryRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Id(id) => write!(f, "{id}"),
+            Self::Name(name) => write!(f, "`{name}`"),
+        }
+    }
+}
+
+/// Category that the annotations of the matched categories are moved to.
+///
+/// The fields which are not given are taken from the old category, or from the existing category the annotations are merged into:
+/// - With only a name, the annotations are merged into the category with this name if there is one, otherwise the category is renamed.
+/// - With only an id, the annotations are merged into the category with this id if there is one, otherwise the category is given this id.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(from = "TargetRepr")]
+pub struct CategoryTarget {
+    pub id: Option<u32>,
+    pub name: Option<String>,
+    pub supercategory: Option<String>,
+}
+
+/// The different ways a target can be written in the mapping file.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TargetRepr {
+    Id(u32),
+    Name(String),
+    Full(FullTarget),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FullTarget {
+    id: Option<u32>,
+    name: Option<String>,
+    supercategory: Option<String>,
+}
+
+impl From<TargetRepr> for CategoryTarget {
+    fn from(target: TargetRepr) -> Self {
+        match target {
+            TargetRepr::Id(id) => Self {
+                id: Some(id),
+                ..Default::default()
+            },
+            TargetRepr::Name(name) => Self {
+                name: Some(name),
+                ..Default::default()
+            },
+            TargetRepr::Full(FullTarget {
+                id,
+                name,
+                supercategory,
+            }) => Self {
+                id,
+                name,
+                supercategory,
+            },
+        }
+    }
+}
+
+/// Rule of a category mapping: the annotations of the `from` categories are moved to the `to` category, or deleted if `to` is `None`.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MappingRule {
+    pub from: CategoryRef,
+    /// Required in the json file, where deleting a category has to be explicit (`"to": null`).
+    #[serde(deserialize_with = "deserialize_target")]
+    pub to: Option<CategoryTarget>,
+}
+
+fn deserialize_target<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<CategoryTarget>, D::Error> {
+    Option::deserialize(deserializer)
+}
+
+/// Load a json file with a list of mapping rules, see the [module documentation](self) for the format.
+///
+/// # Errors
+///
+/// Will return `Err` if the file cannot be read or parsed.
+pub fn load_mapping<P: AsRef<Path>>(path: P) -> Result<Vec<MappingRule>, RemapError> {
+    let path = path.as_ref();
+    let content =
+        fs::read_to_string(path).map_err(|err| RemapError::Read(err, path.to_path_buf()))?;
+    serde_json::from_str(&content).map_err(|err| RemapError::Json(err, path.to_path_buf()))
+}
+
+/// What happened to the annotations of a category affected by the mapping.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct CategoryChange {
+    pub old_id: u32,
+    pub old_name: String,
+    /// `None` if the category was deleted.
+    pub new_id: Option<u32>,
+    /// `None` if the category was deleted.
+    pub new_name: Option<String>,
+    /// Number of annotations moved to the new category (or deleted).
+    pub annotations: usize,
+}
+
+/// Report of the changes made by [`HashmapDataset::remap_categories`].
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct RemapReport {
+    /// The categories matched by a rule, in the order of the dataset.
+    pub changes: Vec<CategoryChange>,
+    /// The `from` of the rules which did not match any category of the dataset.
+    pub unmatched: Vec<CategoryRef>,
+}
+
+impl fmt::Display for RemapReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in &self.changes {
+            write!(f, "`{}` ({}) -> ", change.old_name, change.old_id)?;
+            match (&change.new_name, change.new_id) {
+                (Some(new_name), Some(new_id)) => write!(f, "`{new_name}` ({new_id})")?,
+                _ => write!(f, "deleted")?,
+            }
+            writeln!(f, ": {} annotation(s)", change.annotations)?;
+        }
+        for category in &self.unmatched {
+            writeln!(f, "no category matches {category}")?;
+        }
+        write!(
+            f,
+            "{} annotation(s) affected",
+            self.changes
+                .iter()
+                .map(|change| change.annotations)
+                .sum::<usize>()
+        )
+    }
+}
+
+impl HashmapDataset {
+    /// Apply a category mapping to the dataset: rename, merge and delete categories, and move or delete their annotations.
+    ///
+    /// The categories which are not matched by a rule are kept as they are. A new category keeps the id of the (first) old
+    /// category it comes from when it is not used by another category, otherwise it is given a new id after the largest one.
+    /// The categories are then ordered by id.
+    ///
+    /// ## Args
+    /// - `rules`: The mapping rules, see [`MappingRule`].
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if a category is matched by several rules, or if a target would give a category the id or the name of
+    /// a different category. The dataset is not modified in that case.
+    pub fn remap_categories(&mut self, rules: &[MappingRule]) -> Result<RemapReport, RemapError> {
+        let cats: Vec<Category> = self.get_cats().into_iter().cloned().collect();
+        let mut report = RemapReport::default();
+
+        // Target of each category matched by a rule, `None` for the deleted categories.
+        let mut targets: HashMap<u32, Option<&CategoryTarget>> = HashMap::new();
+        for rule in rules {
+            let mut matched = false;
+            for cat in cats.iter().filter(|cat| rule.from.matches(cat)) {
+                matched = true;
+                if targets.insert(cat.id, rule.to.as_ref()).is_some() {
+                    return Err(RemapError::Conflict(format!(
+                        "the category `{}` ({}) is matched by several rules.",
+                        cat.name, cat.id
+                    )));
+                }
+            }
+            if !matched {
+                report.unmatched.push(rule.from.clone());
+            }
+        }
+
+        let target_ids: HashSet<u32> = targets
+            .values()
+            .filter_map(|target| target.and_then(|target| target.id))
+            .collect();
+        let mut next_id = cats
+            .iter()
+            .map(|cat| cat.id)
+            .chain(target_ids.iter().copied())
+            .max()
+            .unwrap_or_default()
+            + 1;
+        let mut new_cats: BTreeMap<u32, Category> = cats
+            .iter()
+            .filter(|cat| !targets.contains_key(&cat.id))
+            .map(|cat| (cat.id, cat.clone()))
+            .collect();
+        // New id of each category matched by a rule, `None` for the deleted categories.
+        let mut new_ids: HashMap<u32, Option<u32>> = HashMap::new();
+
+        for cat in &cats {
+            let target = match targets.get(&cat.id) {
+                Some(Some(target)) => target,
+                Some(None) => {
+                    new_ids.insert(cat.id, None);
+                    continue;
+                }
+                None => continue,
+            };
+            let name = target.name.as_ref().unwrap_or(&cat.name);
+            let same_name_id = new_cats
+                .values()
+                .find(|new_cat| new_cat.name == *name)
+                .map(|new_cat| new_cat.id);
+            let new_id = match (target.id, same_name_id) {
+                (Some(id), Some(same_name_id)) if target.name.is_some() && id != same_name_id => {
+                    return Err(RemapError::Conflict(format!(
+                        "the name `{name}` of the category {id} is already used by the category {same_name_id}."
+                    )));
+                }
+                (Some(id), _) => id,
+                (None, Some(same_name_id)) => same_name_id,
+                (None, None)
+                    if !new_cats.contains_key(&cat.id) && !target_ids.contains(&cat.id) =>
+                {
+                    cat.id
+                }
+                (None, None) => {
+                    next_id += 1;
+                    next_id - 1
+                }
+            };
+
+            match new_cats.get_mut(&new_id) {
+                Some(new_cat) if target.name.is_some() && new_cat.name != *name => {
+                    return Err(RemapError::Conflict(format!(
+                        "the id {new_id} of the category `{name}` is already used by the category `{}`.",
+                        new_cat.name
+                    )));
+                }
+                Some(new_cat) => {
+                    if let Some(supercategory) = &target.supercategory {
+                        new_cat.supercategory = supercategory.clone();
+                    }
+                }
+                None => {
+                    new_cats.insert(
+                        new_id,
+                        Category {
+                            id: new_id,
+                            name: name.clone(),
+                            supercategory: target
+                                .supercategory
+                                .clone()
+                                .unwrap_or_else(|| cat.supercategory.clone()),
+                            ..cat.clone()
+                        },
+                    );
+                }
+            }
+            new_ids.insert(cat.id, Some(new_id));
+        }
+
+        let mut nb_anns: HashMap<u32, usize> = HashMap::new();
+        for ann in self.get_anns() {
+            *nb_anns.entry(ann.category_id).or_default() += 1;
+        }
+        for cat in &cats {
+            if let Some(new_id) = new_ids.get(&cat.id) {
+                report.changes.push(CategoryChange {
+                    old_id: cat.id,
+                    old_name: cat.name.clone(),
+                    new_id: *new_id,
+                    new_name: new_id.map(|new_id| new_cats[&new_id].name.clone()),
+                    annotations: nb_anns.get(&cat.id).copied().unwrap_or_default(),
+                });
+            }
+        }
+
+        self.retain_anns(|ann| new_ids.get(&ann.category_id) != Some(&None));
+        for ann in self.anns.values_mut() {
+            if let Some(Some(new_id)) = new_ids.get(&ann.category_id) {
+                ann.category_id = *new_id;
+            }
+        }
+        self.set_cats(new_cats.into_values().collect());
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+    use crate::coco::fixtures::{self, ann, img};
+    use crate::coco::object_detection::Dataset;
+
+    fn cat(id: u32, name: &str) -> Category {
+        Category {
+            supercategory: "object".to_string(),
+            ..fixtures::cat(id, name)
+        }
+    }
+
+    fn dataset() -> HashmapDataset {
+        let dataset = Dataset {
+            images: vec![img(1, "1.jpg")],
+            annotations: vec![
+                ann(1, 1, 1),
+                ann(2, 1, 2),
+                ann(3, 1, 2),
+                ann(4, 1, 3),
+                ann(5, 1, 4),
+                ann(6, 1, 5),
+            ],
+            categories: vec![
+                cat(1, "car"),
+                cat(2, "automobile"),
+                cat(3, "truck"),
+                cat(4, "trash"),
+                cat(5, "bike"),
+            ],
+            ..Default::default()
+        };
+        HashmapDataset::from_dataset(dataset, "N/A").unwrap()
+    }
+
+    fn rules(json: &str) -> Vec<MappingRule> {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn cats(dataset: &HashmapDataset) -> Vec<(u32, &str, &str)> {
+        dataset
+            .get_cats()
+            .iter()
+            .map(|cat| (cat.id, cat.name.as_str(), cat.supercategory.as_str()))
+            .collect()
+    }
+
+    fn ann_cats(dataset: &HashmapDataset) -> Vec<(u64, u32)> {
+        dataset
+            .get_anns()
+            .iter()
+            .map(|ann| (ann.id, ann.category_id))
+            .collect()
+    }
+
+    #[test]
+    fn remap() {
+        let mut dataset = dataset();
+        let report = dataset
+            .remap_categories(&rules(
+                r#"[
+                    {"from": "automobile", "to": "car"},
+                    {"from": 3, "to": {"name": "vehicle", "supercategory": "vehicle"}},
+                    {"from": "trash", "to": null},
+                    {"from": "bike", "to": {"id": 10, "name": "bicycle"}},
+                    {"from": "unicorn", "to": "horse"}
+                ]"#,
+            ))
+            .unwrap();
+
+        assert_eq!(
+            cats(&dataset),
+            vec![
+                (1, "car", "object"),
+                (3, "vehicle", "vehicle"),
+                (10, "bicycle", "object")
+            ]
+        );
+        assert_eq!(
+            ann_cats(&dataset),
+            vec![(1, 1), (2, 1), (3, 1), (4, 3), (6, 10)]
+        );
+        assert_eq!(dataset.get_img_anns(1).unwrap().len(), 5);
+
+        let changes: Vec<(u32, Option<u32>, usize)> = report
+            .changes
+            .iter()
+            .map(|change| (change.old_id, change.new_id, change.annotations))
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                (2, Some(1), 2),
+                (3, Some(3), 1),
+                (4, None, 1),
+                (5, Some(10), 1)
+            ]
+        );
+        assert_eq!(
+            report.unmatched,
+            vec![CategoryRef::Name("unicorn".to_string())]
+        );
+        assert!(report.to_string().ends_with("5 annotation(s) affected"));
+    }
+
+    #[rstest]
+    #[case::merge_into_id(r#"[{"from": "truck", "to": 1}]"#, vec![(1, "car"), (2, "automobile")])]
+    #[case::swap_names(
+        r#"[{"from": 1, "to": "automobile"}, {"from": 2, "to": "car"}]"#,
+        vec![(1, "automobile"), (2, "car"), (3, "truck")]
+    )]
+    #[case::new_id_taken(
+        r#"[{"from": "car", "to": "vehicle"}, {"from": "truck", "to": {"id": 1, "name": "lorry"}}]"#,
+        vec![(1, "lorry"), (2, "automobile"), (4, "vehicle")]
+    )]
+    fn remap_ids(#[case] json: &str, #[case] expected: Vec<(u32, &str)>) {
+        let mut dataset = dataset();
+        dataset.retain_cats(|cat| cat.id <= 3);
+        dataset.remap_categories(&rules(json)).unwrap();
+        let cats: Vec<(u32, &str)> = cats(&dataset)
+            .into_iter()
+            .map(|(id, name, _)| (id, name))
+            .collect();
+        assert_eq!(cats, expected);
+    }
+
+    #[rstest]
+    #[case::matched_twice(r#"[{"from": "car", "to": "vehicle"}, {"from": 1, "to": null}]"#)]
+    #[case::id_taken(r#"[{"from": "truck", "to": {"id": 1, "name": "lorry"}}]"#)]
+    #[case::name_taken(r#"[{"from": "truck", "to": {"id": 8, "name": "car"}}]"#)]
+    fn conflict(#[case] json: &str) {
+        let mut dataset = dataset();
+        assert!(matches!(
+            dataset.remap_categories(&rules(json)),
+            Err(RemapError::Conflict(_))
+        ));
+        assert_eq!(dataset, self::dataset());
+    }
+
+    #[rstest]
+    #[case::missing_target(r#"[{"from": "car"}]"#)]
+    #[case::unknown_field(r#"[{"from": "car", "to": {"nam": "vehicle"}}]"#)]
+    fn invalid_rules(#[case] json: &str) {
+        assert!(serde_json::from_str::<Vec<MappingRule>>(json).is_err());
+    }
+}
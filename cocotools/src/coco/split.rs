@@ -0,0 +1,507 @@
+//! Module to split a COCO dataset into several datasets (train/val/test for example).
+//!
+//! The splits are done at the image level, all the annotations of an image end up in the same split.
+use std::collections::BTreeMap;
+use std::fmt;
+
+use indexmap::IndexMap;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use serde::Serialize;
+
+use crate::coco::object_detection::{HashmapDataset, Image};
+use crate::errors::{MissingIdError, SplitError};
+
+/// Images that must end up in the same split, with their number of annotations for each category.
+pub(crate) struct SplitUnit {
+    pub img_ids: Vec<u64>,
+    pub cat_counts: BTreeMap<u32, usize>,
+}
+
+impl SplitUnit {
+    pub(crate) fn new(dataset: &HashmapDataset, img_ids: Vec<u64>) -> Result<Self, MissingIdError> {
+        let mut cat_counts = BTreeMap::new();
+        for img_id in &img_ids {
+            for ann in dataset.get_img_anns(*img_id)? {
+                *cat_counts.entry(ann.category_id).or_default() += 1;
+            }
+        }
+        Ok(Self {
+            img_ids,
+            cat_counts,
+        })
+    }
+}
+
+impl HashmapDataset {
+    /// Split the dataset by image, with the given proportion of images in each split.
+    ///
+    /// Each split keeps all the categories of the dataset (as well as the `info`, `licenses` and extra fields),
+    /// and the entries keep their ids, which makes each split a valid COCO dataset.
+    ///
+    /// When `stratify` is true, the images are assigned with an iterative stratification: the images of the category with
+    /// the fewest annotations left are assigned first, each to the split which needs the most annotations of that category.
+    /// This way each split gets (approximately) its share of the annotations of every category, even the rare ones.
+    ///
+    /// ## Args
+    /// - `ratios`: The proportion of images of each split, for example `[0.8, 0.1, 0.1]`. The ratios are normalized, so `[8.0, 1.0, 1.0]` gives the same split.
+    /// - `seed`: The seed used to shuffle the images, the same seed always gives the same split.
+    /// - `stratify`: If true, balance the annotations of each category across the splits.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if `ratios` is empty or has a ratio which is not a positive number.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::path::PathBuf;
+    /// use cocotools::COCO;
+    ///
+    /// let dataset = COCO::new(
+    ///     PathBuf::from("../data_samples/coco_25k/annotations.json"),
+    ///     PathBuf::from("../data_samples/coco_25k/images"),
+    /// )?;
+    /// let splits = dataset.split(&[0.8, 0.2], 42, true)?;
+    /// assert_eq!(splits.len(), 2);
+    /// assert_eq!(splits[0].get_imgs().len() + splits[1].get_imgs().len(), dataset.get_imgs().len());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn split(
+        &self,
+        ratios: &[f64],
+        seed: u64,
+        stratify: bool,
+    ) -> Result<Vec<Self>, SplitError> {
+        let units = self
+            .get_imgs()
+            .iter()
+            .map(|img| SplitUnit::new(self, vec![img.id]))
+            .collect::<Result<Vec<SplitUnit>, MissingIdError>>()?;
+        let assignment = assign_units(&units, ratios, seed, stratify)?;
+
+        let mut split_img_ids = vec![Vec::new(); ratios.len()];
+        for (unit, split) in units.into_iter().zip(assignment) {
+            split_img_ids[split].extend(unit.img_ids);
+        }
+        Ok(split_img_ids
+            .iter()
+            .map(|img_ids| self.subset(img_ids))
+            .collect::<Result<Vec<Self>, MissingIdError>>()?)
+    }
+
+    /// Split the dataset by image into `k` disjoint folds, and return the `k` (train, val) pairs of datasets.
+    ///
+    /// The val dataset of the i-th pair has the images of the i-th fold, and the train dataset has the images of all the other folds.
+    /// Like with [`HashmapDataset::split`], all the datasets keep all the categories and the ids of the entries.
+    ///
+    /// ## Args
+    /// - `k`: The number of folds.
+    /// - `seed`: The seed used to shuffle the images.
+    /// - `stratify`: If true, balance the annotations of each category across the folds (see [`HashmapDataset::split`]).
+    /// - `group_by`: Name of an image field (`date_captured` for example, or a field not defined by the COCO format such as a capture session).
+    ///   The images with the same value for this field are put in the same fold, so that near-duplicate images do not end up in both the train and val datasets.
+    ///   The images without the field (or with an empty value) are not grouped.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if `k` is less than 2, or if there are less than `k` images (or groups of images).
+    pub fn k_fold(
+        &self,
+        k: usize,
+        seed: u64,
+        stratify: bool,
+        group_by: Option<&str>,
+    ) -> Result<Vec<(Self, Self)>, SplitError> {
+        if k < 2 {
+            return Err(SplitError::InvalidFoldNumber(k));
+        }
+        let mut groups: IndexMap<Option<String>, Vec<u64>> = IndexMap::new();
+        let mut units = Vec::new();
+        for img in self.get_imgs() {
+            match group_by.and_then(|field| image_field(img, field)) {
+                Some(value) => groups.entry(Some(value)).or_default().push(img.id),
+                None => units.push(SplitUnit::new(self, vec![img.id])?),
+            }
+        }
+        for img_ids in groups.into_values() {
+            units.push(SplitUnit::new(self, img_ids)?);
+        }
+        if units.len() < k {
+            return Err(SplitError::NotEnoughGroups(k, units.len()));
+        }
+        let assignment = assign_units(&units, &vec![1.0; k], seed, stratify)?;
+
+        (0..k)
+            .map(|fold| {
+                let (val_units, train_units): (Vec<_>, Vec<_>) = units
+                    .iter()
+                    .zip(&assignment)
+                    .partition(|(_, unit_fold)| **unit_fold == fold);
+                let img_ids = |units: Vec<(&SplitUnit, &usize)>| -> Vec<u64> {
+                    units
+                        .into_iter()
+                        .flat_map(|(unit, _)| unit.img_ids.iter().copied())
+                        .collect()
+                };
+                Ok((
+                    self.subset(&img_ids(train_units))?,
+                    self.subset(&img_ids(val_units))?,
+                ))
+            })
+            .collect()
+    }
+
+    /// Returns the number of annotations of each category, in the order of the categories (unused categories have a count of 0).
+    #[must_use]
+    pub fn category_counts(&self) -> Vec<(u32, usize)> {
+        let mut counts: BTreeMap<u32, usize> = BTreeMap::new();
+        for ann in self.get_anns() {
+            *counts.entry(ann.category_id).or_default() += 1;
+        }
+        self.get_cats()
+            .iter()
+            .map(|cat| (cat.id, counts.get(&cat.id).copied().unwrap_or_default()))
+            .collect()
+    }
+}
+
+/// Number of images and of annotations of each category in the train and val datasets of a fold.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct FoldSummary {
+    pub train_images: usize,
+    pub val_images: usize,
+    pub categories: Vec<FoldCategoryCount>,
+}
+
+/// Number of annotations of a category in the train and val datasets of a fold.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct FoldCategoryCount {
+    pub id: u32,
+    pub name: String,
+    pub train: usize,
+    pub val: usize,
+}
+
+impl FoldSummary {
+    /// Summarize a fold from its train and val datasets, which are expected to have the same categories.
+    #[must_use]
+    pub fn new(train: &HashmapDataset, val: &HashmapDataset) -> Self {
+        let val_counts: BTreeMap<u32, usize> = val.category_counts().into_iter().collect();
+        let categories = train
+            .category_counts()
+            .into_iter()
+            .map(|(cat_id, train_count)| FoldCategoryCount {
+                id: cat_id,
+                name: train
+                    .get_cat(cat_id)
+                    .map(|cat| cat.name.clone())
+                    .unwrap_or_default(),
+                train: train_count,
+                val: val_counts.get(&cat_id).copied().unwrap_or_default(),
+            })
+            .collect();
+        Self {
+            train_images: train.get_imgs().len(),
+            val_images: val.get_imgs().len(),
+            categories,
+        }
+    }
+}
+
+/// Display the counts of the categories which have annotations.
+impl fmt::Display for FoldSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} train images, {} val images",
+            self.train_images, self.val_images
+        )?;
+        for cat in self
+            .categories
+            .iter()
+            .filter(|cat| cat.train > 0 || cat.val > 0)
+        {
+            write!(f, "\n  {}: {} train, {} val", cat.name, cat.train, cat.val)?;
+        }
+        Ok(())
+    }
+}
+
+/// Value of a field of the image as a string, `None` if the image does not have the field or if its value is empty.
+fn image_field(img: &Image, field: &str) -> Option<String> {
+    let value = match field {
+        "id" => img.id.to_string(),
+        "width" => img.width.to_string(),
+        "height" => img.height.to_string(),
+        "file_name" => img.file_name.clone(),
+        "license" => img.license.to_string(),
+        "flickr_url" => img.flickr_url.clone(),
+        "coco_url" => img.coco_url.clone(),
+        "date_captured" => img.date_captured.clone(),
+        _ => match img.extra.get(field)? {
+            serde_json::Value::Null => return None,
+            serde_json::Value::String(value) => value.clone(),
+            value => value.to_string(),
+        },
+    };
+    if value.is_empty() {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+/// Assign each unit to a split, and return the index of the split of each unit.
+///
+/// The units are shuffled with the seed, then each one is given to the split which needs the most images
+/// (and when stratifying, to the split which needs the most annotations of the category being assigned).
+#[allow(clippy::cast_precision_loss)]
+pub(crate) fn assign_units(
+    units: &[SplitUnit],
+    ratios: &[f64],
+    seed: u64,
+    stratify: bool,
+) -> Result<Vec<usize>, SplitError> {
+    if ratios.is_empty()
+        || ratios
+            .iter()
+            .any(|ratio| !ratio.is_finite() || *ratio <= 0.0)
+    {
+        return Err(SplitError::InvalidRatios(ratios.to_vec()));
+    }
+    let total_ratio: f64 = ratios.iter().sum();
+    let ratios: Vec<f64> = ratios.iter().map(|ratio| ratio / total_ratio).collect();
+
+    let mut order: Vec<usize> = (0..units.len()).collect();
+    order.shuffle(&mut StdRng::seed_from_u64(seed));
+
+    let nb_imgs: usize = units.iter().map(|unit| unit.img_ids.len()).sum();
+    // Number of images that each split still needs.
+    let mut needed_imgs: Vec<f64> = ratios.iter().map(|ratio| ratio * nb_imgs as f64).collect();
+    let mut assignment = vec![0; units.len()];
+
+    if stratify {
+        // Number of annotations of each category that are not assigned yet.
+        let mut remaining: BTreeMap<u32, usize> = BTreeMap::new();
+        for unit in units {
+            for (cat_id, count) in &unit.cat_counts {
+                *remaining.entry(*cat_id).or_default() += count;
+            }
+        }
+        // Number of annotations of each category that each split still needs.
+        let mut needed_anns: Vec<BTreeMap<u32, f64>> = ratios
+            .iter()
+            .map(|ratio| {
+                remaining
+                    .iter()
+                    .map(|(cat_id, count)| (*cat_id, ratio * *count as f64))
+                    .collect()
+            })
+            .collect();
+
+        while let Some(cat_id) = remaining
+            .iter()
+            .filter(|(_, count)| **count > 0)
+            .min_by_key(|(_, count)| **count)
+            .map(|(cat_id, _)| *cat_id)
+        {
+            let (with_cat, without_cat): (Vec<usize>, Vec<usize>) = order
+                .into_iter()
+                .partition(|unit_idx| units[*unit_idx].cat_counts.contains_key(&cat_id));
+            for unit_idx in with_cat {
+                let unit = &units[unit_idx];
+                let split = neediest_split(&needed_imgs, |split| needed_anns[split][&cat_id]);
+                assignment[unit_idx] = split;
+                needed_imgs[split] -= unit.img_ids.len() as f64;
+                for (unit_cat_id, count) in &unit.cat_counts {
+                    *needed_anns[split].entry(*unit_cat_id).or_default() -= *count as f64;
+                    *remaining.entry(*unit_cat_id).or_default() -= count;
+                }
+            }
+            order = without_cat;
+        }
+    }
+
+    // The units left are all the units when not stratifying, and the units without annotations otherwise.
+    for unit_idx in order {
+        let split = neediest_split(&needed_imgs, |_| 0.0);
+        assignment[unit_idx] = split;
+        needed_imgs[split] -= units[unit_idx].img_ids.len() as f64;
+    }
+    Ok(assignment)
+}
+
+/// Index of the split with the highest `needed(split)`, ties are broken by the number of images needed, and then by the index.
+fn neediest_split<F: Fn(usize) -> f64>(needed_imgs: &[f64], needed: F) -> usize {
+    let mut best = 0;
+    for split in 1..needed_imgs.len() {
+        if (needed(split), needed_imgs[split]) > (needed(best), needed_imgs[best]) {
+            best = split;
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::collections::HashSet;
+
+    use rstest::rstest;
+
+    use super::*;
+    use crate::coco::fixtures::{ann, cat, img};
+    use crate::coco::object_detection::{Dataset, ExtraFields, Image};
+
+    /// 20 images, all with a dog. One image out of 5 also has two cats, and the last two images have nothing.
+    /// The images are in capture sessions of 3 images.
+    fn dataset() -> HashmapDataset {
+        let mut dataset = Dataset {
+            categories: vec![cat(1, "dog"), cat(2, "cat"), cat(3, "bird")],
+            ..Default::default()
+        };
+        for img_id in 1..=22 {
+            let mut extra = ExtraFields::default();
+            extra.insert("session".to_string(), ((img_id - 1) / 3).into());
+            dataset.images.push(Image {
+                extra,
+                ..img(img_id, &format!("{img_id}.jpg"))
+            });
+            let category_ids: &[u32] = if img_id > 20 {
+                &[]
+            } else if img_id % 5 == 0 {
+                &[1, 2, 2]
+            } else {
+                &[1]
+            };
+            for category_id in category_ids {
+                let ann_id = dataset.annotations.len() as u64 + 1;
+                dataset.annotations.push(ann(ann_id, img_id, *category_id));
+            }
+        }
+        HashmapDataset::from_dataset(dataset, "N/A").unwrap()
+    }
+
+    fn img_ids(dataset: &HashmapDataset) -> Vec<u64> {
+        dataset.get_imgs().iter().map(|img| img.id).collect()
+    }
+
+    #[rstest]
+    #[case::random(false)]
+    #[case::stratified(true)]
+    fn split_images(#[case] stratify: bool) {
+        let dataset = dataset();
+        let splits = dataset.split(&[0.5, 0.25, 0.25], 0, stratify).unwrap();
+
+        let sizes: Vec<usize> = splits.iter().map(|split| split.get_imgs().len()).collect();
+        assert!(sizes.iter().all(|size| *size >= 5), "{sizes:?}");
+        assert_eq!(sizes.iter().sum::<usize>(), 22);
+
+        let all_img_ids: HashSet<u64> = splits.iter().flat_map(img_ids).collect();
+        assert_eq!(all_img_ids.len(), 22);
+        for split in &splits {
+            assert_eq!(split.get_cats().len(), 3);
+            for ann in split.get_anns() {
+                assert!(split.get_img(ann.image_id).is_ok());
+            }
+        }
+        assert_eq!(
+            splits
+                .iter()
+                .map(|split| split.get_anns().len())
+                .sum::<usize>(),
+            dataset.get_anns().len()
+        );
+
+        let same_seed = dataset.split(&[0.5, 0.25, 0.25], 0, stratify).unwrap();
+        assert_eq!(splits, same_seed);
+    }
+
+    #[test]
+    fn stratified_split_balances_categories() {
+        let splits = dataset().split(&[1.0, 1.0], 1, true).unwrap();
+        for split in &splits {
+            assert_eq!(split.category_counts(), vec![(1, 10), (2, 4), (3, 0)]);
+            assert_eq!(split.get_imgs().len(), 11);
+        }
+    }
+
+    #[rstest]
+    #[case::random(false, None)]
+    #[case::stratified(true, None)]
+    #[case::grouped(false, Some("session"))]
+    #[case::grouped_stratified(true, Some("session"))]
+    fn k_fold(#[case] stratify: bool, #[case] group_by: Option<&str>) {
+        let dataset = dataset();
+        let folds = dataset.k_fold(4, 0, stratify, group_by).unwrap();
+        assert_eq!(folds.len(), 4);
+
+        let mut val_img_ids = HashSet::new();
+        for (train, val) in &folds {
+            assert!(!val.get_imgs().is_empty());
+            assert_eq!(train.get_imgs().len() + val.get_imgs().len(), 22);
+            let train_img_ids: HashSet<u64> = img_ids(train).into_iter().collect();
+            for img_id in img_ids(val) {
+                assert!(!train_img_ids.contains(&img_id));
+                assert!(val_img_ids.insert(img_id));
+            }
+            if group_by.is_some() {
+                // The images are grouped by 3 (1, 2 and 3 together, then 4, 5 and 6, etc.)
+                for img_id in img_ids(val) {
+                    let group_img_ids = ((img_id - 1) / 3 * 3 + 1)..=((img_id - 1) / 3 * 3 + 3);
+                    assert!(group_img_ids
+                        .filter(|id| *id <= 22)
+                        .all(|id| !train_img_ids.contains(&id)));
+                }
+            }
+        }
+        assert_eq!(val_img_ids.len(), 22);
+    }
+
+    #[test]
+    fn fold_summary() {
+        let dataset = dataset();
+        let folds = dataset.k_fold(2, 0, true, None).unwrap();
+        let summary = FoldSummary::new(&folds[0].0, &folds[0].1);
+        assert_eq!((summary.train_images, summary.val_images), (11, 11));
+        assert_eq!(
+            summary.categories[1],
+            FoldCategoryCount {
+                id: 2,
+                name: "cat".to_string(),
+                train: 4,
+                val: 4
+            }
+        );
+        assert_eq!(
+            summary.to_string().lines().nth(2),
+            Some("  cat: 4 train, 4 val")
+        );
+    }
+
+    #[test]
+    fn invalid_k_fold() {
+        assert!(matches!(
+            dataset().k_fold(1, 0, false, None),
+            Err(SplitError::InvalidFoldNumber(1))
+        ));
+        assert!(matches!(
+            dataset().k_fold(9, 0, false, Some("session")),
+            Err(SplitError::NotEnoughGroups(9, 8))
+        ));
+    }
+
+    #[rstest]
+    #[case::empty(&[])]
+    #[case::zero(&[0.8, 0.0])]
+    #[case::negative(&[1.2, -0.2])]
+    #[case::nan(&[f64::NAN])]
+    fn invalid_ratios(#[case] ratios: &[f64]) {
+        assert!(matches!(
+            dataset().split(ratios, 0, false),
+            Err(SplitError::InvalidRatios(_))
+        ));
+    }
+}
@@ -0,0 +1,573 @@
+//! Module to check the consistency of a COCO dataset.
+//!
+//! The checks are done on the [`Dataset`] as found in the json file, since the [`HashmapDataset`](crate::COCO) silently drops the entries with a duplicated id.
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::coco::object_detection::{
+    Annotation, Bbox, CocoRle, Dataset, Image, PolygonsRS, Rle, Segmentation,
+};
+use crate::mask::conversions::rle_from_poly;
+use crate::mask::utils::Area;
+
+/// Tolerance (in pixels) used when checking that a bounding box is inside its image.
+const BOUNDS_TOLERANCE: f64 = 1.0;
+/// Maximum relative difference between the `area` of an annotation and the area of its segmentation.
+const AREA_TOLERANCE: f64 = 0.1;
+/// Differences of area (in pixels) below this value are ignored, the rasterization of small polygons is not precise enough.
+const AREA_MIN_DIFFERENCE: f64 = 5.0;
+
+/// Severity of a validation issue.
+///
+/// Errors make the dataset unusable (or the results obtained with it wrong), while warnings point to entries that are most likely wrong.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// Kind of problem found by the validation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueKind {
+    DuplicateImageId,
+    DuplicateAnnotationId,
+    DuplicateCategoryId,
+    /// The annotation's image id is not in the dataset.
+    MissingImage,
+    /// The annotation's category id is not in the dataset.
+    MissingCategory,
+    BboxOutOfBounds,
+    /// An image or a bounding box has a zero or negative width/height.
+    NonPositiveSize,
+    /// The `area` of the annotation does not match the area of its segmentation.
+    AreaMismatch,
+    /// A polygon has an odd number of coordinates or less than 3 points.
+    InvalidPolygon,
+    /// The size of an RLE does not match the size of its image, or its counts do not cover the image.
+    RleSizeMismatch,
+    /// The compressed counts of a COCO RLE cannot be decoded.
+    InvalidRle,
+    /// `iscrowd` is neither 0 nor 1.
+    InvalidIscrowd,
+}
+
+/// Problem found in the dataset, with the ids of the entries concerned.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Issue {
+    pub severity: Severity,
+    pub kind: IssueKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image_id: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub annotation_id: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category_id: Option<u32>,
+    /// Human readable description of the issue.
+    pub message: String,
+}
+
+/// Result of the validation of a dataset.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct ValidationReport {
+    pub issues: Vec<Issue>,
+}
+
+impl ValidationReport {
+    /// Number of issues with the given severity.
+    #[must_use]
+    pub fn count(&self, severity: Severity) -> usize {
+        self.issues
+            .iter()
+            .filter(|issue| issue.severity == severity)
+            .count()
+    }
+
+    /// Returns `true` if at least one issue is an error.
+    #[must_use]
+    pub fn has_errors(&self) -> bool {
+        self.count(Severity::Error) > 0
+    }
+
+    /// Serialize the report to a json string, for example to be used by a CI job.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the report cannot be serialized.
+    pub fn json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+
+    fn push(&mut self, severity: Severity, kind: IssueKind, message: String) -> &mut Issue {
+        let idx = self.issues.len();
+        self.issues.push(Issue {
+            severity,
+            kind,
+            image_id: None,
+            annotation_id: None,
+            category_id: None,
+            message,
+        });
+        &mut self.issues[idx]
+    }
+
+    fn push_ann(
+        &mut self,
+        severity: Severity,
+        kind: IssueKind,
+        ann: &Annotation,
+        message: String,
+    ) -> &mut Issue {
+        let issue = self.push(severity, kind, message);
+        issue.annotation_id = Some(ann.id);
+        issue.image_id = Some(ann.image_id);
+        issue.category_id = Some(ann.category_id);
+        issue
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Warning => write!(f, "warning"),
+            Self::Error => write!(f, "error"),
+        }
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.severity, self.message)?;
+        let mut ids = Vec::new();
+        if let Some(id) = self.image_id {
+            ids.push(format!("image {id}"));
+        }
+        if let Some(id) = self.annotation_id {
+            ids.push(format!("annotation {id}"));
+        }
+        if let Some(id) = self.category_id {
+            ids.push(format!("category {id}"));
+        }
+        if !ids.is_empty() {
+            write!(f, " ({})", ids.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for issue in &self.issues {
+            writeln!(f, "{issue}")?;
+        }
+        write!(
+            f,
+            "{} error(s), {} warning(s)",
+            self.count(Severity::Error),
+            self.count(Severity::Warning)
+        )
+    }
+}
+
+/// Check the consistency of a dataset.
+///
+/// The following problems are reported:
+/// - Errors: duplicated image/annotation/category ids, annotations referencing an image or category not in the dataset,
+///   images with a non-positive size, invalid polygons (odd number of coordinates or less than 3 points),
+///   RLEs whose size does not match their image and `iscrowd` values other than 0 and 1.
+/// - Warnings: bounding boxes outside of their image or with a non-positive size,
+///   and an `area` that differs from the area of the segmentation by more than 10% (and 5 pixels).
+///
+/// ## Args
+/// - dataset: The dataset to validate, as loaded from the json file (see [`Dataset::load`]).
+///
+/// ## Example
+///
+/// ```
+/// # use cocotools::coco::object_detection::Dataset;
+/// use cocotools::coco::validate;
+///
+/// let dataset = Dataset::load("../data_samples/coco_25k/annotations.json")?;
+/// let report = validate::validate(&dataset);
+/// assert!(!report.has_errors());
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[must_use]
+pub fn validate(dataset: &Dataset) -> ValidationReport {
+    let mut report = ValidationReport::default();
+
+    let mut imgs: HashMap<u64, &Image> = HashMap::new();
+    for img in &dataset.images {
+        if imgs.insert(img.id, img).is_some() {
+            report
+                .push(
+                    Severity::Error,
+                    IssueKind::DuplicateImageId,
+                    format!("The image id {} is used by several images.", img.id),
+                )
+                .image_id = Some(img.id);
+        }
+        if img.width == 0 || img.height == 0 {
+            report
+                .push(
+                    Severity::Error,
+                    IssueKind::NonPositiveSize,
+                    format!(
+                        "The image {:?} has a size of {}x{}.",
+                        img.file_name, img.width, img.height
+                    ),
+                )
+                .image_id = Some(img.id);
+        }
+    }
+
+    let mut cat_ids = HashSet::new();
+    for cat in &dataset.categories {
+        if !cat_ids.insert(cat.id) {
+            report
+                .push(
+                    Severity::Error,
+                    IssueKind::DuplicateCategoryId,
+                    format!("The category id {} is used by several categories.", cat.id),
+                )
+                .category_id = Some(cat.id);
+        }
+    }
+
+    let mut ann_ids = HashSet::new();
+    for ann in &dataset.annotations {
+        if !ann_ids.insert(ann.id) {
+            report.push_ann(
+                Severity::Error,
+                IssueKind::DuplicateAnnotationId,
+                ann,
+                format!(
+                    "The annotation id {} is used by several annotations.",
+                    ann.id
+                ),
+            );
+        }
+        if !cat_ids.contains(&ann.category_id) {
+            report.push_ann(
+                Severity::Error,
+                IssueKind::MissingCategory,
+                ann,
+                format!(
+                    "The category {} of the annotation is not in the dataset.",
+                    ann.category_id
+                ),
+            );
+        }
+        if ann.iscrowd > 1 {
+            report.push_ann(
+                Severity::Error,
+                IssueKind::InvalidIscrowd,
+                ann,
+                format!("iscrowd should be 0 or 1, but is {}.", ann.iscrowd),
+            );
+        }
+        match imgs.get(&ann.image_id) {
+            // The image size is already reported, checking the annotation against it would only add noise.
+            Some(img) if img.width == 0 || img.height == 0 => {}
+            Some(img) => validate_ann(&mut report, ann, img),
+            None => {
+                report.push_ann(
+                    Severity::Error,
+                    IssueKind::MissingImage,
+                    ann,
+                    format!(
+                        "The image {} of the annotation is not in the dataset.",
+                        ann.image_id
+                    ),
+                );
+            }
+        }
+    }
+
+    report
+}
+
+/// Checks of an annotation that require its image.
+fn validate_ann(report: &mut ValidationReport, ann: &Annotation, img: &Image) {
+    let bbox = &ann.bbox;
+    if bbox.width <= 0.0 || bbox.height <= 0.0 {
+        report.push_ann(
+            Severity::Warning,
+            IssueKind::NonPositiveSize,
+            ann,
+            format!(
+                "The bounding box has a size of {}x{}.",
+                bbox.width, bbox.height
+            ),
+        );
+    }
+    if !bbox_in_bounds(bbox, img) {
+        report.push_ann(
+            Severity::Warning,
+            IssueKind::BboxOutOfBounds,
+            ann,
+            format!(
+                "The bounding box [{}, {}, {}, {}] is outside of the {}x{} image.",
+                bbox.left, bbox.top, bbox.width, bbox.height, img.width, img.height
+            ),
+        );
+    }
+
+    if let Some(area) = segmentation_area(report, ann, img) {
+        let area = f64::from(area);
+        let difference = (ann.area - area).abs();
+        if difference > AREA_TOLERANCE * area && difference > AREA_MIN_DIFFERENCE {
+            report.push_ann(
+                Severity::Warning,
+                IssueKind::AreaMismatch,
+                ann,
+                format!(
+                    "The area is {}, but the segmentation has an area of {area}.",
+                    ann.area
+                ),
+            );
+        }
+    }
+}
+
+fn bbox_in_bounds(bbox: &Bbox, img: &Image) -> bool {
+    bbox.left >= -BOUNDS_TOLERANCE
+        && bbox.top >= -BOUNDS_TOLERANCE
+        && bbox.left + bbox.width <= f64::from(img.width) + BOUNDS_TOLERANCE
+        && bbox.top + bbox.height <= f64::from(img.height) + BOUNDS_TOLERANCE
+}
+
+/// Check the segmentation of the annotation, and return its area if it is valid and not empty.
+fn segmentation_area(report: &mut ValidationReport, ann: &Annotation, img: &Image) -> Option<u32> {
+    let size = vec![img.height, img.width];
+    let polygons = match &ann.segmentation {
+        Segmentation::Polygons(counts) => counts,
+        Segmentation::PolygonsRS(poly) => &poly.counts,
+        Segmentation::Rle(rle) => return rle_area(report, ann, rle, &size),
+        Segmentation::CocoRle(coco_rle) => {
+            if !check_rle_size(report, ann, &coco_rle.size, &size) {
+                return None;
+            }
+            if !coco_rle_is_valid(coco_rle) {
+                report.push_ann(
+                    Severity::Error,
+                    IssueKind::InvalidRle,
+                    ann,
+                    "The counts of the COCO RLE cannot be decoded.".to_string(),
+                );
+                return None;
+            }
+            return rle_area(report, ann, &Rle::from(coco_rle), &size);
+        }
+    };
+
+    let mut valid = true;
+    for (i, poly) in polygons.iter().enumerate() {
+        if poly.len() % 2 != 0 || poly.len() < 6 {
+            report.push_ann(
+                Severity::Error,
+                IssueKind::InvalidPolygon,
+                ann,
+                format!(
+                    "The polygon {i} has {} coordinates, an even number of at least 6 is required.",
+                    poly.len()
+                ),
+            );
+            valid = false;
+        }
+    }
+    // Annotations without segmentation (bounding box or keypoints only) have their area computed from the bounding box.
+    if !valid || polygons.is_empty() {
+        return None;
+    }
+    let poly = PolygonsRS {
+        size,
+        counts: polygons.clone(),
+    };
+    Some(rle_from_poly(&poly).area())
+}
+
+fn rle_area(
+    report: &mut ValidationReport,
+    ann: &Annotation,
+    rle: &Rle,
+    size: &[u32],
+) -> Option<u32> {
+    if !check_rle_size(report, ann, &rle.size, size) {
+        return None;
+    }
+    let nb_pixels: u64 = rle.counts.iter().map(|&count| u64::from(count)).sum();
+    if nb_pixels != u64::from(size[0]) * u64::from(size[1]) {
+        report.push_ann(
+            Severity::Error,
+            IssueKind::RleSizeMismatch,
+            ann,
+            format!(
+                "The RLE counts cover {nb_pixels} pixels, but the image has {} pixels.",
+                u64::from(size[0]) * u64::from(size[1])
+            ),
+        );
+        return None;
+    }
+    Some(rle.area())
+}
+
+fn check_rle_size(
+    report: &mut ValidationReport,
+    ann: &Annotation,
+    rle_size: &[u32],
+    size: &[u32],
+) -> bool {
+    if rle_size == size {
+        return true;
+    }
+    report.push_ann(
+        Severity::Error,
+        IssueKind::RleSizeMismatch,
+        ann,
+        format!("The RLE has a size of {rle_size:?}, but the image has a size of {size:?}."),
+    );
+    false
+}
+
+/// Check that the counts of a COCO RLE can be decoded, i.e. that they only use the characters of the encoding and that the last integer is complete.
+fn coco_rle_is_valid(coco_rle: &CocoRle) -> bool {
+    let bytes = coco_rle.counts.as_bytes();
+    bytes.iter().all(|byte| (48..112).contains(byte))
+        && bytes.last().map_or(true, |byte| (byte - 48) & 32 == 0)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::coco::object_detection::{Category, ExtraFields};
+    use rstest::rstest;
+
+    fn dataset() -> Dataset {
+        Dataset {
+            images: vec![Image {
+                id: 1,
+                width: 10,
+                height: 8,
+                file_name: "1.jpg".to_string(),
+                ..Default::default()
+            }],
+            annotations: vec![ann(
+                1,
+                Segmentation::Polygons(vec![vec![0.0, 0.0, 4.0, 0.0, 4.0, 4.0, 0.0, 4.0]]),
+            )],
+            categories: vec![Category {
+                id: 1,
+                name: "cat".to_string(),
+                supercategory: "animal".to_string(),
+                keypoints: None,
+                skeleton: None,
+                extra: ExtraFields::default(),
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn ann(id: u64, segmentation: Segmentation) -> Annotation {
+        Annotation {
+            id,
+            image_id: 1,
+            category_id: 1,
+            segmentation,
+            area: 16.0,
+            bbox: Bbox {
+                left: 0.0,
+                top: 0.0,
+                width: 4.0,
+                height: 4.0,
+            },
+            iscrowd: 0,
+            score: None,
+            keypoints: None,
+            num_keypoints: None,
+            extra: ExtraFields::default(),
+        }
+    }
+
+    fn kinds(report: &ValidationReport) -> Vec<(Severity, IssueKind)> {
+        report
+            .issues
+            .iter()
+            .map(|issue| (issue.severity, issue.kind))
+            .collect()
+    }
+
+    #[test]
+    fn valid_dataset() {
+        let report = validate(&dataset());
+        assert_eq!(report.issues, vec![]);
+        assert_eq!(report.to_string(), "0 error(s), 0 warning(s)");
+    }
+
+    #[rstest]
+    #[case::duplicate_ann(|d: &mut Dataset| d.annotations.push(d.annotations[0].clone()), (Severity::Error, IssueKind::DuplicateAnnotationId))]
+    #[case::duplicate_img(|d: &mut Dataset| d.images.push(d.images[0].clone()), (Severity::Error, IssueKind::DuplicateImageId))]
+    #[case::duplicate_cat(|d: &mut Dataset| d.categories.push(d.categories[0].clone()), (Severity::Error, IssueKind::DuplicateCategoryId))]
+    #[case::missing_cat(|d: &mut Dataset| d.annotations[0].category_id = 2, (Severity::Error, IssueKind::MissingCategory))]
+    #[case::missing_img(|d: &mut Dataset| d.annotations[0].image_id = 2, (Severity::Error, IssueKind::MissingImage))]
+    #[case::iscrowd(|d: &mut Dataset| d.annotations[0].iscrowd = 2, (Severity::Error, IssueKind::InvalidIscrowd))]
+    #[case::img_size(|d: &mut Dataset| d.images[0].width = 0, (Severity::Error, IssueKind::NonPositiveSize))]
+    #[case::bbox_size(|d: &mut Dataset| d.annotations[0].bbox.width = 0.0, (Severity::Warning, IssueKind::NonPositiveSize))]
+    #[case::bbox_bounds(|d: &mut Dataset| d.annotations[0].bbox.left = 8.0, (Severity::Warning, IssueKind::BboxOutOfBounds))]
+    #[case::area(|d: &mut Dataset| d.annotations[0].area = 30.0, (Severity::Warning, IssueKind::AreaMismatch))]
+    #[case::odd_polygon(|d: &mut Dataset| d.annotations[0].segmentation = Segmentation::Polygons(vec![vec![0.0, 0.0, 4.0, 0.0, 4.0]]), (Severity::Error, IssueKind::InvalidPolygon))]
+    #[case::short_polygon(|d: &mut Dataset| d.annotations[0].segmentation = Segmentation::Polygons(vec![vec![0.0, 0.0, 4.0, 0.0]]), (Severity::Error, IssueKind::InvalidPolygon))]
+    #[case::rle_size(|d: &mut Dataset| d.annotations[0].segmentation = Segmentation::Rle(Rle { size: vec![10, 8], counts: vec![80] }), (Severity::Error, IssueKind::RleSizeMismatch))]
+    #[case::rle_counts(|d: &mut Dataset| d.annotations[0].segmentation = Segmentation::Rle(Rle { size: vec![8, 10], counts: vec![60, 16] }), (Severity::Error, IssueKind::RleSizeMismatch))]
+    #[case::coco_rle(|d: &mut Dataset| d.annotations[0].segmentation = Segmentation::CocoRle(CocoRle { size: vec![8, 10], counts: "0`".to_string() }), (Severity::Error, IssueKind::InvalidRle))]
+    fn invalid_dataset(#[case] corrupt: fn(&mut Dataset), #[case] expected: (Severity, IssueKind)) {
+        let mut dataset = dataset();
+        corrupt(&mut dataset);
+        let report = validate(&dataset);
+        assert_eq!(kinds(&report), vec![expected]);
+    }
+
+    #[test]
+    fn rle_area() {
+        let mut dataset = dataset();
+        // 4x4 square in the top left corner (the RLE is column major).
+        dataset.annotations[0].segmentation = Segmentation::Rle(Rle {
+            size: vec![8, 10],
+            counts: vec![0, 4, 4, 4, 4, 4, 4, 4, 52],
+        });
+        assert_eq!(validate(&dataset).issues, vec![]);
+        dataset.annotations[0].area = 100.0;
+        assert_eq!(
+            kinds(&validate(&dataset)),
+            vec![(Severity::Warning, IssueKind::AreaMismatch)]
+        );
+    }
+
+    #[test]
+    fn report_output() {
+        let mut dataset = dataset();
+        dataset.annotations[0].category_id = 3;
+        let report = validate(&dataset);
+        assert!(report.has_errors());
+        assert_eq!(
+            report.to_string(),
+            "error: The category 3 of the annotation is not in the dataset. (image 1, annotation 1, category 3)\n1 error(s), 0 warning(s)"
+        );
+        let json: serde_json::Value = serde_json::from_str(&report.json().unwrap()).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({"issues": [{
+                "severity": "error",
+                "kind": "missing_category",
+                "image_id": 1,
+                "annotation_id": 1,
+                "category_id": 3,
+                "message": "The category 3 of the annotation is not in the dataset."
+            }]})
+        );
+    }
+}
@@ -0,0 +1,123 @@
+//! Module containing the conversions between the COCO format and other dataset formats.
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::coco::object_detection::{
+    Annotation, Bbox, Category, ExtraFields, HashmapDataset, Image, Polygons, Rle, Segmentation,
+};
+use crate::errors::ConversionError;
+
+pub mod cityscapes;
+pub mod cvat;
+pub mod kitti;
+pub mod labelme;
+pub mod labelstudio;
+pub mod openimages;
+pub mod semantic;
+pub mod voc;
+mod xml;
+pub mod yolo;
+
+/// Extensions of the image files looked for when importing a dataset without an image list.
+pub(crate) const IMAGE_EXTENSIONS: [&str; 8] =
+    ["bmp", "jpeg", "jpg", "png", "tif", "tiff", "webp", "gif"];
+
+/// Polygon of the rectangle of the bounding box, used as the segmentation of annotations that only have a bounding box.
+pub(crate) fn bbox_polygon(bbox: &Bbox) -> Vec<f64> {
+    let right = bbox.left + bbox.width;
+    let bottom = bbox.top + bbox.height;
+    vec![
+        bbox.left, bbox.top, right, bbox.top, right, bottom, bbox.left, bottom,
+    ]
+}
+
+/// Polygons of the segmentation of the annotation, the RLE masks being converted to polygons.
+pub(crate) fn ann_polygons(ann: &Annotation) -> Polygons {
+    match &ann.segmentation {
+        Segmentation::Polygons(counts) => counts.clone(),
+        Segmentation::PolygonsRS(poly) => poly.counts.clone(),
+        Segmentation::Rle(rle) => Polygons::from(rle),
+        Segmentation::CocoRle(coco_rle) => Polygons::from(&Rle::from(coco_rle)),
+    }
+}
+
+/// Whether the polygons are missing or are the rectangle of the bounding box, in which case the annotation is exported as a box.
+pub(crate) fn is_bbox_rectangle(polygons: &Polygons, bbox: &Bbox) -> bool {
+    polygons.is_empty() || *polygons == vec![bbox_polygon(bbox)]
+}
+
+/// Categories with the given names, in the same order, and the ids 1 to n.
+pub(crate) fn categories_from_names<'a, I: IntoIterator<Item = &'a str>>(
+    names: I,
+) -> Vec<Category> {
+    names
+        .into_iter()
+        .zip(1..)
+        .map(|(name, id)| Category {
+            id,
+            name: name.to_string(),
+            supercategory: String::new(),
+            keypoints: None,
+            skeleton: None,
+            extra: ExtraFields::default(),
+        })
+        .collect()
+}
+
+/// Id of the category with the given name, or 0 if there is none.
+pub(crate) fn category_id(categories: &[Category], name: &str) -> u32 {
+    categories
+        .iter()
+        .find(|cat| cat.name == name)
+        .map_or(0, |cat| cat.id)
+}
+
+/// Copy the image from the dataset's image folder to the given path, creating its parent folders.
+pub(crate) fn copy_image(
+    dataset: &HashmapDataset,
+    img: &Image,
+    dst_path: &Path,
+) -> Result<(), ConversionError> {
+    let src_path = dataset.image_folder.join(&img.file_name);
+    create_parent(dst_path)?;
+    fs::copy(&src_path, dst_path).map_err(|err| ConversionError::Read(err, src_path))?;
+    Ok(())
+}
+
+/// Create the parent folders of the path if they do not exist.
+pub(crate) fn create_parent(path: &Path) -> Result<(), ConversionError> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|err| ConversionError::Write(err, PathBuf::from(parent)))?;
+    }
+    Ok(())
+}
+
+/// Paths of the files with one of the given extensions in the folder and its subfolders, relative to the folder and sorted.
+pub(crate) fn list_files(
+    folder: &Path,
+    extensions: &[&str],
+) -> Result<Vec<PathBuf>, ConversionError> {
+    let mut files = Vec::new();
+    let mut folders = vec![folder.to_path_buf()];
+    while let Some(current_folder) = folders.pop() {
+        let entries = fs::read_dir(&current_folder)
+            .map_err(|err| ConversionError::Read(err, current_folder.clone()))?;
+        for entry in entries {
+            let path = entry
+                .map_err(|err| ConversionError::Read(err, current_folder.clone()))?
+                .path();
+            if path.is_dir() {
+                folders.push(path);
+            } else if path.extension().map_or(false, |ext| {
+                extensions.contains(&ext.to_string_lossy().to_lowercase().as_str())
+            }) {
+                if let Ok(relative_path) = path.strip_prefix(folder) {
+                    files.push(relative_path.to_path_buf());
+                }
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
@@ -0,0 +1,364 @@
+//! Module to import datasets whose instances are encoded in PNG images, like [Cityscapes](https://www.cityscapes-dataset.com/) or ADE20K.
+//!
+//! Each annotated image has a 16-bit grayscale PNG (`instanceIds`) in which each pixel holds `label_id * 1000 + instance_index` if it belongs
+//! to an object, or only `label_id` if it belongs to a stuff region or to a group of objects that could not be told apart (for example a crowd).\
+//! The labels are described by a table giving, for each label id, its name and whether it has instances.
+use std::fs;
+use std::path::Path;
+
+use image::ColorType;
+use ndarray::Array2;
+use serde::{Deserialize, Serialize};
+
+use crate::coco::object_detection::{
+    Annotation, Bbox, Category, CocoRle, Dataset, ExtraFields, HashmapDataset, Image, Segmentation,
+};
+use crate::coco::panoptic::rles_from_segment_ids;
+use crate::converters::list_files;
+use crate::errors::ConversionError;
+use crate::mask::utils::Area;
+
+/// Factor by which the label id is multiplied in the PNG images, the instance index being added to it.
+const LABEL_FACTOR: u32 = 1000;
+
+/// Description of a label, with the same fields as the `Label` tuples of the cityscapesScripts.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LabelDefinition {
+    /// Id of the label in the PNG images, used as the category id.
+    pub id: u32,
+    pub name: String,
+    /// Group of the label, used as the supercategory.
+    #[serde(default)]
+    pub category: String,
+    /// Whether the objects of this label are annotated individually.
+    #[serde(default)]
+    pub has_instances: bool,
+    /// Whether the label is ignored during the evaluation (void regions, rare classes, ...).
+    #[serde(default)]
+    pub ignore_in_eval: bool,
+}
+
+/// Import a dataset annotated with instance PNG images.
+///
+/// Each object (`label_id * 1000 + instance_index` value) becomes an annotation with a COCO RLE segmentation.
+/// The regions of a label with instances but without instance index (groups of objects) become crowd annotations (`iscrowd=1`).
+/// The stuff regions (labels without instances) are skipped, unless `include_stuff` is true in which case each of them becomes
+/// an annotation, except for the labels ignored in evaluation. Pixels with the value 0 are never imported.
+///
+/// The categories are the imported labels, with the label ids as category ids and the label categories as supercategories.
+/// The images and annotations are given the ids 1 to n.
+///
+/// ## Args
+/// - `instances_folder`: The folder with the instance PNG images (in subfolders or not).
+/// - `image_folder`: The folder with the images, with the same structure as the instances folder.
+/// - labels: The label table.
+/// - `instances_suffix`: The end of the names of the instance PNG images, for example `_gtFine_instanceIds.png` for Cityscapes.
+///   The other files are ignored.
+/// - `image_suffix`: The end of the names of the images, replacing the `instances_suffix` to get the file name of an image
+///   (for example `_leftImg8bit.png` for Cityscapes).
+/// - `include_stuff`: If true, also import the stuff regions.
+///
+/// # Errors
+///
+/// Will return `Err` if an instance PNG cannot be read, is not a 16-bit grayscale image, or has a label id which is not in the table.
+pub fn import<P: AsRef<Path>>(
+    instances_folder: P,
+    image_folder: P,
+    labels: &[LabelDefinition],
+    instances_suffix: &str,
+    image_suffix: &str,
+    include_stuff: bool,
+) -> Result<HashmapDataset, ConversionError> {
+    let instances_folder = instances_folder.as_ref();
+    let imported_labels: Vec<&LabelDefinition> = labels
+        .iter()
+        .filter(|label| label.has_instances || (include_stuff && !label.ignore_in_eval))
+        .collect();
+
+    let mut dataset = Dataset {
+        categories: imported_labels
+            .iter()
+            .map(|label| Category {
+                id: label.id,
+                name: label.name.clone(),
+                supercategory: label.category.clone(),
+                keypoints: None,
+                skeleton: None,
+                extra: ExtraFields::default(),
+            })
+            .collect(),
+        ..Default::default()
+    };
+
+    let instance_paths = list_files(instances_folder, &["png"])?
+        .into_iter()
+        .filter(|path| path.to_string_lossy().ends_with(instances_suffix));
+    for (img_id, relative_path) in (1..).zip(instance_paths) {
+        let instances_path = instances_folder.join(&relative_path);
+        let instances = image::open(&instances_path)
+            .map_err(|err| ConversionError::Image(err, instances_path.clone()))?;
+        if instances.color() != ColorType::L16 {
+            return Err(ConversionError::InvalidContent(
+                instances_path,
+                "the instance image is not a 16-bit grayscale image.".to_string(),
+            ));
+        }
+        let instances = instances.into_luma16();
+        let relative_path = relative_path.to_string_lossy();
+        let img = Image {
+            id: img_id,
+            width: instances.width(),
+            height: instances.height(),
+            file_name: format!(
+                "{}{image_suffix}",
+                &relative_path[..relative_path.len() - instances_suffix.len()]
+            ),
+            ..Default::default()
+        };
+
+        let segment_ids = Array2::from_shape_fn(
+            (img.height as usize, img.width as usize),
+            #[allow(clippy::cast_possible_truncation)]
+            |(y, x)| u32::from(instances.get_pixel(x as u32, y as u32)[0]),
+        );
+        let mut segments: Vec<_> = rles_from_segment_ids(&segment_ids).into_iter().collect();
+        segments.sort_unstable_by_key(|(segment_id, _)| *segment_id);
+        for (segment_id, rle) in segments {
+            let (label_id, is_group) = if segment_id < LABEL_FACTOR {
+                (segment_id, true)
+            } else {
+                (segment_id / LABEL_FACTOR, false)
+            };
+            let label = labels
+                .iter()
+                .find(|label| label.id == label_id)
+                .ok_or_else(|| {
+                    ConversionError::InvalidContent(
+                        instances_path.clone(),
+                        format!("the label id {label_id} is not in the label table."),
+                    )
+                })?;
+            if !imported_labels.contains(&label) {
+                continue;
+            }
+            dataset.annotations.push(Annotation {
+                id: dataset.annotations.len() as u64 + 1,
+                image_id: img.id,
+                category_id: label.id,
+                area: f64::from(rle.area()),
+                bbox: Bbox::from(&rle),
+                segmentation: Segmentation::CocoRle(CocoRle::try_from(&rle)?),
+                iscrowd: u32::from(is_group && label.has_instances),
+                score: None,
+                keypoints: None,
+                num_keypoints: None,
+                extra: ExtraFields::default(),
+            });
+        }
+        dataset.images.push(img);
+    }
+
+    Ok(HashmapDataset::from_dataset(dataset, image_folder)?)
+}
+
+/// Load a label table from a json file, with a list of objects with the fields of [`LabelDefinition`] in camel case.
+///
+/// # Errors
+///
+/// Will return `Err` if the file cannot be read or is not a valid label table.
+pub fn load_labels<P: AsRef<Path>>(path: P) -> Result<Vec<LabelDefinition>, ConversionError> {
+    let path = path.as_ref();
+    let content =
+        fs::read_to_string(path).map_err(|err| ConversionError::Read(err, path.to_path_buf()))?;
+    serde_json::from_str(&content).map_err(|err| ConversionError::Json(err, path.to_path_buf()))
+}
+
+/// The label table of the Cityscapes dataset, from the cityscapesScripts (without the license plate, which has no id).
+#[must_use]
+pub fn cityscapes_labels() -> Vec<LabelDefinition> {
+    [
+        (0, "unlabeled", "void", false, true),
+        (1, "ego vehicle", "void", false, true),
+        (2, "rectification border", "void", false, true),
+        (3, "out of roi", "void", false, true),
+        (4, "static", "void", false, true),
+        (5, "dynamic", "void", false, true),
+        (6, "ground", "void", false, true),
+        (7, "road", "flat", false, false),
+        (8, "sidewalk", "flat", false, false),
+        (9, "parking", "flat", false, true),
+        (10, "rail track", "flat", false, true),
+        (11, "building", "construction", false, false),
+        (12, "wall", "construction", false, false),
+        (13, "fence", "construction", false, false),
+        (14, "guard rail", "construction", false, true),
+        (15, "bridge", "construction", false, true),
+        (16, "tunnel", "construction", false, true),
+        (17, "pole", "object", false, false),
+        (18, "polegroup", "object", false, true),
+        (19, "traffic light", "object", false, false),
+        (20, "traffic sign", "object", false, false),
+        (21, "vegetation", "nature", false, false),
+        (22, "terrain", "nature", false, false),
+        (23, "sky", "sky", false, false),
+        (24, "person", "human", true, false),
+        (25, "rider", "human", true, false),
+        (26, "car", "vehicle", true, false),
+        (27, "truck", "vehicle", true, false),
+        (28, "bus", "vehicle", true, false),
+        (29, "caravan", "vehicle", true, true),
+        (30, "trailer", "vehicle", true, true),
+        (31, "train", "vehicle", true, false),
+        (32, "motorcycle", "vehicle", true, false),
+        (33, "bicycle", "vehicle", true, false),
+    ]
+    .into_iter()
+    .map(
+        |(id, name, category, has_instances, ignore_in_eval)| LabelDefinition {
+            id,
+            name: name.to_string(),
+            category: category.to_string(),
+            has_instances,
+            ignore_in_eval,
+        },
+    )
+    .collect()
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use image::{ImageBuffer, Luma};
+
+    use super::*;
+
+    const INSTANCES_SUFFIX: &str = "_gtFine_instanceIds.png";
+    const IMAGE_SUFFIX: &str = "_leftImg8bit.png";
+
+    /// A 6x4 image with two cars, a group of persons, some road and some unlabeled pixels.
+    fn instances_folder() -> tempfile::TempDir {
+        let folder = tempfile::tempdir().unwrap();
+        let instances: ImageBuffer<Luma<u16>, Vec<u16>> = ImageBuffer::from_fn(6, 4, |x, y| {
+            Luma([match (x, y) {
+                (0..=1, 0..=1) => 26_000,
+                (4..=5, 0) => 26_001,
+                (3, 2..=3) => 24,
+                (_, 3) => 7,
+                _ => 0,
+            }])
+        });
+        fs::create_dir(folder.path().join("aachen")).unwrap();
+        instances
+            .save(folder.path().join(format!("aachen/a{INSTANCES_SUFFIX}")))
+            .unwrap();
+        fs::write(folder.path().join("aachen/a_gtFine_labelIds.png"), "").unwrap();
+        folder
+    }
+
+    #[test]
+    fn import_instances() {
+        let folder = instances_folder();
+        let dataset = import(
+            folder.path(),
+            folder.path(),
+            &cityscapes_labels(),
+            INSTANCES_SUFFIX,
+            IMAGE_SUFFIX,
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(dataset.get_cats().len(), 10);
+        let img = dataset.get_img(1).unwrap();
+        assert_eq!(
+            (img.file_name.as_str(), img.width, img.height),
+            ("aachen/a_leftImg8bit.png", 6, 4)
+        );
+        let anns: Vec<(u32, u32, f64, &Bbox)> = dataset
+            .get_img_anns(1)
+            .unwrap()
+            .iter()
+            .map(|ann| (ann.category_id, ann.iscrowd, ann.area, &ann.bbox))
+            .collect();
+        assert_eq!(
+            anns,
+            vec![
+                (
+                    24,
+                    1,
+                    2.0,
+                    &Bbox {
+                        left: 3.0,
+                        top: 2.0,
+                        width: 1.0,
+                        height: 2.0
+                    }
+                ),
+                (
+                    26,
+                    0,
+                    4.0,
+                    &Bbox {
+                        left: 0.0,
+                        top: 0.0,
+                        width: 2.0,
+                        height: 2.0
+                    }
+                ),
+                (
+                    26,
+                    0,
+                    2.0,
+                    &Bbox {
+                        left: 4.0,
+                        top: 0.0,
+                        width: 2.0,
+                        height: 1.0
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn import_stuff() {
+        let folder = instances_folder();
+        let dataset = import(
+            folder.path(),
+            folder.path(),
+            &cityscapes_labels(),
+            INSTANCES_SUFFIX,
+            IMAGE_SUFFIX,
+            true,
+        )
+        .unwrap();
+        let road = dataset
+            .get_img_anns(1)
+            .unwrap()
+            .into_iter()
+            .find(|ann| ann.category_id == 7)
+            .unwrap();
+        assert_eq!((road.iscrowd, road.area), (0, 5.0));
+    }
+
+    #[test]
+    fn unknown_label() {
+        let folder = instances_folder();
+        let labels: Vec<LabelDefinition> = cityscapes_labels()
+            .into_iter()
+            .filter(|label| label.id != 26)
+            .collect();
+        assert!(matches!(
+            import(
+                folder.path(),
+                folder.path(),
+                &labels,
+                INSTANCES_SUFFIX,
+                IMAGE_SUFFIX,
+                false
+            ),
+            Err(ConversionError::InvalidContent(_, _))
+        ));
+    }
+}
@@ -0,0 +1,588 @@
+//! Module to convert a COCO dataset to and from the [CVAT for images 1.1](https://opencv.github.io/cvat/docs/manual/advanced/xml_format/) xml format.
+//!
+//! The xml file has one `image` element per image, containing its shapes. The supported shapes are:
+//! - `box`, with the coordinates of its top left (`xtl`, `ytl`) and bottom right (`xbr`, `ybr`) corners.
+//! - `polygon`, with its `points` written as `x1,y1;x2,y2;...`.
+//! - `mask`, with a run-length encoding (`rle`) of the mask inside its bounding box (`left`, `top`, `width`, `height`).
+//!   The counts are row-major and start with the number of background pixels, unlike the COCO [`Rle`].
+//!
+//! The other shapes (polylines, points, ellipses, etc.) have no COCO equivalent and are ignored.
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use ndarray::ShapeBuilder;
+use roxmltree::{Document, Node};
+use serde_json::{Map, Value};
+
+use crate::coco::object_detection::{
+    Annotation, Bbox, Category, Dataset, ExtraFields, HashmapDataset, Image, PolygonsRS, Rle,
+    Segmentation,
+};
+use crate::converters::xml::{child_text, escape_xml, parse_number};
+use crate::converters::{ann_polygons, bbox_polygon, create_parent, is_bbox_rectangle};
+use crate::errors::ConversionError;
+use crate::mask::conversions::rle_from_poly;
+use crate::mask::utils::Area;
+use crate::mask::Mask;
+
+/// Import a CVAT for images 1.1 xml file into a COCO dataset.
+///
+/// The categories are the labels of the `meta` section (followed by the labels only used by shapes), given the ids 1 to n.
+/// The images and annotations are given the ids 1 to n, in the order of the file.
+///
+/// The attributes of the shapes and their `occluded` flag are kept in an `attributes` object of the [`Annotation::extra`] fields,
+/// like in the COCO files exported by CVAT. Boxes have their rectangle as segmentation, and masks are converted to [`Rle`].
+///
+/// ## Args
+/// - `xml_path`: The path to the CVAT xml file.
+/// - `image_folder`: The folder with the images of the dataset.
+///
+/// # Errors
+///
+/// Will return `Err` if the file cannot be read, is not valid xml, or if a shape is missing a required attribute or is outside of its image.
+pub fn import<P: AsRef<Path>>(
+    xml_path: P,
+    image_folder: P,
+) -> Result<HashmapDataset, ConversionError> {
+    let xml_path = xml_path.as_ref();
+    let content = fs::read_to_string(xml_path)
+        .map_err(|err| ConversionError::Read(err, xml_path.to_path_buf()))?;
+    let document = Document::parse(&content)
+        .map_err(|err| ConversionError::Xml(err, xml_path.to_path_buf()))?;
+    let invalid = |reason: String| ConversionError::InvalidContent(xml_path.to_path_buf(), reason);
+    let root = document.root_element();
+
+    let mut categories: Vec<Category> = Vec::new();
+    let labels = root
+        .descendants()
+        .filter(|node| node.has_tag_name("label"))
+        .filter_map(|label| child_text(label, "name"));
+    for name in labels {
+        add_category(&mut categories, name);
+    }
+
+    let mut dataset = Dataset::default();
+    for (img_id, image) in (1..).zip(root.children().filter(|node| node.has_tag_name("image"))) {
+        let img = Image {
+            id: img_id,
+            width: parse_attribute(image, "width").map_err(invalid)?,
+            height: parse_attribute(image, "height").map_err(invalid)?,
+            file_name: attribute(image, "name").map_err(invalid)?.to_string(),
+            ..Default::default()
+        };
+        for shape in image.children().filter(Node::is_element) {
+            let ann_id = dataset.annotations.len() as u64 + 1;
+            if let Some(ann) = parse_shape(shape, ann_id, &img, &mut categories)
+                .map_err(|reason| invalid(format!("in the image {:?}, {reason}", img.file_name)))?
+            {
+                dataset.annotations.push(ann);
+            }
+        }
+        dataset.images.push(img);
+    }
+    dataset.categories = categories;
+
+    Ok(HashmapDataset::from_dataset(dataset, image_folder)?)
+}
+
+/// Export a COCO dataset to a CVAT for images 1.1 xml file, for example to upload pre-annotations.
+///
+/// Annotations whose segmentation is empty or is the rectangle of their bounding box are exported as boxes,
+/// annotations with polygons as one `polygon` per polygon, and annotations with RLE masks as masks.\
+/// The `attributes` object of the [`Annotation::extra`] fields is written back as the shapes' attributes (the `occluded` attribute being the shapes' flag).
+///
+/// ## Args
+/// - dataset: The COCO dataset to export.
+/// - `output_path`: The path of the xml file to write.
+///
+/// # Errors
+///
+/// Will return `Err` if the file cannot be written.
+pub fn export<P: AsRef<Path>>(
+    dataset: &HashmapDataset,
+    output_path: P,
+) -> Result<(), ConversionError> {
+    let output_path = output_path.as_ref();
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<annotations>\n  <version>1.1</version>\n",
+    );
+    xml.push_str(&meta_xml(dataset));
+
+    for (index, img) in dataset.get_imgs().iter().enumerate() {
+        xml.push_str(&format!(
+            "  <image id=\"{index}\" name=\"{}\" width=\"{}\" height=\"{}\">\n",
+            escape_xml(&img.file_name),
+            img.width,
+            img.height
+        ));
+        for ann in dataset.get_img_anns(img.id).unwrap_or_default() {
+            // Annotations with a category not in the dataset cannot be given a label.
+            if let Ok(cat) = dataset.get_cat(ann.category_id) {
+                xml.push_str(&shape_xml(ann, &cat.name));
+            }
+        }
+        xml.push_str("  </image>\n");
+    }
+    xml.push_str("</annotations>\n");
+
+    create_parent(output_path)?;
+    fs::write(output_path, xml)
+        .map_err(|err| ConversionError::Write(err, output_path.to_path_buf()))
+}
+
+/// Add a category with the given name if there is none yet, and return its id.
+fn add_category(categories: &mut Vec<Category>, name: &str) -> u32 {
+    if let Some(cat) = categories.iter().find(|cat| cat.name == name) {
+        return cat.id;
+    }
+    let id = categories.len() as u32 + 1;
+    categories.push(Category {
+        id,
+        name: name.to_string(),
+        supercategory: String::new(),
+        keypoints: None,
+        skeleton: None,
+        extra: ExtraFields::default(),
+    });
+    id
+}
+
+/// Parse a shape of an image, returns `None` for the shapes without COCO equivalent.
+fn parse_shape(
+    shape: Node,
+    ann_id: u64,
+    img: &Image,
+    categories: &mut Vec<Category>,
+) -> Result<Option<Annotation>, String> {
+    let size = vec![img.height, img.width];
+    let (segmentation, bbox, area) = match shape.tag_name().name() {
+        "box" => {
+            let (xtl, ytl): (f64, f64) = (
+                parse_attribute(shape, "xtl")?,
+                parse_attribute(shape, "ytl")?,
+            );
+            let (xbr, ybr): (f64, f64) = (
+                parse_attribute(shape, "xbr")?,
+                parse_attribute(shape, "ybr")?,
+            );
+            let bbox = Bbox {
+                left: xtl,
+                top: ytl,
+                width: xbr - xtl,
+                height: ybr - ytl,
+            };
+            let segmentation = PolygonsRS {
+                size,
+                counts: vec![bbox_polygon(&bbox)],
+            };
+            (
+                Segmentation::PolygonsRS(segmentation),
+                bbox.clone(),
+                bbox.width * bbox.height,
+            )
+        }
+        "polygon" => {
+            let polygon = parse_points(attribute(shape, "points")?)?;
+            let segmentation = PolygonsRS {
+                size,
+                counts: vec![polygon],
+            };
+            let bbox = Bbox::from(&segmentation.counts);
+            let area = f64::from(rle_from_poly(&segmentation).area());
+            (Segmentation::PolygonsRS(segmentation), bbox, area)
+        }
+        "mask" => {
+            let mask = parse_mask(shape, img)?;
+            let rle = Rle::from(&mask);
+            let bbox = mask_bbox(&mask);
+            let area = f64::from(rle.area());
+            (Segmentation::Rle(rle), bbox, area)
+        }
+        _ => return Ok(None),
+    };
+
+    let mut attributes = Map::new();
+    attributes.insert(
+        "occluded".to_string(),
+        Value::Bool(shape.attribute("occluded") == Some("1")),
+    );
+    for attribute in shape
+        .children()
+        .filter(|node| node.has_tag_name("attribute"))
+    {
+        if let Some(name) = attribute.attribute("name") {
+            let value = attribute.text().unwrap_or_default().to_string();
+            attributes.insert(name.to_string(), Value::String(value));
+        }
+    }
+    let mut extra = ExtraFields::default();
+    extra.insert("attributes".to_string(), Value::Object(attributes));
+
+    Ok(Some(Annotation {
+        id: ann_id,
+        image_id: img.id,
+        category_id: add_category(categories, attribute(shape, "label")?),
+        segmentation,
+        area,
+        bbox,
+        iscrowd: 0,
+        score: None,
+        keypoints: None,
+        num_keypoints: None,
+        extra,
+    }))
+}
+
+/// Parse the CVAT points format: `x1,y1;x2,y2;...`.
+fn parse_points(points: &str) -> Result<Vec<f64>, String> {
+    let mut polygon = Vec::new();
+    for point in points.split(';') {
+        let mut xy = point.split(',');
+        match (xy.next(), xy.next(), xy.next()) {
+            (Some(x), Some(y), None) => {
+                polygon.push(parse_number(x, "points")?);
+                polygon.push(parse_number(y, "points")?);
+            }
+            _ => return Err(format!("invalid point `{point}` in a polygon.")),
+        }
+    }
+    if polygon.len() < 6 {
+        return Err("a polygon has less than 3 points.".to_string());
+    }
+    Ok(polygon)
+}
+
+/// Decode the CVAT RLE of a mask into a mask of the size of the image.
+fn parse_mask(shape: Node, img: &Image) -> Result<Mask, String> {
+    let left: usize = parse_attribute(shape, "left")?;
+    let top: usize = parse_attribute(shape, "top")?;
+    let width: usize = parse_attribute(shape, "width")?;
+    let height: usize = parse_attribute(shape, "height")?;
+    if left + width > img.width as usize || top + height > img.height as usize {
+        return Err(format!(
+            "the mask [{left}, {top}, {width}, {height}] is outside of the image."
+        ));
+    }
+
+    let mut mask = Mask::zeros((img.height as usize, img.width as usize).f());
+    let mut position = 0;
+    let mut value = 0;
+    for count in attribute(shape, "rle")?.split(',') {
+        let count: usize = parse_number(count.trim(), "rle")?;
+        if position + count > width * height {
+            return Err("the rle of a mask has more pixels than its bounding box.".to_string());
+        }
+        if value == 1 {
+            for index in position..position + count {
+                mask[[top + index / width, left + index % width]] = 1;
+            }
+        }
+        position += count;
+        value = 1 - value;
+    }
+    Ok(mask)
+}
+
+/// Bounding box of the pixels of the mask, as computed by pycocotools.
+#[allow(clippy::cast_precision_loss)]
+fn mask_bbox(mask: &Mask) -> Bbox {
+    match mask_extent(mask) {
+        Some((left, top, width, height)) => Bbox {
+            left: left as f64,
+            top: top as f64,
+            width: width as f64,
+            height: height as f64,
+        },
+        None => Bbox::default(),
+    }
+}
+
+/// Position and size of the smallest rectangle containing the pixels of the mask, `None` if the mask is empty.
+fn mask_extent(mask: &Mask) -> Option<(usize, usize, usize, usize)> {
+    let mut extent: Option<(usize, usize, usize, usize)> = None;
+    for ((row, col), value) in mask.indexed_iter() {
+        if *value != 0 {
+            extent = Some(match extent {
+                None => (col, row, col, row),
+                Some((left, top, right, bottom)) => {
+                    (left.min(col), top.min(row), right.max(col), bottom.max(row))
+                }
+            });
+        }
+    }
+    extent.map(|(left, top, right, bottom)| (left, top, right - left + 1, bottom - top + 1))
+}
+
+/// The `meta` section of the xml file, with the labels and the names of their attributes.
+fn meta_xml(dataset: &HashmapDataset) -> String {
+    let mut attributes: HashMap<u32, Vec<&str>> = HashMap::new();
+    for ann in dataset.get_anns() {
+        if let Some(Value::Object(ann_attributes)) = ann.extra.get("attributes") {
+            let names = attributes.entry(ann.category_id).or_default();
+            for name in ann_attributes.keys() {
+                if name != "occluded" && !names.contains(&name.as_str()) {
+                    names.push(name);
+                }
+            }
+        }
+    }
+
+    let mut xml = String::from("  <meta>\n    <task>\n      <labels>\n");
+    for cat in dataset.get_cats() {
+        xml.push_str(&format!(
+            "        <label>\n          <name>{}</name>\n          <attributes>\n",
+            escape_xml(&cat.name)
+        ));
+        for name in attributes.get(&cat.id).into_iter().flatten() {
+            xml.push_str(&format!(
+                "            <attribute>\n              <name>{}</name>\n              <input_type>text</input_type>\n            </attribute>\n",
+                escape_xml(name)
+            ));
+        }
+        xml.push_str("          </attributes>\n        </label>\n");
+    }
+    xml.push_str("      </labels>\n    </task>\n  </meta>\n");
+    xml
+}
+
+/// The shape element(s) corresponding to the annotation.
+fn shape_xml(ann: &Annotation, label: &str) -> String {
+    let mut occluded = false;
+    let mut attributes = String::new();
+    if let Some(Value::Object(ann_attributes)) = ann.extra.get("attributes") {
+        for (name, value) in ann_attributes {
+            if name == "occluded" {
+                occluded = value.as_bool().unwrap_or(false) || value.as_u64() == Some(1);
+                continue;
+            }
+            let value = match value {
+                Value::String(value) => value.clone(),
+                value => value.to_string(),
+            };
+            attributes.push_str(&format!(
+                "      <attribute name=\"{}\">{}</attribute>\n",
+                escape_xml(name),
+                escape_xml(&value)
+            ));
+        }
+    }
+    let common = format!(
+        "label=\"{}\" source=\"manual\" occluded=\"{}\"",
+        escape_xml(label),
+        u8::from(occluded)
+    );
+
+    match &ann.segmentation {
+        Segmentation::Rle(rle) => return mask_xml(&Mask::from(rle), &common, &attributes),
+        Segmentation::CocoRle(coco_rle) => {
+            return mask_xml(&Mask::from(&Rle::from(coco_rle)), &common, &attributes)
+        }
+        Segmentation::Polygons(_) | Segmentation::PolygonsRS(_) => {}
+    }
+
+    let polygons = ann_polygons(ann);
+    let bbox = &ann.bbox;
+    if is_bbox_rectangle(&polygons, bbox) {
+        return format!(
+            "    <box {common} xtl=\"{:.2}\" ytl=\"{:.2}\" xbr=\"{:.2}\" ybr=\"{:.2}\" z_order=\"0\">\n{attributes}    </box>\n",
+            bbox.left,
+            bbox.top,
+            bbox.left + bbox.width,
+            bbox.top + bbox.height
+        );
+    }
+    polygons
+        .iter()
+        .map(|polygon| {
+            let points: Vec<String> = polygon
+                .chunks_exact(2)
+                .map(|xy| format!("{:.2},{:.2}", xy[0], xy[1]))
+                .collect();
+            format!(
+                "    <polygon {common} points=\"{}\" z_order=\"0\">\n{attributes}    </polygon>\n",
+                points.join(";")
+            )
+        })
+        .collect()
+}
+
+/// The mask element of a mask, with the CVAT RLE of its bounding box.
+fn mask_xml(mask: &Mask, common: &str, attributes: &str) -> String {
+    let (left, top, width, height) = match mask_extent(mask) {
+        Some(extent) => extent,
+        // CVAT does not support empty masks.
+        None => return String::new(),
+    };
+    let mut counts = Vec::new();
+    let mut count = 0;
+    let mut previous_value = 0;
+    for row in top..top + height {
+        for col in left..left + width {
+            let value = mask[[row, col]];
+            if value != previous_value {
+                counts.push(count.to_string());
+                previous_value = value;
+                count = 0;
+            }
+            count += 1;
+        }
+    }
+    counts.push(count.to_string());
+    format!(
+        "    <mask {common} rle=\"{}\" left=\"{left}\" top=\"{top}\" width=\"{width}\" height=\"{height}\" z_order=\"0\">\n{attributes}    </mask>\n",
+        counts.join(", ")
+    )
+}
+
+fn attribute<'a>(node: Node<'a, '_>, name: &str) -> Result<&'a str, String> {
+    node.attribute(name).ok_or_else(|| {
+        format!(
+            "a `{}` element does not have a `{name}` attribute.",
+            node.tag_name().name()
+        )
+    })
+}
+
+fn parse_attribute<T: std::str::FromStr>(node: Node, name: &str) -> Result<T, String> {
+    parse_number(attribute(node, name)?, name)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::float_cmp)]
+mod tests {
+    use super::*;
+    use crate::coco::object_detection::CocoRle;
+
+    const CVAT_XML: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<annotations>
+  <version>1.1</version>
+  <meta>
+    <task>
+      <labels>
+        <label><name>car</name><attributes></attributes></label>
+        <label><name>person</name><attributes></attributes></label>
+      </labels>
+    </task>
+  </meta>
+  <image id="0" name="a.jpg" width="10" height="8">
+    <box label="car" source="manual" occluded="1" xtl="1.00" ytl="2.00" xbr="5.00" ybr="6.00" z_order="0">
+      <attribute name="color">red</attribute>
+    </box>
+    <polygon label="person" source="manual" occluded="0" points="0.00,0.00;4.00,0.00;4.00,4.00" z_order="0">
+    </polygon>
+    <mask label="tree" source="manual" occluded="0" rle="0, 2, 1, 3" left="3" top="4" width="3" height="2" z_order="0">
+    </mask>
+    <points label="car" source="manual" occluded="0" points="1.00,1.00" z_order="0">
+    </points>
+  </image>
+  <image id="1" name="b.jpg" width="10" height="8">
+  </image>
+</annotations>
+"#;
+
+    fn import_str(content: &str) -> Result<HashmapDataset, ConversionError> {
+        let folder = tempfile::tempdir().unwrap();
+        let xml_path = folder.path().join("annotations.xml");
+        fs::write(&xml_path, content).unwrap();
+        import(xml_path.as_path(), Path::new("images"))
+    }
+
+    #[test]
+    fn import_shapes() {
+        let dataset = import_str(CVAT_XML).unwrap();
+        let cats: Vec<&str> = dataset
+            .get_cats()
+            .iter()
+            .map(|cat| cat.name.as_str())
+            .collect();
+        assert_eq!(cats, vec!["car", "person", "tree"]);
+        assert_eq!(dataset.get_imgs().len(), 2);
+        assert!(dataset.get_img_anns(2).unwrap().is_empty());
+
+        let anns = dataset.get_img_anns(1).unwrap();
+        assert_eq!(anns.len(), 3);
+        assert_eq!(
+            anns[0].bbox,
+            Bbox {
+                left: 1.0,
+                top: 2.0,
+                width: 4.0,
+                height: 4.0
+            }
+        );
+        assert_eq!(
+            anns[0].extra["attributes"],
+            serde_json::json!({"occluded": true, "color": "red"})
+        );
+        assert_eq!(anns[1].category_id, 2);
+        assert!(anns[1].area > 0.0 && anns[1].area < 16.0);
+        assert_eq!(anns[1].bbox.width, 4.0);
+
+        // The counts are row-major inside the 3x2 box at (3, 4).
+        assert_eq!(anns[2].category_id, 3);
+        let mask = Mask::try_from(&anns[2].segmentation).unwrap();
+        let pixels: Vec<(usize, usize)> = mask
+            .indexed_iter()
+            .filter(|(_, value)| **value == 1)
+            .map(|(position, _)| position)
+            .collect();
+        assert_eq!(pixels, vec![(4, 3), (4, 4), (5, 3), (5, 4), (5, 5)]);
+        assert_eq!(anns[2].area, 5.0);
+        assert_eq!(
+            anns[2].bbox,
+            Bbox {
+                left: 3.0,
+                top: 4.0,
+                width: 3.0,
+                height: 2.0
+            }
+        );
+    }
+
+    #[test]
+    fn import_invalid() {
+        let content = CVAT_XML.replace("left=\"3\"", "left=\"8\"");
+        match import_str(&content) {
+            Err(ConversionError::InvalidContent(_, reason)) => assert_eq!(
+                reason,
+                "in the image \"a.jpg\", the mask [8, 4, 3, 2] is outside of the image."
+            ),
+            _ => panic!("Expected an invalid content error."),
+        }
+    }
+
+    #[test]
+    fn export_import_roundtrip() {
+        let dataset = import_str(CVAT_XML).unwrap();
+        let folder = tempfile::tempdir().unwrap();
+        let xml_path = folder.path().join("exported.xml");
+        export(&dataset, &xml_path).unwrap();
+
+        let exported = fs::read_to_string(&xml_path).unwrap();
+        assert!(exported.contains("<box label=\"car\" source=\"manual\" occluded=\"1\" xtl=\"1.00\" ytl=\"2.00\" xbr=\"5.00\" ybr=\"6.00\""));
+        assert!(
+            exported.contains("rle=\"0, 2, 1, 3\" left=\"3\" top=\"4\" width=\"3\" height=\"2\"")
+        );
+
+        let reimported = import(xml_path.as_path(), Path::new("images")).unwrap();
+        assert_eq!(reimported.get_anns(), dataset.get_anns());
+        assert_eq!(reimported.get_cats(), dataset.get_cats());
+    }
+
+    #[test]
+    fn coco_rle_export() {
+        let mut dataset = Dataset::from(&import_str(CVAT_XML).unwrap());
+        let rle = match &dataset.annotations[2].segmentation {
+            Segmentation::Rle(rle) => rle.clone(),
+            _ => panic!("Expected a RLE."),
+        };
+        dataset.annotations[2].segmentation =
+            Segmentation::CocoRle(CocoRle::try_from(&rle).unwrap());
+        let dataset = HashmapDataset::from_dataset(dataset, "images").unwrap();
+        let xml = shape_xml(dataset.get_ann(3).unwrap(), "tree");
+        assert!(xml.starts_with(
+            "    <mask label=\"tree\" source=\"manual\" occluded=\"0\" rle=\"0, 2, 1, 3\""
+        ));
+    }
+}
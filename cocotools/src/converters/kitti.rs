@@ -0,0 +1,391 @@
+//! Module to convert a COCO dataset to and from the [KITTI 2D object](https://www.cvlibs.net/datasets/kitti/eval_object.php) label format.
+//!
+//! A KITTI dataset has one text file per image (`label_2/<image name>.txt`), with one line per object:
+//! `type truncated occluded alpha left top right bottom height width length x y z rotation_y [score]`.\
+//! The bounding box is given in pixels, the other values describe the object in 3D and are not used by COCO.
+//! The `DontCare` objects mark the regions in which the objects were not labeled and where the detections should be ignored.
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::coco::object_detection::{
+    Annotation, Bbox, Dataset, ExtraFields, HashmapDataset, Image, PolygonsRS, Segmentation,
+};
+use crate::converters::{
+    bbox_polygon, categories_from_names, category_id, copy_image, create_parent, list_files,
+    IMAGE_EXTENSIONS,
+};
+use crate::errors::ConversionError;
+use crate::mask::conversions::rle_from_poly;
+use crate::mask::utils::Area;
+
+/// Type of the KITTI objects marking the regions without labels.
+const DONT_CARE: &str = "DontCare";
+
+/// Object of a KITTI label file.
+struct KittiObject {
+    type_name: String,
+    bbox: Bbox,
+    score: Option<f64>,
+    extra: ExtraFields,
+}
+
+/// Import a KITTI object dataset into a COCO dataset.
+///
+/// The categories are the object types found in the label files, sorted alphabetically and given the ids 1 to n.
+/// The images and annotations are given the ids 1 to n. Images without label file have no annotation.
+///
+/// The `DontCare` objects are imported as crowd annotations (`iscrowd=1`) of the `DontCare` category.
+/// The `truncated`, `occluded`, `alpha`, `dimensions` (height, width, length), `location` (x, y, z) and `rotation_y` values
+/// are kept in the [`Annotation::extra`] fields, and are written back when exporting to KITTI.
+/// The segmentation of the annotations is the rectangle of their bounding box.
+///
+/// ## Args
+/// - `image_folder`: The folder with the images (`image_2`).
+/// - `labels_folder`: The folder with the label files (`label_2`), with the same structure as the image folder.
+///
+/// # Errors
+///
+/// Will return `Err` if a file cannot be read or if a line of a label file is not valid.
+pub fn import<P: AsRef<Path>>(
+    image_folder: P,
+    labels_folder: P,
+) -> Result<HashmapDataset, ConversionError> {
+    let image_folder = image_folder.as_ref();
+    let labels_folder = labels_folder.as_ref();
+
+    let mut images = Vec::new();
+    for (img_id, relative_path) in (1..).zip(list_files(image_folder, &IMAGE_EXTENSIONS)?) {
+        let img_path = image_folder.join(&relative_path);
+        let (width, height) = image::image_dimensions(&img_path)
+            .map_err(|err| ConversionError::ImageSize(err, img_path.clone()))?;
+        let img = Image {
+            id: img_id,
+            width,
+            height,
+            file_name: relative_path.to_string_lossy().into_owned(),
+            ..Default::default()
+        };
+
+        let mut objects = Vec::new();
+        let label_path = labels_folder.join(&relative_path).with_extension("txt");
+        if label_path.is_file() {
+            let content = fs::read_to_string(&label_path)
+                .map_err(|err| ConversionError::Read(err, label_path.clone()))?;
+            for (line_idx, line) in content.lines().enumerate() {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                objects.push(parse_line(line).map_err(|reason| {
+                    ConversionError::InvalidLine(label_path.clone(), line_idx + 1, reason)
+                })?);
+            }
+        }
+        images.push((img, objects));
+    }
+
+    let names: BTreeSet<&str> = images
+        .iter()
+        .flat_map(|(_, objects)| objects.iter().map(|object| object.type_name.as_str()))
+        .collect();
+    let categories = categories_from_names(names);
+
+    let mut annotations = Vec::new();
+    for (img, objects) in &images {
+        for object in objects {
+            let category_id = category_id(&categories, &object.type_name);
+            let segmentation = PolygonsRS {
+                size: vec![img.height, img.width],
+                counts: vec![bbox_polygon(&object.bbox)],
+            };
+            annotations.push(Annotation {
+                id: annotations.len() as u64 + 1,
+                image_id: img.id,
+                category_id,
+                area: f64::from(rle_from_poly(&segmentation).area()),
+                segmentation: Segmentation::PolygonsRS(segmentation),
+                bbox: object.bbox.clone(),
+                iscrowd: u32::from(object.type_name == DONT_CARE),
+                score: object.score,
+                keypoints: None,
+                num_keypoints: None,
+                extra: object.extra.clone(),
+            });
+        }
+    }
+
+    let dataset = Dataset {
+        images: images.into_iter().map(|(img, _)| img).collect(),
+        annotations,
+        categories,
+        ..Default::default()
+    };
+    Ok(HashmapDataset::from_dataset(dataset, image_folder)?)
+}
+
+/// Parse a line of a label file, returning the reason why the line is invalid on failure.
+fn parse_line(line: &str) -> Result<KittiObject, String> {
+    let mut tokens = line.split_whitespace();
+    let type_name = tokens.next().unwrap_or_default().to_string();
+    let values = tokens
+        .map(|token| {
+            token
+                .parse::<f64>()
+                .map_err(|_| format!("`{token}` is not a number."))
+        })
+        .collect::<Result<Vec<f64>, String>>()?;
+    if values.len() != 14 && values.len() != 15 {
+        return Err(format!(
+            "expected 14 values after the type, or 15 with a score, but got {}.",
+            values.len()
+        ));
+    }
+
+    let mut extra = ExtraFields::default();
+    extra.insert("truncated".to_string(), values[0].into());
+    #[allow(clippy::cast_possible_truncation)]
+    extra.insert("occluded".to_string(), (values[1] as i64).into());
+    extra.insert("alpha".to_string(), values[2].into());
+    extra.insert("dimensions".to_string(), values[7..10].into());
+    extra.insert("location".to_string(), values[10..13].into());
+    extra.insert("rotation_y".to_string(), values[13].into());
+
+    Ok(KittiObject {
+        type_name,
+        bbox: Bbox {
+            left: values[3],
+            top: values[4],
+            width: values[5] - values[3],
+            height: values[6] - values[4],
+        },
+        score: values.get(14).copied(),
+        extra,
+    })
+}
+
+/// Export a COCO dataset to the KITTI object label format.
+///
+/// The following files are created in the output folder:
+/// - `label_2/<image name>.txt`, with the objects of each image. Images without annotations get an empty file.
+/// - `image_2/<image name>`, a copy of each image if `copy_images` is true.
+///
+/// The object types are the category names, with the spaces replaced by underscores. Crowd annotations are exported as `DontCare` objects.\
+/// The KITTI values are taken from the [`Annotation::extra`] fields if present. Otherwise the objects are written as not truncated
+/// and fully visible (or with -1 for the `DontCare` objects), with the values KITTI uses for unknown 3D information.
+/// The score is written for the annotations that have one, as expected for the detection results.
+///
+/// ## Args
+/// - dataset: The COCO dataset to export.
+/// - `output_folder`: The folder where the KITTI dataset will be written.
+/// - `copy_images`: If true, copy the images from the dataset's image folder to the output folder.
+///
+/// # Errors
+///
+/// Will return `Err` if a file cannot be written or an image cannot be copied.
+pub fn export<P: AsRef<Path>>(
+    dataset: &HashmapDataset,
+    output_folder: P,
+    copy_images: bool,
+) -> Result<(), ConversionError> {
+    let output_folder = output_folder.as_ref();
+    let labels_folder = output_folder.join("label_2");
+    let images_folder = output_folder.join("image_2");
+
+    for img in dataset.get_imgs() {
+        let mut content = String::new();
+        for ann in dataset.get_img_anns(img.id).unwrap_or_default() {
+            let type_name = if ann.iscrowd == 0 {
+                match dataset.get_cat(ann.category_id) {
+                    Ok(cat) => cat.name.replace(' ', "_"),
+                    Err(_) => continue,
+                }
+            } else {
+                DONT_CARE.to_string()
+            };
+            content.push_str(&label_line(ann, &type_name));
+            content.push('\n');
+        }
+
+        let label_path = labels_folder.join(&img.file_name).with_extension("txt");
+        create_parent(&label_path)?;
+        fs::write(&label_path, content).map_err(|err| ConversionError::Write(err, label_path))?;
+
+        if copy_images {
+            copy_image(dataset, img, &images_folder.join(&img.file_name))?;
+        }
+    }
+    Ok(())
+}
+
+/// Line of a label file for the annotation, without the line break.
+fn label_line(ann: &Annotation, type_name: &str) -> String {
+    let number = |name: &str, default: f64| {
+        ann.extra
+            .get(name)
+            .and_then(Value::as_f64)
+            .unwrap_or(default)
+    };
+    let numbers = |name: &str, default: f64| match ann.extra.get(name).and_then(Value::as_array) {
+        Some(values) if values.len() == 3 => values
+            .iter()
+            .map(|value| value.as_f64().unwrap_or(default))
+            .collect(),
+        _ => vec![default; 3],
+    };
+    // The truncation and occlusion of the objects are known, but not the ones of the DontCare regions.
+    let (truncated, occluded) = if type_name == DONT_CARE {
+        (-1.0, -1)
+    } else {
+        (0.0, 0)
+    };
+
+    let mut values = vec![number("truncated", truncated)];
+    values.extend([
+        number("alpha", -10.0),
+        ann.bbox.left,
+        ann.bbox.top,
+        ann.bbox.left + ann.bbox.width,
+        ann.bbox.top + ann.bbox.height,
+    ]);
+    values.extend(numbers("dimensions", -1.0));
+    values.extend(numbers("location", -1000.0));
+    values.push(number("rotation_y", -10.0));
+
+    let mut line = format!(
+        "{type_name} {:.2} {}",
+        values[0],
+        ann.extra
+            .get("occluded")
+            .and_then(Value::as_i64)
+            .unwrap_or(occluded)
+    );
+    for value in &values[1..] {
+        line.push_str(&format!(" {value:.2}"));
+    }
+    // The scores are used to rank the detections, rounding them would create ties.
+    if let Some(score) = ann.score {
+        line.push_str(&format!(" {score}"));
+    }
+    line
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use image::RgbImage;
+
+    use super::*;
+
+    const LABELS: &str =
+        "Car 0.00 0 -1.58 587.01 173.33 614.12 200.12 1.65 1.67 3.64 -0.65 1.71 46.70 -1.59
+Pedestrian 0.50 2 0.21 10.00 20.00 30.00 60.00 1.73 0.60 0.90 1.00 1.50 20.00 0.25 0.873
+
+DontCare -1 -1 -10 503.89 169.71 590.61 190.13 -1 -1 -1 -1000 -1000 -1000 -10
+";
+
+    fn kitti_folder() -> tempfile::TempDir {
+        let folder = tempfile::tempdir().unwrap();
+        fs::create_dir_all(folder.path().join("image_2")).unwrap();
+        fs::create_dir_all(folder.path().join("label_2")).unwrap();
+        for name in ["000000", "000001"] {
+            RgbImage::new(1242, 375)
+                .save(folder.path().join(format!("image_2/{name}.png")))
+                .unwrap();
+        }
+        fs::write(folder.path().join("label_2/000000.txt"), LABELS).unwrap();
+        folder
+    }
+
+    #[test]
+    fn import_labels() {
+        let folder = kitti_folder();
+        let dataset = import(folder.path().join("image_2"), folder.path().join("label_2")).unwrap();
+
+        let cats: Vec<&str> = dataset
+            .get_cats()
+            .iter()
+            .map(|cat| cat.name.as_str())
+            .collect();
+        assert_eq!(cats, vec!["Car", "DontCare", "Pedestrian"]);
+        assert_eq!(dataset.get_imgs().len(), 2);
+        assert!(dataset.get_img_anns(2).unwrap().is_empty());
+
+        let anns = dataset.get_img_anns(1).unwrap();
+        assert_eq!(anns.len(), 3);
+        assert_eq!(
+            anns[1].bbox,
+            Bbox {
+                left: 10.0,
+                top: 20.0,
+                width: 20.0,
+                height: 40.0
+            }
+        );
+        assert_eq!((anns[1].iscrowd, anns[1].area), (0, 800.0));
+        assert_eq!(anns[1].score, Some(0.873));
+        assert_eq!(anns[1].extra["truncated"], 0.5);
+        assert_eq!(anns[1].extra["occluded"], 2);
+        assert_eq!(
+            anns[1].extra["location"],
+            serde_json::json!([1.0, 1.5, 20.0])
+        );
+        assert_eq!((anns[2].category_id, anns[2].iscrowd), (2, 1));
+    }
+
+    #[test]
+    fn invalid_line() {
+        let folder = kitti_folder();
+        fs::write(
+            folder.path().join("label_2/000001.txt"),
+            "Car 0.00 0 -1.58 1 2 3 4",
+        )
+        .unwrap();
+        assert!(matches!(
+            import(folder.path().join("image_2"), folder.path().join("label_2")),
+            Err(ConversionError::InvalidLine(_, 1, _))
+        ));
+    }
+
+    #[test]
+    fn export_import_roundtrip() {
+        let folder = kitti_folder();
+        let dataset = import(folder.path().join("image_2"), folder.path().join("label_2")).unwrap();
+
+        let output_folder = tempfile::tempdir().unwrap();
+        export(&dataset, output_folder.path(), true).unwrap();
+        assert_eq!(
+            fs::read_to_string(output_folder.path().join("label_2/000000.txt")).unwrap(),
+            LABELS.replace("\n\n", "\n").replace(
+                "DontCare -1 -1 -10 503.89 169.71 590.61 190.13 -1 -1 -1 -1000 -1000 -1000 -10",
+                "DontCare -1.00 -1 -10.00 503.89 169.71 590.61 190.13 -1.00 -1.00 -1.00 -1000.00 -1000.00 -1000.00 -10.00"
+            )
+        );
+        assert_eq!(
+            fs::read_to_string(output_folder.path().join("label_2/000001.txt")).unwrap(),
+            ""
+        );
+
+        let reimported = import(
+            output_folder.path().join("image_2"),
+            output_folder.path().join("label_2"),
+        )
+        .unwrap();
+        assert_eq!(reimported.get_cats(), dataset.get_cats());
+        let boxes = |dataset: &HashmapDataset| -> Vec<(u32, u32, Bbox)> {
+            dataset
+                .get_anns()
+                .iter()
+                .map(|ann| (ann.category_id, ann.iscrowd, ann.bbox.clone()))
+                .collect()
+        };
+        assert_eq!(boxes(&reimported).len(), 3);
+        for ((cat_id, iscrowd, bbox), (expected_cat_id, expected_iscrowd, expected_bbox)) in
+            boxes(&reimported).into_iter().zip(boxes(&dataset))
+        {
+            assert_eq!((cat_id, iscrowd), (expected_cat_id, expected_iscrowd));
+            assert!((bbox.width - expected_bbox.width).abs() < 1e-9);
+        }
+    }
+}
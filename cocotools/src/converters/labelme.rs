@@ -0,0 +1,442 @@
+//! Module to convert a COCO dataset to and from a folder of [LabelMe](https://github.com/wkentaro/labelme) json files.
+//!
+//! LabelMe writes one json file per image, with the image's size, path (relative to the json file), optionally its content
+//! encoded in base64 (`imageData`), and a list of labeled shapes. Shapes with the same label and `group_id` belong to the same object.
+use std::collections::BTreeSet;
+use std::f64::consts::PI;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+
+use crate::coco::object_detection::{
+    Annotation, Bbox, Dataset, ExtraFields, HashmapDataset, Image, Polygons, PolygonsRS,
+    Segmentation,
+};
+use crate::converters::{
+    ann_polygons, bbox_polygon, categories_from_names, category_id, copy_image, create_parent,
+    is_bbox_rectangle, list_files,
+};
+use crate::errors::ConversionError;
+use crate::mask::conversions::rle_from_poly;
+use crate::mask::utils::Area;
+
+/// Number of points of the polygons approximating the circles.
+const CIRCLE_POINTS: u32 = 24;
+/// Version of LabelMe written in the exported files.
+const LABELME_VERSION: &str = "5.2.1";
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LabelMeFile {
+    #[serde(default)]
+    version: String,
+    #[serde(default)]
+    flags: serde_json::Map<String, serde_json::Value>,
+    shapes: Vec<Shape>,
+    image_path: String,
+    #[serde(default)]
+    image_data: Option<String>,
+    image_height: u32,
+    image_width: u32,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Shape {
+    label: String,
+    points: Vec<[f64; 2]>,
+    #[serde(default)]
+    group_id: Option<i64>,
+    #[serde(default = "default_shape_type")]
+    shape_type: String,
+    #[serde(default)]
+    flags: serde_json::Map<String, serde_json::Value>,
+}
+
+fn default_shape_type() -> String {
+    "polygon".to_string()
+}
+
+/// Import a folder of LabelMe json files into a COCO dataset.
+///
+/// The polygon, rectangle, circle (approximated by a polygon) and linestrip (closed into a polygon) shapes are converted,
+/// the points and lines are ignored since they do not have an area.
+/// The shapes of an image with the same label and `group_id` are merged into a single annotation with several polygons.
+///
+/// The categories are the labels sorted alphabetically, given the ids 1 to n. The images and annotations are given the ids 1 to n.\
+/// The file names of the images are their paths relative to the LabelMe folder.
+///
+/// ## Args
+/// - `labelme_folder`: The folder with the LabelMe json files (in subfolders or not).
+/// - `extracted_images_folder`: If given, the images embedded in the json files (`imageData`) are decoded and written to this folder,
+///   which becomes the image folder of the dataset. Otherwise the images are expected to be in the LabelMe folder.
+///
+/// # Errors
+///
+/// Will return `Err` if a file cannot be read, is not a valid LabelMe file, or if an embedded image cannot be decoded or written.
+pub fn import<P: AsRef<Path>>(
+    labelme_folder: P,
+    extracted_images_folder: Option<P>,
+) -> Result<HashmapDataset, ConversionError> {
+    let labelme_folder = labelme_folder.as_ref();
+    let extracted_images_folder = extracted_images_folder.as_ref().map(AsRef::as_ref);
+
+    let mut files = Vec::new();
+    for json_path in list_files(labelme_folder, &["json"])? {
+        let path = labelme_folder.join(&json_path);
+        let content =
+            fs::read_to_string(&path).map_err(|err| ConversionError::Read(err, path.clone()))?;
+        let file: LabelMeFile =
+            serde_json::from_str(&content).map_err(|err| ConversionError::Json(err, path))?;
+        files.push((json_path, file));
+    }
+
+    let labels: BTreeSet<&str> = files
+        .iter()
+        .flat_map(|(_, file)| file.shapes.iter().map(|shape| shape.label.as_str()))
+        .collect();
+    let categories = categories_from_names(labels);
+
+    let mut dataset = Dataset::default();
+    for (img_id, (json_path, file)) in (1..).zip(&files) {
+        let file_name = image_file_name(json_path, &file.image_path);
+        if let (Some(folder), Some(image_data)) = (extracted_images_folder, &file.image_data) {
+            let img_path = folder.join(&file_name);
+            let bytes = BASE64.decode(image_data).map_err(|err| {
+                ConversionError::InvalidContent(
+                    labelme_folder.join(json_path),
+                    format!("the image data is not valid base64 ({err})."),
+                )
+            })?;
+            create_parent(&img_path)?;
+            fs::write(&img_path, bytes).map_err(|err| ConversionError::Write(err, img_path))?;
+        }
+        let img = Image {
+            id: img_id,
+            width: file.image_width,
+            height: file.image_height,
+            file_name: file_name.to_string_lossy().into_owned(),
+            ..Default::default()
+        };
+
+        // Group the polygons by label and group id, keeping the order of the file.
+        let mut objects: Vec<((&str, Option<i64>), Polygons)> = Vec::new();
+        for shape in &file.shapes {
+            let polygon = match shape_polygon(shape) {
+                Some(polygon) => polygon,
+                None => continue,
+            };
+            let key = (shape.label.as_str(), shape.group_id);
+            match objects
+                .iter_mut()
+                .find(|(object_key, _)| shape.group_id.is_some() && *object_key == key)
+            {
+                Some((_, polygons)) => polygons.push(polygon),
+                None => objects.push((key, vec![polygon])),
+            }
+        }
+
+        for ((label, _), polygons) in objects {
+            let segmentation = PolygonsRS {
+                size: vec![img.height, img.width],
+                counts: polygons,
+            };
+            dataset.annotations.push(Annotation {
+                id: dataset.annotations.len() as u64 + 1,
+                image_id: img.id,
+                category_id: category_id(&categories, label),
+                area: f64::from(rle_from_poly(&segmentation).area()),
+                bbox: Bbox::from(&segmentation.counts),
+                segmentation: Segmentation::PolygonsRS(segmentation),
+                iscrowd: 0,
+                score: None,
+                keypoints: None,
+                num_keypoints: None,
+                extra: ExtraFields::default(),
+            });
+        }
+        dataset.images.push(img);
+    }
+    dataset.categories = categories;
+
+    let image_folder = extracted_images_folder.unwrap_or(labelme_folder);
+    Ok(HashmapDataset::from_dataset(dataset, image_folder)?)
+}
+
+/// Export a COCO dataset to a folder of LabelMe json files.
+///
+/// The json file of an image is written at the image's relative path, with a `.json` extension.
+/// Annotations whose segmentation is empty or is the rectangle of their bounding box are exported as rectangles, the others as polygons
+/// (RLE masks are converted to polygons). The polygons of an annotation made of several polygons share the annotation id as `group_id`.
+///
+/// ## Args
+/// - dataset: The COCO dataset to export.
+/// - `output_folder`: The folder where the json files will be written.
+/// - `copy_images`: If true, copy the images next to their json file, where LabelMe expects them.
+/// - `embed_images`: If true, embed the images in the json files (`imageData`), so that they can be opened without the image files.
+///
+/// # Errors
+///
+/// Will return `Err` if a file cannot be written or an image cannot be read.
+pub fn export<P: AsRef<Path>>(
+    dataset: &HashmapDataset,
+    output_folder: P,
+    copy_images: bool,
+    embed_images: bool,
+) -> Result<(), ConversionError> {
+    let output_folder = output_folder.as_ref();
+    for img in dataset.get_imgs() {
+        let dst_path = output_folder.join(&img.file_name);
+        let image_data = if embed_images {
+            let src_path = dataset.image_folder.join(&img.file_name);
+            let bytes = fs::read(&src_path).map_err(|err| ConversionError::Read(err, src_path))?;
+            Some(BASE64.encode(bytes))
+        } else {
+            None
+        };
+        if copy_images {
+            copy_image(dataset, img, &dst_path)?;
+        }
+
+        let mut shapes = Vec::new();
+        for ann in dataset.get_img_anns(img.id).unwrap_or_default() {
+            if let Ok(cat) = dataset.get_cat(ann.category_id) {
+                shapes.extend(ann_shapes(ann, &cat.name));
+            }
+        }
+        let file = LabelMeFile {
+            version: LABELME_VERSION.to_string(),
+            flags: serde_json::Map::new(),
+            shapes,
+            image_path: Path::new(&img.file_name)
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned(),
+            image_data,
+            image_height: img.height,
+            image_width: img.width,
+        };
+
+        let json_path = dst_path.with_extension("json");
+        create_parent(&json_path)?;
+        let content = serde_json::to_string_pretty(&file)
+            .map_err(|err| ConversionError::Json(err, json_path.clone()))?;
+        fs::write(&json_path, content).map_err(|err| ConversionError::Write(err, json_path))?;
+    }
+    Ok(())
+}
+
+/// Path of the image relative to the LabelMe folder, from the path of the json file and the image path relative to it.
+fn image_file_name(json_path: &Path, image_path: &str) -> PathBuf {
+    // LabelMe files created on Windows use backslashes.
+    let image_path = image_path.replace('\\', "/");
+    let mut file_name = PathBuf::new();
+    for component in json_path
+        .parent()
+        .unwrap_or_else(|| Path::new(""))
+        .join(image_path)
+        .components()
+    {
+        match component {
+            Component::ParentDir => {
+                file_name.pop();
+            }
+            Component::Normal(name) => file_name.push(name),
+            _ => {}
+        }
+    }
+    file_name
+}
+
+/// Polygon of a shape, `None` for the shapes without area.
+fn shape_polygon(shape: &Shape) -> Option<Vec<f64>> {
+    let points = &shape.points;
+    match shape.shape_type.as_str() {
+        "polygon" | "linestrip" if points.len() >= 3 => {
+            Some(points.iter().flat_map(|point| *point).collect())
+        }
+        "rectangle" if points.len() == 2 => {
+            let (left, right) = (
+                points[0][0].min(points[1][0]),
+                points[0][0].max(points[1][0]),
+            );
+            let (top, bottom) = (
+                points[0][1].min(points[1][1]),
+                points[0][1].max(points[1][1]),
+            );
+            Some(bbox_polygon(&Bbox {
+                left,
+                top,
+                width: right - left,
+                height: bottom - top,
+            }))
+        }
+        "circle" if points.len() == 2 => {
+            let [cx, cy] = points[0];
+            let radius = (points[1][0] - cx).hypot(points[1][1] - cy);
+            Some(
+                (0..CIRCLE_POINTS)
+                    .flat_map(|i| {
+                        let angle = 2.0 * PI * f64::from(i) / f64::from(CIRCLE_POINTS);
+                        [cx + radius * angle.cos(), cy + radius * angle.sin()]
+                    })
+                    .collect(),
+            )
+        }
+        _ => None,
+    }
+}
+
+/// LabelMe shapes of an annotation.
+fn ann_shapes(ann: &Annotation, label: &str) -> Vec<Shape> {
+    let shape = |points: Vec<[f64; 2]>, group_id: Option<i64>, shape_type: &str| Shape {
+        label: label.to_string(),
+        points,
+        group_id,
+        shape_type: shape_type.to_string(),
+        flags: serde_json::Map::new(),
+    };
+
+    let polygons = ann_polygons(ann);
+    let bbox = &ann.bbox;
+    if is_bbox_rectangle(&polygons, bbox) {
+        let points = vec![
+            [bbox.left, bbox.top],
+            [bbox.left + bbox.width, bbox.top + bbox.height],
+        ];
+        return vec![shape(points, None, "rectangle")];
+    }
+
+    #[allow(clippy::cast_possible_wrap)]
+    let group_id = if polygons.len() > 1 {
+        Some(ann.id as i64)
+    } else {
+        None
+    };
+    polygons
+        .iter()
+        .map(|polygon| {
+            let points = polygon.chunks_exact(2).map(|xy| [xy[0], xy[1]]).collect();
+            shape(points, group_id, "polygon")
+        })
+        .collect()
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::float_cmp)]
+mod tests {
+    use super::*;
+
+    const LABELME_JSON: &str = r#"{
+  "version": "5.0.1",
+  "flags": {},
+  "shapes": [
+    {"label": "dog", "points": [[1, 2], [5, 6]], "group_id": null, "shape_type": "rectangle", "flags": {}},
+    {"label": "person", "points": [[0, 0], [4, 0], [4, 4]], "group_id": 1, "shape_type": "polygon", "flags": {}},
+    {"label": "person", "points": [[10, 0], [14, 0], [14, 4]], "group_id": 1, "shape_type": "polygon", "flags": {}},
+    {"label": "dog", "points": [[10, 10], [13, 10]], "group_id": null, "shape_type": "circle", "flags": {}},
+    {"label": "dog", "points": [[1, 1]], "group_id": null, "shape_type": "point", "flags": {}},
+    {"label": "cat", "points": [[0, 10], [4, 10], [4, 14]], "shape_type": "linestrip"}
+  ],
+  "imagePath": "..\\images\\a.png",
+  "imageData": "aGVsbG8=",
+  "imageHeight": 20,
+  "imageWidth": 30
+}"#;
+
+    fn labelme_folder() -> tempfile::TempDir {
+        let folder = tempfile::tempdir().unwrap();
+        fs::create_dir_all(folder.path().join("labels")).unwrap();
+        fs::write(folder.path().join("labels/a.json"), LABELME_JSON).unwrap();
+        folder
+    }
+
+    #[test]
+    fn import_shapes() {
+        let folder = labelme_folder();
+        let dataset = import(folder.path(), None).unwrap();
+
+        let cats: Vec<&str> = dataset
+            .get_cats()
+            .iter()
+            .map(|cat| cat.name.as_str())
+            .collect();
+        assert_eq!(cats, vec!["cat", "dog", "person"]);
+        let img = dataset.get_img(1).unwrap();
+        assert_eq!(
+            (img.file_name.as_str(), img.width, img.height),
+            ("images/a.png", 30, 20)
+        );
+
+        let anns = dataset.get_img_anns(1).unwrap();
+        assert_eq!(anns.len(), 4);
+        assert_eq!(
+            anns[0].bbox,
+            Bbox {
+                left: 1.0,
+                top: 2.0,
+                width: 4.0,
+                height: 4.0
+            }
+        );
+        assert_eq!(anns[0].area, 16.0);
+        // The two polygons of the person are merged.
+        assert_eq!(anns[1].category_id, 3);
+        match &anns[1].segmentation {
+            Segmentation::PolygonsRS(poly) => assert_eq!(poly.counts.len(), 2),
+            _ => panic!("Expected polygons."),
+        }
+        assert_eq!(anns[1].bbox.width, 14.0);
+        // The circle is approximated by a polygon.
+        assert_eq!(anns[2].bbox.width, 6.0);
+        assert_eq!(anns[3].category_id, 1);
+    }
+
+    #[test]
+    fn extract_images() {
+        let folder = labelme_folder();
+        let images_folder = folder.path().join("extracted");
+        let dataset = import(folder.path(), Some(images_folder.as_path())).unwrap();
+        assert_eq!(dataset.image_folder, images_folder);
+        assert_eq!(
+            fs::read_to_string(images_folder.join("images/a.png")).unwrap(),
+            "hello"
+        );
+    }
+
+    #[test]
+    fn export_import_roundtrip() {
+        let folder = labelme_folder();
+        let images_folder = folder.path().join("extracted");
+        let dataset = import(folder.path(), Some(images_folder.as_path())).unwrap();
+
+        let output_folder = tempfile::tempdir().unwrap();
+        export(&dataset, output_folder.path(), true, true).unwrap();
+        let exported: serde_json::Value = serde_json::from_str(
+            &fs::read_to_string(output_folder.path().join("images/a.json")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(exported["imagePath"], "a.png");
+        assert_eq!(exported["imageData"], "aGVsbG8=");
+        assert_eq!(exported["shapes"][0]["shape_type"], "rectangle");
+        assert_eq!(exported["shapes"][1]["group_id"], 2);
+        assert!(output_folder.path().join("images/a.png").is_file());
+
+        let reimported = import(output_folder.path(), None).unwrap();
+        let (anns, reimported_anns) = (dataset.get_anns(), reimported.get_anns());
+        assert_eq!(reimported_anns.len(), anns.len());
+        // The points of the circle are only equal up to the json float precision.
+        for (reimported_ann, ann) in reimported_anns.iter().zip(&anns) {
+            assert_eq!(
+                (reimported_ann.category_id, reimported_ann.area),
+                (ann.category_id, ann.area)
+            );
+            assert_eq!(reimported_ann.bbox, ann.bbox);
+        }
+        assert_eq!(reimported_anns[1].segmentation, anns[1].segmentation);
+        assert_eq!(reimported.get_cats(), dataset.get_cats());
+    }
+}
@@ -0,0 +1,779 @@
+//! Module to convert a COCO dataset to and from the [Label Studio](https://labelstud.io/) json format.
+//!
+//! A Label Studio json file is a list of tasks, each with the url of its image in `data` and the labeled regions in the `result`
+//! of its `annotations` (made by the annotators) and `predictions` (made by a model, used as pre-annotations).
+//! The coordinates of the regions are given in percentage of the size of the image.\
+//! Brush masks use Label Studio's own RLE, see [`decode_brush_rle`].
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::coco::object_detection::{
+    Annotation, Bbox, Dataset, ExtraFields, HashmapDataset, Image, PolygonsRS, Rle, Segmentation,
+};
+use crate::converters::{
+    ann_polygons, bbox_polygon, categories_from_names, category_id, create_parent,
+    is_bbox_rectangle,
+};
+use crate::errors::ConversionError;
+use crate::mask::conversions::rle_from_poly;
+use crate::mask::utils::Area;
+use crate::mask::Mask;
+
+/// Name of the labels control tag in the exported regions, the default of the Label Studio templates.
+const FROM_NAME: &str = "label";
+/// Name of the image object tag in the exported regions, the default of the Label Studio templates.
+const TO_NAME: &str = "image";
+/// Number of bits of the values encoded in the brush RLEs.
+const BRUSH_WORD_SIZE: u32 = 8;
+/// Number of bits available to encode the length of a run in the brush RLEs, the 2-bits index of the size is written before each run.
+const BRUSH_RLE_SIZES: [u32; 4] = [3, 4, 8, 16];
+
+#[derive(Deserialize, Serialize)]
+struct Task {
+    data: serde_json::Map<String, Value>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    annotations: Vec<Completion>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    predictions: Vec<Completion>,
+}
+
+/// An annotation or a prediction of a task.
+#[derive(Deserialize, Serialize)]
+struct Completion {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    model_version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    score: Option<f64>,
+    #[serde(default, skip_serializing)]
+    was_cancelled: bool,
+    #[serde(default)]
+    result: Vec<Region>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Region {
+    #[serde(default)]
+    id: String,
+    #[serde(rename = "type")]
+    region_type: String,
+    #[serde(default)]
+    from_name: String,
+    #[serde(default)]
+    to_name: String,
+    #[serde(default)]
+    original_width: Option<u32>,
+    #[serde(default)]
+    original_height: Option<u32>,
+    #[serde(default)]
+    image_rotation: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    score: Option<f64>,
+    #[serde(default)]
+    value: RegionValue,
+}
+
+/// Value of a region, whose fields depend on the type of the region.
+#[derive(Default, Deserialize, Serialize)]
+struct RegionValue {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    x: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    y: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    width: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    height: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rotation: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    points: Option<Vec<[f64; 2]>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    format: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rle: Option<Vec<u8>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rectanglelabels: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    polygonlabels: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    brushlabels: Option<Vec<String>>,
+}
+
+/// Import a Label Studio json export into a COCO dataset.
+///
+/// The rectangle, polygon and brush regions are converted, the other region types are ignored.
+/// The rectangles have their (possibly rotated) rectangle as segmentation, and the brush masks are converted to [`Rle`].\
+/// Only the first annotation of a task which was not cancelled is used, or the first prediction if `use_predictions` is true.
+/// The scores of the predicted regions (or of the predictions if the regions have none) are kept.
+///
+/// The categories are the labels sorted alphabetically, given the ids 1 to n. The images and annotations are given the ids 1 to n.\
+/// The file name of an image is the path after `?d=` in its url for the local storage, and the last component of its url otherwise.
+/// The size of an image is taken from its regions, or read from the image file if the task has no region.
+///
+/// ## Args
+/// - `json_path`: The path to the Label Studio json file.
+/// - `image_folder`: The folder with the images.
+/// - `use_predictions`: If true, import the predictions of the tasks instead of their annotations.
+///
+/// # Errors
+///
+/// Will return `Err` if a file cannot be read, if the json file is not a valid Label Studio export, or if a region is not valid.
+pub fn import<P: AsRef<Path>>(
+    json_path: P,
+    image_folder: P,
+    use_predictions: bool,
+) -> Result<HashmapDataset, ConversionError> {
+    let json_path = json_path.as_ref();
+    let image_folder = image_folder.as_ref();
+    let content = fs::read_to_string(json_path)
+        .map_err(|err| ConversionError::Read(err, json_path.to_path_buf()))?;
+    let tasks: Vec<Task> = serde_json::from_str(&content)
+        .map_err(|err| ConversionError::Json(err, json_path.to_path_buf()))?;
+
+    let completions: Vec<Option<&Completion>> = tasks
+        .iter()
+        .map(|task| {
+            if use_predictions {
+                task.predictions.first()
+            } else {
+                task.annotations
+                    .iter()
+                    .find(|annotation| !annotation.was_cancelled)
+            }
+        })
+        .collect();
+
+    let labels: BTreeSet<&str> = completions
+        .iter()
+        .flatten()
+        .flat_map(|completion| completion.result.iter().filter_map(region_label))
+        .collect();
+    let categories = categories_from_names(labels);
+
+    let mut dataset = Dataset::default();
+    for ((img_id, task), completion) in (1..).zip(&tasks).zip(completions) {
+        let url = task
+            .data
+            .get("image")
+            .or_else(|| task.data.values().next())
+            .and_then(Value::as_str)
+            .ok_or_else(|| {
+                ConversionError::InvalidContent(
+                    json_path.to_path_buf(),
+                    format!("the task {img_id} does not have an image url."),
+                )
+            })?;
+        let file_name = image_file_name(url);
+        let regions = completion.map_or(&[][..], |completion| &completion.result);
+        let size = regions
+            .iter()
+            .find_map(|region| region.original_width.zip(region.original_height));
+        let (width, height) = match size {
+            Some(size) => size,
+            None => {
+                let img_path = image_folder.join(&file_name);
+                image::image_dimensions(&img_path)
+                    .map_err(|err| ConversionError::ImageSize(err, img_path))?
+            }
+        };
+        let img = Image {
+            id: img_id,
+            width,
+            height,
+            file_name,
+            ..Default::default()
+        };
+
+        for region in regions {
+            let label = match region_label(region) {
+                Some(label) => label,
+                None => continue,
+            };
+            let (segmentation, area, bbox) =
+                region_segmentation(region, &img).map_err(|reason| {
+                    ConversionError::InvalidContent(
+                        json_path.to_path_buf(),
+                        format!("the region `{}` of the task {img_id} {reason}", region.id),
+                    )
+                })?;
+            dataset.annotations.push(Annotation {
+                id: dataset.annotations.len() as u64 + 1,
+                image_id: img.id,
+                category_id: category_id(&categories, label),
+                segmentation,
+                area,
+                bbox,
+                iscrowd: 0,
+                score: region
+                    .score
+                    .or_else(|| completion.and_then(|completion| completion.score)),
+                keypoints: None,
+                num_keypoints: None,
+                extra: ExtraFields::default(),
+            });
+        }
+        dataset.images.push(img);
+    }
+    dataset.categories = categories;
+
+    Ok(HashmapDataset::from_dataset(dataset, image_folder)?)
+}
+
+/// Export a COCO dataset as Label Studio pre-annotation tasks.
+///
+/// Each image becomes a task whose image url is `image_root` followed by the file name of the image, for example
+/// `/data/local-files/?d=` for a local storage or `s3://bucket/` for a cloud storage.
+/// The annotations of an image are written as the regions of a prediction, with their score if they have one, and the prediction
+/// is given the mean score of its regions. The regions use the `label` and `image` tag names of the Label Studio templates.
+///
+/// Annotations whose segmentation is empty or is the rectangle of their bounding box are exported as rectangles, RLE masks as brush masks,
+/// and the other annotations as polygons (one region per polygon).
+///
+/// ## Args
+/// - dataset: The COCO dataset to export.
+/// - `output_path`: The path of the json file to write.
+/// - `image_root`: The beginning of the image urls.
+/// - `model_version`: The model version given to the predictions.
+///
+/// # Errors
+///
+/// Will return `Err` if the json file cannot be written.
+pub fn export<P: AsRef<Path>>(
+    dataset: &HashmapDataset,
+    output_path: P,
+    image_root: &str,
+    model_version: &str,
+) -> Result<(), ConversionError> {
+    let output_path = output_path.as_ref();
+
+    let mut tasks = Vec::new();
+    for img in dataset.get_imgs() {
+        let mut data = serde_json::Map::new();
+        data.insert(
+            "image".to_string(),
+            format!("{image_root}{}", img.file_name).into(),
+        );
+
+        let mut regions = Vec::new();
+        let mut scores = Vec::new();
+        for ann in dataset.get_img_anns(img.id).unwrap_or_default() {
+            if let Ok(cat) = dataset.get_cat(ann.category_id) {
+                regions.extend(ann_regions(ann, img, &cat.name));
+                scores.extend(ann.score);
+            }
+        }
+        let predictions = if regions.is_empty() {
+            Vec::new()
+        } else {
+            #[allow(clippy::cast_precision_loss)]
+            let score =
+                (!scores.is_empty()).then(|| scores.iter().sum::<f64>() / scores.len() as f64);
+            vec![Completion {
+                model_version: Some(model_version.to_string()),
+                score,
+                was_cancelled: false,
+                result: regions,
+            }]
+        };
+        tasks.push(Task {
+            data,
+            annotations: Vec::new(),
+            predictions,
+        });
+    }
+
+    create_parent(output_path)?;
+    let content = serde_json::to_string(&tasks)
+        .map_err(|err| ConversionError::Json(err, output_path.to_path_buf()))?;
+    fs::write(output_path, content)
+        .map_err(|err| ConversionError::Write(err, output_path.to_path_buf()))
+}
+
+/// File name of an image from its Label Studio url.
+fn image_file_name(url: &str) -> String {
+    match url.split_once("?d=") {
+        Some((_, path)) => path.to_string(),
+        None => url.rsplit('/').next().unwrap_or(url).to_string(),
+    }
+}
+
+/// Label of a rectangle, polygon or brush region, `None` for the other regions.
+fn region_label(region: &Region) -> Option<&str> {
+    let labels = match region.region_type.as_str() {
+        "rectanglelabels" => &region.value.rectanglelabels,
+        "polygonlabels" => &region.value.polygonlabels,
+        "brushlabels" => &region.value.brushlabels,
+        _ => return None,
+    };
+    labels.as_ref()?.first().map(String::as_str)
+}
+
+/// Segmentation of a region with its area and bounding box, returning the reason why the region is invalid on failure.
+fn region_segmentation(region: &Region, img: &Image) -> Result<(Segmentation, f64, Bbox), String> {
+    let width = f64::from(img.width) / 100.0;
+    let height = f64::from(img.height) / 100.0;
+    let value = &region.value;
+    let polygon = match region.region_type.as_str() {
+        "rectanglelabels" => match (value.x, value.y, value.width, value.height) {
+            (Some(x), Some(y), Some(w), Some(h)) => {
+                let bbox = Bbox {
+                    left: x * width,
+                    top: y * height,
+                    width: w * width,
+                    height: h * height,
+                };
+                let angle = value.rotation.unwrap_or_default().to_radians();
+                // The rectangles are rotated clockwise around their top left corner.
+                bbox_polygon(&bbox)
+                    .chunks_exact(2)
+                    .flat_map(|xy| {
+                        let (dx, dy) = (xy[0] - bbox.left, xy[1] - bbox.top);
+                        [
+                            bbox.left + dx * angle.cos() - dy * angle.sin(),
+                            bbox.top + dx * angle.sin() + dy * angle.cos(),
+                        ]
+                    })
+                    .collect()
+            }
+            _ => return Err("does not have a position and a size.".to_string()),
+        },
+        "polygonlabels" => match &value.points {
+            Some(points) if points.len() >= 3 => points
+                .iter()
+                .flat_map(|[x, y]| [x * width, y * height])
+                .collect(),
+            _ => return Err("does not have at least 3 points.".to_string()),
+        },
+        _ => {
+            let rle = match (&value.format, &value.rle) {
+                (Some(format), Some(rle)) if format == "rle" => rle,
+                _ => return Err("does not have a mask in the rle format.".to_string()),
+            };
+            let (width, height) = (img.width as usize, img.height as usize);
+            let values = decode_brush_rle(rle, 4 * width * height)?;
+            // The values are the RGBA channels of the mask image, the alpha channel giving the mask.
+            let mask = Mask::from_shape_fn((height, width), |(y, x)| {
+                u8::from(values[4 * (y * width + x) + 3] != 0)
+            });
+            let rle = Rle::from(&mask);
+            let (area, bbox) = (f64::from(rle.area()), Bbox::from(&rle));
+            return Ok((Segmentation::Rle(rle), area, bbox));
+        }
+    };
+    let poly = PolygonsRS {
+        size: vec![img.height, img.width],
+        counts: vec![polygon],
+    };
+    let (area, bbox) = (
+        f64::from(rle_from_poly(&poly).area()),
+        Bbox::from(&poly.counts),
+    );
+    Ok((Segmentation::PolygonsRS(poly), area, bbox))
+}
+
+/// Label Studio regions of an annotation.
+fn ann_regions(ann: &Annotation, img: &Image, label: &str) -> Vec<Region> {
+    let width = f64::from(img.width) / 100.0;
+    let height = f64::from(img.height) / 100.0;
+    let region = |id: String, region_type: &str, value: RegionValue| Region {
+        id,
+        region_type: region_type.to_string(),
+        from_name: FROM_NAME.to_string(),
+        to_name: TO_NAME.to_string(),
+        original_width: Some(img.width),
+        original_height: Some(img.height),
+        image_rotation: 0.0,
+        score: ann.score,
+        value,
+    };
+    let labels = Some(vec![label.to_string()]);
+
+    match &ann.segmentation {
+        Segmentation::Rle(rle) => {
+            return vec![region(
+                ann.id.to_string(),
+                "brushlabels",
+                brush_value(rle, label),
+            )]
+        }
+        Segmentation::CocoRle(coco_rle) => {
+            return vec![region(
+                ann.id.to_string(),
+                "brushlabels",
+                brush_value(&Rle::from(coco_rle), label),
+            )]
+        }
+        Segmentation::Polygons(_) | Segmentation::PolygonsRS(_) => {}
+    }
+
+    let polygons = ann_polygons(ann);
+    let bbox = &ann.bbox;
+    if is_bbox_rectangle(&polygons, bbox) {
+        let value = RegionValue {
+            x: Some(bbox.left / width),
+            y: Some(bbox.top / height),
+            width: Some(bbox.width / width),
+            height: Some(bbox.height / height),
+            rotation: Some(0.0),
+            rectanglelabels: labels,
+            ..Default::default()
+        };
+        return vec![region(ann.id.to_string(), "rectanglelabels", value)];
+    }
+    polygons
+        .iter()
+        .enumerate()
+        .map(|(i, polygon)| {
+            let value = RegionValue {
+                points: Some(
+                    polygon
+                        .chunks_exact(2)
+                        .map(|xy| [xy[0] / width, xy[1] / height])
+                        .collect(),
+                ),
+                polygonlabels: labels.clone(),
+                ..Default::default()
+            };
+            let id = if polygons.len() == 1 {
+                ann.id.to_string()
+            } else {
+                format!("{}_{i}", ann.id)
+            };
+            region(id, "polygonlabels", value)
+        })
+        .collect()
+}
+
+/// Value of the brush region of a RLE mask.
+fn brush_value(rle: &Rle, label: &str) -> RegionValue {
+    RegionValue {
+        format: Some("rle".to_string()),
+        rle: Some(encode_brush_rle(&Mask::from(rle))),
+        brushlabels: Some(vec![label.to_string()]),
+        ..Default::default()
+    }
+}
+
+/// Reads the bits of a byte array, most significant bit first.
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl BitReader<'_> {
+    fn read(&mut self, nb_bits: u32) -> Result<u32, String> {
+        let mut value = 0;
+        for _ in 0..nb_bits {
+            let byte = self
+                .bytes
+                .get(self.position / 8)
+                .ok_or_else(|| "has a brush rle which ends unexpectedly.".to_string())?;
+            value = (value << 1) | u32::from((byte >> (7 - self.position % 8)) & 1);
+            self.position += 1;
+        }
+        Ok(value)
+    }
+}
+
+/// Writes bits to a byte array, most significant bit first.
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    nb_bits: usize,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u32, nb_bits: u32) {
+        for shift in (0..nb_bits).rev() {
+            if self.nb_bits % 8 == 0 {
+                self.bytes.push(0);
+            }
+            if (value >> shift) & 1 == 1 {
+                if let Some(byte) = self.bytes.last_mut() {
+                    *byte |= 1 << (7 - self.nb_bits % 8);
+                }
+            }
+            self.nb_bits += 1;
+        }
+    }
+}
+
+/// Decode a Label Studio brush RLE, giving the values of the RGBA channels of the mask image in row-major order.
+///
+/// The RLE is a bit stream (port of `decode_rle` from the label-studio-converter): the number of values (32 bits),
+/// the number of bits of a value minus 1 (5 bits) and the number of bits of each of the 4 run length sizes minus 1 (4 bits each).
+/// Then each block starts with a bit telling if it is a run of a single value, followed by the index of the size (2 bits)
+/// and the length of the block minus 1, and then either the value of the run or the values of the block.
+///
+/// The number of values given by the bit stream must be `nb_values`, the size of the image times its 4 channels.
+///
+/// # Errors
+///
+/// Will return `Err` if the number of values is not `nb_values` or if the bit stream ends before all the values are decoded, with the reason as message.
+pub fn decode_brush_rle(rle: &[u8], nb_values: usize) -> Result<Vec<u8>, String> {
+    let mut reader = BitReader {
+        bytes: rle,
+        position: 0,
+    };
+    let rle_nb_values = reader.read(32)? as usize;
+    if rle_nb_values != nb_values {
+        return Err(format!(
+            "has a mask of {rle_nb_values} values instead of {nb_values}."
+        ));
+    }
+    let word_size = reader.read(5)? + 1;
+    let mut rle_sizes = [0; 4];
+    for rle_size in &mut rle_sizes {
+        *rle_size = reader.read(4)? + 1;
+    }
+
+    let mut values = Vec::with_capacity(nb_values);
+    while values.len() < nb_values {
+        let is_run = reader.read(1)? == 1;
+        let rle_size = rle_sizes[reader.read(2)? as usize];
+        let length = reader.read(rle_size)? as usize + 1;
+        if is_run {
+            #[allow(clippy::cast_possible_truncation)]
+            let value = reader.read(word_size)? as u8;
+            values.extend(std::iter::repeat(value).take(length));
+        } else {
+            for _ in 0..length {
+                #[allow(clippy::cast_possible_truncation)]
+                values.push(reader.read(word_size)? as u8);
+            }
+        }
+    }
+    values.truncate(nb_values);
+    Ok(values)
+}
+
+/// Encode a mask as a Label Studio brush RLE, the mask pixels having the value 255 in the 4 channels of the image, see [`decode_brush_rle`].
+#[must_use]
+pub fn encode_brush_rle(mask: &Mask) -> Vec<u8> {
+    let mut writer = BitWriter::default();
+    #[allow(clippy::cast_possible_truncation)]
+    writer.write(4 * mask.len() as u32, 32);
+    writer.write(BRUSH_WORD_SIZE - 1, 5);
+    for rle_size in BRUSH_RLE_SIZES {
+        writer.write(rle_size - 1, 4);
+    }
+
+    // Runs of pixels in row-major order, each pixel giving 4 values.
+    let mut runs: Vec<(u32, u32)> = Vec::new();
+    for value in mask.iter() {
+        let value = if *value == 0 { 0 } else { 255 };
+        match runs.last_mut() {
+            Some((run_value, length)) if *run_value == value => *length += 4,
+            _ => runs.push((value, 4)),
+        }
+    }
+    let max_length = 1 << BRUSH_RLE_SIZES[3];
+    for (value, mut length) in runs {
+        while length > 0 {
+            let block_length = length.min(max_length);
+            let size_index = BRUSH_RLE_SIZES
+                .iter()
+                .position(|size| block_length - 1 < 1 << size)
+                .unwrap_or(3);
+            writer.write(1, 1);
+            #[allow(clippy::cast_possible_truncation)]
+            writer.write(size_index as u32, 2);
+            writer.write(block_length - 1, BRUSH_RLE_SIZES[size_index]);
+            writer.write(value, BRUSH_WORD_SIZE);
+            length -= block_length;
+        }
+    }
+    writer.bytes
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    /// A 10x20 image with a rectangle, a rotated rectangle, a polygon, a brush mask and a text region.
+    fn json_content() -> String {
+        let mut mask = Mask::zeros((10, 20));
+        mask.slice_mut(ndarray::s![2..5, 1..4]).fill(1);
+        let rle = serde_json::to_string(&encode_brush_rle(&mask)).unwrap();
+        format!(
+            r#"[
+            {{"id": 1, "data": {{"image": "/data/local-files/?d=images/a.jpg"}}, "annotations": [
+                {{"was_cancelled": true, "result": []}},
+                {{"result": [
+                    {{"id": "r1", "type": "rectanglelabels", "from_name": "label", "to_name": "image", "original_width": 20, "original_height": 10,
+                      "value": {{"x": 10, "y": 20, "width": 50, "height": 40, "rotation": 0, "rectanglelabels": ["car"]}}}},
+                    {{"id": "r2", "type": "rectanglelabels", "original_width": 20, "original_height": 10,
+                      "value": {{"x": 50, "y": 0, "width": 10, "height": 20, "rotation": 90, "rectanglelabels": ["car"]}}}},
+                    {{"id": "p1", "type": "polygonlabels", "original_width": 20, "original_height": 10,
+                      "value": {{"points": [[0, 0], [50, 0], [50, 50]], "polygonlabels": ["person"]}}}},
+                    {{"id": "b1", "type": "brushlabels", "original_width": 20, "original_height": 10,
+                      "value": {{"format": "rle", "rle": {rle}, "brushlabels": ["person"]}}}},
+                    {{"id": "t1", "type": "textarea", "value": {{"text": ["hello"]}}}}
+                ]}}
+            ]}},
+            {{"id": 2, "data": {{"image": "s3://bucket/images/b.jpg"}}, "predictions": [
+                {{"score": 0.5, "result": [
+                    {{"id": "r3", "type": "rectanglelabels", "original_width": 20, "original_height": 10, "score": 0.9,
+                      "value": {{"x": 0, "y": 0, "width": 10, "height": 10, "rectanglelabels": ["dog"]}}}}
+                ]}}
+            ]}}
+        ]"#
+        )
+    }
+
+    /// A folder with the Label Studio json file and the images, which are read for the tasks without regions.
+    fn label_studio_folder(content: &str) -> tempfile::TempDir {
+        let folder = tempfile::tempdir().unwrap();
+        fs::write(folder.path().join("export.json"), content).unwrap();
+        fs::create_dir(folder.path().join("images")).unwrap();
+        for name in ["images/a.jpg", "b.jpg"] {
+            image::RgbImage::new(20, 10)
+                .save(folder.path().join(name))
+                .unwrap();
+        }
+        folder
+    }
+
+    fn import_str(content: &str, use_predictions: bool) -> Result<HashmapDataset, ConversionError> {
+        let folder = label_studio_folder(content);
+        import(
+            folder.path().join("export.json").as_path(),
+            folder.path(),
+            use_predictions,
+        )
+    }
+
+    #[test]
+    fn brush_rle_roundtrip() {
+        let mask = Mask::from_shape_fn((7, 300), |(y, x)| u8::from(x > 20 && (y + x) % 5 != 0));
+        let rle = encode_brush_rle(&mask);
+        // The number of values, followed by the word size and the run length sizes.
+        assert_eq!(rle[..4], (7u32 * 300 * 4).to_be_bytes());
+        let values = decode_brush_rle(&rle, 7 * 300 * 4).unwrap();
+        assert_eq!(values.len(), 7 * 300 * 4);
+        assert!(values[..4].iter().all(|value| *value == 0));
+        let decoded = Mask::from_shape_fn((7, 300), |(y, x)| {
+            u8::from(values[4 * (y * 300 + x) + 3] != 0)
+        });
+        assert_eq!(decoded, mask);
+        assert!(decode_brush_rle(&rle[..rle.len() / 2], 7 * 300 * 4).is_err());
+        // The number of values is checked before decoding anything.
+        assert!(decode_brush_rle(&rle, 7 * 300).is_err());
+        assert!(decode_brush_rle(&u32::MAX.to_be_bytes(), 7 * 300 * 4).is_err());
+    }
+
+    #[test]
+    fn import_annotations() {
+        let dataset = import_str(&json_content(), false).unwrap();
+        let cats: Vec<&str> = dataset
+            .get_cats()
+            .iter()
+            .map(|cat| cat.name.as_str())
+            .collect();
+        assert_eq!(cats, vec!["car", "person"]);
+        assert_eq!(dataset.get_img(1).unwrap().file_name, "images/a.jpg");
+        assert_eq!(dataset.get_img(2).unwrap().file_name, "b.jpg");
+        assert!(dataset.get_img_anns(2).unwrap().is_empty());
+
+        let anns = dataset.get_img_anns(1).unwrap();
+        assert_eq!(anns.len(), 4);
+        assert_eq!(
+            anns[0].bbox,
+            Bbox {
+                left: 2.0,
+                top: 2.0,
+                width: 10.0,
+                height: 4.0
+            }
+        );
+        // The rectangle is rotated by 90 degrees around its top left corner.
+        let rotated = &anns[1].bbox;
+        assert!((rotated.left - 8.0).abs() < 1e-9 && rotated.top.abs() < 1e-9);
+        assert!((rotated.width - 2.0).abs() < 1e-9 && (rotated.height - 2.0).abs() < 1e-9);
+        assert_eq!(anns[2].category_id, 2);
+        assert_eq!(
+            anns[3].bbox,
+            Bbox {
+                left: 1.0,
+                top: 2.0,
+                width: 3.0,
+                height: 3.0
+            }
+        );
+        assert_eq!(anns[3].area, 9.0);
+        assert!(matches!(anns[3].segmentation, Segmentation::Rle(_)));
+    }
+
+    #[test]
+    fn import_predictions() {
+        let dataset = import_str(&json_content(), true).unwrap();
+        let anns = dataset.get_anns();
+        assert_eq!(anns.len(), 1);
+        assert_eq!((anns[0].image_id, anns[0].score), (2, Some(0.9)));
+        assert_eq!(dataset.get_img(1).unwrap().width, 20);
+    }
+
+    #[test]
+    fn invalid_region() {
+        let content = r#"[{"data": {"image": "a.jpg"}, "annotations": [{"result": [
+            {"id": "p1", "type": "polygonlabels", "original_width": 20, "original_height": 10,
+             "value": {"points": [[0, 0], [50, 0]], "polygonlabels": ["person"]}}
+        ]}]}]"#;
+        assert!(matches!(
+            import_str(content, false),
+            Err(ConversionError::InvalidContent(_, _))
+        ));
+    }
+
+    #[test]
+    fn export_import_roundtrip() {
+        let folder = label_studio_folder(&json_content());
+        let mut dataset = import(
+            folder.path().join("export.json").as_path(),
+            folder.path(),
+            false,
+        )
+        .unwrap();
+        for ann in dataset.anns.values_mut() {
+            ann.score = Some(0.5 + 0.1 * ann.id as f64);
+        }
+        let json_path = folder.path().join("tasks.json");
+        export(&dataset, &json_path, "/data/local-files/?d=", "v1").unwrap();
+
+        let tasks: Value = serde_json::from_str(&fs::read_to_string(&json_path).unwrap()).unwrap();
+        assert_eq!(
+            tasks[0]["data"]["image"],
+            "/data/local-files/?d=images/a.jpg"
+        );
+        assert_eq!(tasks[0]["predictions"][0]["model_version"], "v1");
+        assert!((tasks[0]["predictions"][0]["score"].as_f64().unwrap() - 0.75).abs() < 1e-9);
+        assert_eq!(
+            tasks[0]["predictions"][0]["result"][0]["type"],
+            "rectanglelabels"
+        );
+        assert_eq!(
+            tasks[0]["predictions"][0]["result"][3]["type"],
+            "brushlabels"
+        );
+        assert!(tasks[1].get("predictions").is_none());
+
+        let reimported = import(json_path.as_path(), folder.path(), true).unwrap();
+        assert_eq!(reimported.get_cats(), dataset.get_cats());
+        let (anns, reimported_anns) = (dataset.get_anns(), reimported.get_anns());
+        assert_eq!(reimported_anns.len(), anns.len());
+        for (reimported_ann, ann) in reimported_anns.iter().zip(&anns) {
+            assert_eq!(
+                (reimported_ann.category_id, reimported_ann.score),
+                (ann.category_id, ann.score)
+            );
+            assert!((reimported_ann.bbox.left - ann.bbox.left).abs() < 1e-9);
+            assert!((reimported_ann.area - ann.area).abs() < 1e-9);
+        }
+        assert_eq!(reimported_anns[3].segmentation, anns[3].segmentation);
+    }
+}
@@ -0,0 +1,312 @@
+//! Module to import the bounding boxes of an [Open Images](https://storage.googleapis.com/openimages/web/download.html) style dataset.
+//!
+//! The boxes are given in a csv file with one row per box, and the columns
+//! `ImageID,Source,LabelName,Confidence,XMin,XMax,YMin,YMax,IsOccluded,IsTruncated,IsGroupOf,IsDepiction,IsInside`.\
+//! The coordinates are normalized by the size of the image, and the labels are class MIDs (for example `/m/0k4j`),
+//! whose names are given in a class description csv file with the rows `LabelName,DisplayName`.
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::coco::object_detection::{
+    Annotation, Bbox, Category, Dataset, ExtraFields, HashmapDataset, Image, PolygonsRS,
+    Segmentation,
+};
+use crate::converters::{bbox_polygon, IMAGE_EXTENSIONS};
+use crate::errors::ConversionError;
+use crate::mask::conversions::rle_from_poly;
+use crate::mask::utils::Area;
+
+/// Row of the bounding boxes csv file. The flags are -1 when unknown.
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct BoxRow {
+    #[serde(rename = "ImageID")]
+    image_id: String,
+    label_name: String,
+    x_min: f64,
+    x_max: f64,
+    y_min: f64,
+    y_max: f64,
+    #[serde(default)]
+    is_occluded: Option<i8>,
+    #[serde(default)]
+    is_truncated: Option<i8>,
+    #[serde(default)]
+    is_group_of: Option<i8>,
+    #[serde(default)]
+    is_depiction: Option<i8>,
+    #[serde(default)]
+    is_inside: Option<i8>,
+}
+
+/// Import the bounding boxes of an Open Images style dataset.
+///
+/// The csv file is read row by row, so that only the imported annotations are kept in memory.
+/// The rows of the images which are not in the image folder (`<ImageID>.jpg` or another image extension) are skipped,
+/// which allows importing a subset of the dataset with the full csv file.
+///
+/// The categories are the classes of the class description file, in the order of the file and given the ids 1 to n.
+/// The images and annotations are given the ids 1 to n, in the order of the csv file. The size of the images is read from the image files.\
+/// Groups of objects (`IsGroupOf`) become crowd annotations (`iscrowd=1`), and the `IsOccluded`, `IsTruncated`, `IsDepiction` and `IsInside`
+/// flags are kept in the [`Annotation::extra`] fields as `occluded`, `truncated`, `depiction` and `inside` when they are known.
+/// The segmentation of the annotations is the rectangle of their bounding box.
+///
+/// ## Args
+/// - `boxes_csv`: The csv file with the bounding boxes.
+/// - `class_descriptions_csv`: The csv file with the name of each class MID, with or without header.
+/// - `image_folder`: The folder with the images.
+///
+/// # Errors
+///
+/// Will return `Err` if a csv file cannot be read or parsed, if a box has a class MID which is not in the class description file,
+/// or if the size of an image cannot be read.
+pub fn import<P: AsRef<Path>>(
+    boxes_csv: P,
+    class_descriptions_csv: P,
+    image_folder: P,
+) -> Result<HashmapDataset, ConversionError> {
+    let boxes_csv = boxes_csv.as_ref();
+    let image_folder = image_folder.as_ref();
+
+    let categories = load_class_descriptions(class_descriptions_csv)?;
+    let category_ids: HashMap<&str, u32> = categories
+        .iter()
+        .map(|(label_name, cat)| (label_name.as_str(), cat.id))
+        .collect();
+
+    let mut dataset = Dataset::default();
+    // Index of each image in the dataset's images, `None` if the image is not in the image folder.
+    let mut image_indices: HashMap<String, Option<usize>> = HashMap::new();
+    let mut reader = csv::Reader::from_path(boxes_csv)
+        .map_err(|err| ConversionError::Csv(err, boxes_csv.to_path_buf()))?;
+    for row in reader.deserialize() {
+        let row: BoxRow = row.map_err(|err| ConversionError::Csv(err, boxes_csv.to_path_buf()))?;
+
+        let image_index = match image_indices.get(&row.image_id) {
+            Some(image_index) => *image_index,
+            None => {
+                let image_index = match find_image(image_folder, &row.image_id) {
+                    Some(file_name) => {
+                        let img_path = image_folder.join(&file_name);
+                        let (width, height) = image::image_dimensions(&img_path)
+                            .map_err(|err| ConversionError::ImageSize(err, img_path))?;
+                        dataset.images.push(Image {
+                            id: dataset.images.len() as u64 + 1,
+                            width,
+                            height,
+                            file_name,
+                            ..Default::default()
+                        });
+                        Some(dataset.images.len() - 1)
+                    }
+                    None => None,
+                };
+                image_indices.insert(row.image_id.clone(), image_index);
+                image_index
+            }
+        };
+        let img = match image_index {
+            Some(image_index) => &dataset.images[image_index],
+            None => continue,
+        };
+
+        let category_id = *category_ids.get(row.label_name.as_str()).ok_or_else(|| {
+            ConversionError::InvalidContent(
+                boxes_csv.to_path_buf(),
+                format!(
+                    "the class `{}` of a box of the image `{}` is not in the class descriptions.",
+                    row.label_name, row.image_id
+                ),
+            )
+        })?;
+        let width = f64::from(img.width);
+        let height = f64::from(img.height);
+        let bbox = Bbox {
+            left: row.x_min * width,
+            top: row.y_min * height,
+            width: (row.x_max - row.x_min) * width,
+            height: (row.y_max - row.y_min) * height,
+        };
+        let segmentation = PolygonsRS {
+            size: vec![img.height, img.width],
+            counts: vec![bbox_polygon(&bbox)],
+        };
+
+        let mut extra = ExtraFields::default();
+        for (name, flag) in [
+            ("occluded", row.is_occluded),
+            ("truncated", row.is_truncated),
+            ("depiction", row.is_depiction),
+            ("inside", row.is_inside),
+        ] {
+            if let Some(flag) = flag.filter(|flag| *flag >= 0) {
+                extra.insert(name.to_string(), flag.into());
+            }
+        }
+
+        dataset.annotations.push(Annotation {
+            id: dataset.annotations.len() as u64 + 1,
+            image_id: img.id,
+            category_id,
+            area: f64::from(rle_from_poly(&segmentation).area()),
+            segmentation: Segmentation::PolygonsRS(segmentation),
+            bbox,
+            iscrowd: u32::from(row.is_group_of == Some(1)),
+            score: None,
+            keypoints: None,
+            num_keypoints: None,
+            extra,
+        });
+    }
+    dataset.categories = categories.into_iter().map(|(_, cat)| cat).collect();
+
+    Ok(HashmapDataset::from_dataset(dataset, image_folder)?)
+}
+
+/// Load the class description csv file, giving the category of each class MID.
+fn load_class_descriptions<P: AsRef<Path>>(
+    path: P,
+) -> Result<Vec<(String, Category)>, ConversionError> {
+    let path = path.as_ref();
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .from_path(path)
+        .map_err(|err| ConversionError::Csv(err, path.to_path_buf()))?;
+
+    let mut categories = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|err| ConversionError::Csv(err, path.to_path_buf()))?;
+        match (record.get(0), record.get(1)) {
+            // The header of the recent versions of the file.
+            (Some("LabelName"), _) if categories.is_empty() => {}
+            (Some(label_name), Some(display_name)) => {
+                let category = Category {
+                    id: categories.len() as u32 + 1,
+                    name: display_name.to_string(),
+                    supercategory: String::new(),
+                    keypoints: None,
+                    skeleton: None,
+                    extra: ExtraFields::default(),
+                };
+                categories.push((label_name.to_string(), category));
+            }
+            _ => {
+                return Err(ConversionError::InvalidContent(
+                    path.to_path_buf(),
+                    format!(
+                        "expected rows with a class MID and a name, got {:?}.",
+                        record.iter().collect::<Vec<_>>()
+                    ),
+                ))
+            }
+        }
+    }
+    Ok(categories)
+}
+
+/// File name of the image with the given id in the image folder, if there is one.
+fn find_image(image_folder: &Path, image_id: &str) -> Option<String> {
+    IMAGE_EXTENSIONS
+        .iter()
+        .map(|extension| format!("{image_id}.{extension}"))
+        .find(|file_name| image_folder.join(file_name).is_file())
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::fs;
+
+    use image::RgbImage;
+    use rstest::rstest;
+
+    use super::*;
+
+    const BOXES: &str = "ImageID,Source,LabelName,Confidence,XMin,XMax,YMin,YMax,IsOccluded,IsTruncated,IsGroupOf,IsDepiction,IsInside
+000002b66c9c498e,xclick,/m/01g317,1,0.1,0.5,0.2,0.6,0,1,0,0,0
+000002b66c9c498e,xclick,/m/0284d,1,0.0,1.0,0.5,1.0,1,0,1,0,-1
+00000c4a2a6a0bc1,xclick,/m/01g317,1,0.0,0.5,0.0,0.5,0,0,0,0,0
+000002b97e5471a0,activemil,/m/0284d,1,0.25,0.75,0.25,0.75,-1,-1,-1,-1,-1
+";
+
+    /// A folder with the csv files and two of the three images.
+    fn open_images_folder(class_descriptions: &str) -> tempfile::TempDir {
+        let folder = tempfile::tempdir().unwrap();
+        fs::write(folder.path().join("boxes.csv"), BOXES).unwrap();
+        fs::write(folder.path().join("classes.csv"), class_descriptions).unwrap();
+        RgbImage::new(100, 50)
+            .save(folder.path().join("000002b66c9c498e.jpg"))
+            .unwrap();
+        RgbImage::new(40, 40)
+            .save(folder.path().join("000002b97e5471a0.png"))
+            .unwrap();
+        folder
+    }
+
+    fn import_folder(folder: &tempfile::TempDir) -> Result<HashmapDataset, ConversionError> {
+        import(
+            folder.path().join("boxes.csv"),
+            folder.path().join("classes.csv"),
+            folder.path().to_path_buf(),
+        )
+    }
+
+    #[rstest]
+    #[case::header(
+        "LabelName,DisplayName\n/m/01g317,Person\n/m/0284d,Dairy Product\n/m/0k4j,Car\n"
+    )]
+    #[case::no_header("/m/01g317,Person\n/m/0284d,Dairy Product\n/m/0k4j,Car\n")]
+    fn import_boxes(#[case] class_descriptions: &str) {
+        let folder = open_images_folder(class_descriptions);
+        let dataset = import_folder(&folder).unwrap();
+
+        let cats: Vec<&str> = dataset
+            .get_cats()
+            .iter()
+            .map(|cat| cat.name.as_str())
+            .collect();
+        assert_eq!(cats, vec!["Person", "Dairy Product", "Car"]);
+        let imgs: Vec<(&str, u32, u32)> = dataset
+            .get_imgs()
+            .iter()
+            .map(|img| (img.file_name.as_str(), img.width, img.height))
+            .collect();
+        assert_eq!(
+            imgs,
+            vec![
+                ("000002b66c9c498e.jpg", 100, 50),
+                ("000002b97e5471a0.png", 40, 40)
+            ]
+        );
+
+        let anns = dataset.get_anns();
+        assert_eq!(anns.len(), 3);
+        assert_eq!(
+            anns[0].bbox,
+            Bbox {
+                left: 10.0,
+                top: 10.0,
+                width: 40.0,
+                height: 20.0
+            }
+        );
+        assert_eq!((anns[0].iscrowd, anns[0].area), (0, 800.0));
+        assert_eq!(anns[0].extra["truncated"], 1);
+        assert_eq!((anns[1].category_id, anns[1].iscrowd), (2, 1));
+        assert_eq!(anns[1].extra["occluded"], 1);
+        assert!(!anns[1].extra.contains_key("inside"));
+        assert_eq!(anns[2].image_id, 2);
+        assert!(anns[2].extra.is_empty());
+    }
+
+    #[test]
+    fn unknown_class() {
+        let folder = open_images_folder("/m/01g317,Person\n");
+        assert!(matches!(
+            import_folder(&folder),
+            Err(ConversionError::InvalidContent(_, _))
+        ));
+    }
+}
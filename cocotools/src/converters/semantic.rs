@@ -0,0 +1,459 @@
+//! Module to convert a COCO dataset to and from semantic segmentation label maps.
+//!
+//! A label map is an 8-bit grayscale PNG image with the size of the image it labels, in which the value of each pixel is the index of its category.
+//! The categories are mapped to the indices 0 to n-1 by increasing category id (see [`class_indices`]), and their names are written
+//! one per line, in index order, in a `labels.txt` file next to the label maps.\
+//! The pixels without any category have the ignore value, usually 255.
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use clap::ValueEnum;
+use image::{ColorType, GrayImage, Luma};
+use imageproc::region_labelling::{connected_components, Connectivity};
+use ndarray::Array2;
+
+use crate::coco::object_detection::{
+    Annotation, Bbox, Dataset, ExtraFields, HashmapDataset, Image, PolygonsRS, Rle, Segmentation,
+};
+use crate::coco::panoptic::rles_from_segment_ids;
+use crate::converters::yolo::class_indices;
+use crate::converters::{categories_from_names, create_parent, list_files, IMAGE_EXTENSIONS};
+use crate::errors::{ConversionError, MaskError};
+use crate::mask::conversions::rle_from_poly;
+use crate::mask::utils::Area;
+use crate::mask::Mask;
+
+/// Which annotation gives its category to the pixels where several annotations overlap.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Overlap {
+    /// The annotation that comes last in the dataset.
+    Last,
+    /// The annotation that comes first in the dataset.
+    First,
+    /// The smallest annotation, so that small objects are not hidden by the large ones around them.
+    Smallest,
+}
+
+/// Export a COCO dataset to semantic segmentation label maps.
+///
+/// The following files are created in the output folder:
+/// - `labels.txt`, with the category names in index order.
+/// - `<image name>.png`, the label map of each image.
+///
+/// Annotations with a category not in the dataset are skipped. Crowd annotations are painted like the others.
+///
+/// ## Args
+/// - dataset: The COCO dataset to export.
+/// - `output_folder`: The folder where the label maps will be written, with the same structure as the image folder.
+/// - `ignore_value`: The value of the pixels without any category.
+/// - overlap: Which annotation is kept where several annotations overlap.
+///
+/// # Errors
+///
+/// Will return `Err` if the ignore value is a category index, if a segmentation cannot be decoded, or if a file cannot be written.
+pub fn export<P: AsRef<Path>>(
+    dataset: &HashmapDataset,
+    output_folder: P,
+    ignore_value: u8,
+    overlap: Overlap,
+) -> Result<(), ConversionError> {
+    let output_folder = output_folder.as_ref();
+
+    let mut cats = dataset.get_cats();
+    cats.sort_by_key(|cat| cat.id);
+    let labels_path = output_folder.join("labels.txt");
+    create_parent(&labels_path)?;
+    let content = cats
+        .iter()
+        .map(|cat| format!("{}\n", cat.name))
+        .collect::<String>();
+    fs::write(&labels_path, content).map_err(|err| ConversionError::Write(err, labels_path))?;
+
+    for img in dataset.get_imgs() {
+        let label_map = label_map(dataset, img, ignore_value, overlap)?;
+        let label_map_path = output_folder.join(&img.file_name).with_extension("png");
+        create_parent(&label_map_path)?;
+        label_map
+            .save(&label_map_path)
+            .map_err(|err| ConversionError::Image(err, label_map_path))?;
+    }
+    Ok(())
+}
+
+/// Create the semantic segmentation label map of an image.
+///
+/// ## Args
+/// - dataset: The COCO dataset the image belongs to.
+/// - img: The image whose annotations are painted.
+/// - `ignore_value`: The value of the pixels without any category.
+/// - overlap: Which annotation is kept where several annotations overlap.
+///
+/// # Errors
+///
+/// Will return `Err` if the ignore value is a category index, or if a segmentation cannot be decoded or does not have the size of the image.
+pub fn label_map(
+    dataset: &HashmapDataset,
+    img: &Image,
+    ignore_value: u8,
+    overlap: Overlap,
+) -> Result<GrayImage, ConversionError> {
+    let class_indices = class_indices(dataset);
+    if usize::from(ignore_value) < class_indices.len() {
+        return Err(ConversionError::IgnoreValueConflict(
+            class_indices.len(),
+            ignore_value,
+        ));
+    }
+
+    let mut masks = Vec::new();
+    for ann in dataset.get_img_anns(img.id).unwrap_or_default() {
+        if let Some(class_index) = class_indices.get(&ann.category_id) {
+            let mask = ann_mask(ann, img)?;
+            #[allow(clippy::cast_possible_truncation)]
+            masks.push((*class_index as u8, mask));
+        }
+    }
+    match overlap {
+        Overlap::Last => {}
+        Overlap::First => masks.reverse(),
+        Overlap::Smallest => {
+            masks.sort_by_key(|(_, mask)| {
+                std::cmp::Reverse(mask.iter().filter(|&&m| m != 0).count())
+            });
+        }
+    }
+
+    let mut label_map = GrayImage::from_pixel(img.width, img.height, Luma([ignore_value]));
+    for (class_index, mask) in &masks {
+        for ((y, x), value) in mask.indexed_iter() {
+            if *value != 0 {
+                #[allow(clippy::cast_possible_truncation)]
+                label_map.put_pixel(x as u32, y as u32, Luma([*class_index]));
+            }
+        }
+    }
+    Ok(label_map)
+}
+
+/// Decode the segmentation of an annotation into a mask with the size of its image.
+fn ann_mask(ann: &Annotation, img: &Image) -> Result<Mask, MaskError> {
+    let mask = match &ann.segmentation {
+        Segmentation::PolygonsRS(poly) => Mask::from(&rle_from_poly(poly)),
+        Segmentation::Polygons(polygons) => Mask::from(&rle_from_poly(&PolygonsRS {
+            size: vec![img.height, img.width],
+            counts: polygons.clone(),
+        })),
+        segmentation => Mask::try_from(segmentation)?,
+    };
+    let (height, width) = mask.dim();
+    #[allow(clippy::cast_possible_truncation)]
+    let size = vec![height as u32, width as u32];
+    if size != [img.height, img.width] {
+        return Err(MaskError::SizeMismatch(size, vec![img.height, img.width]));
+    }
+    Ok(mask)
+}
+
+/// Import semantic segmentation label maps as a COCO dataset.
+///
+/// Each connected region of pixels with the same category index (8-connectivity) becomes an annotation with a RLE segmentation.
+/// Touching objects of the same category therefore end up in a single annotation.
+///
+/// The categories are the class names, given the ids 1 to n. The images and annotations are given the ids 1 to n.\
+/// The label map of an image is expected at the same relative path as the image in the image folder, with a `.png` extension.
+/// Images without label map have no annotation.
+///
+/// ## Args
+/// - `image_folder`: The folder with the images.
+/// - `label_maps_folder`: The folder with the label maps, with the same structure as the image folder.
+/// - `class_names`: The name of each category index.
+/// - `ignore_value`: The value of the pixels without any category.
+///
+/// # Errors
+///
+/// Will return `Err` if an image or label map cannot be read, if a label map is not an 8-bit grayscale image of the size of its image,
+/// or if it has a value which is neither a category index nor the ignore value.
+pub fn import<P: AsRef<Path>>(
+    image_folder: P,
+    label_maps_folder: P,
+    class_names: &[String],
+    ignore_value: u8,
+) -> Result<HashmapDataset, ConversionError> {
+    let image_folder = image_folder.as_ref();
+    let label_maps_folder = label_maps_folder.as_ref();
+
+    let mut dataset = Dataset {
+        categories: categories_from_names(class_names.iter().map(String::as_str)),
+        ..Default::default()
+    };
+
+    for (img_id, relative_path) in (1..).zip(list_files(image_folder, &IMAGE_EXTENSIONS)?) {
+        let img_path = image_folder.join(&relative_path);
+        let (width, height) = image::image_dimensions(&img_path)
+            .map_err(|err| ConversionError::ImageSize(err, img_path.clone()))?;
+        let img = Image {
+            id: img_id,
+            width,
+            height,
+            file_name: relative_path.to_string_lossy().into_owned(),
+            ..Default::default()
+        };
+
+        let label_map_path = label_maps_folder.join(&relative_path).with_extension("png");
+        if label_map_path.is_file() {
+            let label_map = image::open(&label_map_path)
+                .map_err(|err| ConversionError::Image(err, label_map_path.clone()))?;
+            if label_map.color() != ColorType::L8 {
+                return Err(ConversionError::InvalidContent(
+                    label_map_path,
+                    "the label map is not an 8-bit grayscale image.".to_string(),
+                ));
+            }
+            let label_map = label_map.into_luma8();
+            if label_map.dimensions() != (width, height) {
+                return Err(ConversionError::InvalidContent(
+                    label_map_path,
+                    format!(
+                        "the label map does not have the size of the image ({width}x{height})."
+                    ),
+                ));
+            }
+            for (class_index, rle) in instances_from_label_map(&label_map, ignore_value) {
+                if usize::from(class_index) >= class_names.len() {
+                    return Err(ConversionError::InvalidContent(
+                        label_map_path,
+                        format!(
+                            "the value {class_index} is not a category index (there are {} categories).",
+                            class_names.len()
+                        ),
+                    ));
+                }
+                dataset.annotations.push(Annotation {
+                    id: dataset.annotations.len() as u64 + 1,
+                    image_id: img.id,
+                    category_id: u32::from(class_index) + 1,
+                    area: f64::from(rle.area()),
+                    bbox: Bbox::from(&rle),
+                    segmentation: Segmentation::Rle(rle),
+                    iscrowd: 0,
+                    score: None,
+                    keypoints: None,
+                    num_keypoints: None,
+                    extra: ExtraFields::default(),
+                });
+            }
+        }
+        dataset.images.push(img);
+    }
+
+    Ok(HashmapDataset::from_dataset(dataset, image_folder)?)
+}
+
+/// Split a label map into its connected regions (8-connectivity), returning the value of each region along with its RLE mask.
+///
+/// The regions are sorted by the position of their first pixel in row-major order. The pixels with the ignore value are skipped.
+#[must_use]
+pub fn instances_from_label_map(label_map: &GrayImage, ignore_value: u8) -> Vec<(u8, Rle)> {
+    let components = connected_components(label_map, Connectivity::Eight, Luma([ignore_value]));
+    let mut values = HashMap::new();
+    let segment_ids = Array2::from_shape_fn(
+        (label_map.height() as usize, label_map.width() as usize),
+        #[allow(clippy::cast_possible_truncation)]
+        |(y, x)| {
+            let component = components.get_pixel(x as u32, y as u32)[0];
+            if component != 0 {
+                values
+                    .entry(component)
+                    .or_insert(label_map.get_pixel(x as u32, y as u32)[0]);
+            }
+            component
+        },
+    );
+    let mut instances: Vec<_> = rles_from_segment_ids(&segment_ids).into_iter().collect();
+    instances.sort_unstable_by_key(|(component, _)| *component);
+    instances
+        .into_iter()
+        .map(|(component, rle)| (values[&component], rle))
+        .collect()
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::coco::fixtures::cat;
+    use crate::coco::object_detection::Polygons;
+
+    fn dataset() -> HashmapDataset {
+        let annotation = |id: u64, category_id: u32, counts: Polygons| {
+            let segmentation = PolygonsRS {
+                size: vec![10, 12],
+                counts,
+            };
+            Annotation {
+                id,
+                image_id: 1,
+                category_id,
+                area: 0.0,
+                bbox: Bbox::from(&segmentation.counts),
+                segmentation: Segmentation::PolygonsRS(segmentation),
+                iscrowd: 0,
+                score: None,
+                keypoints: None,
+                num_keypoints: None,
+                extra: ExtraFields::default(),
+            }
+        };
+        let dataset = Dataset {
+            images: vec![Image {
+                id: 1,
+                width: 12,
+                height: 10,
+                file_name: "images/a.jpg".to_string(),
+                ..Default::default()
+            }],
+            annotations: vec![
+                // A large box, with a small box inside it.
+                annotation(1, 7, vec![vec![0.0, 0.0, 8.0, 0.0, 8.0, 8.0, 0.0, 8.0]]),
+                annotation(2, 3, vec![vec![2.0, 2.0, 4.0, 2.0, 4.0, 4.0, 2.0, 4.0]]),
+                // Two separate boxes of the same annotation.
+                annotation(
+                    3,
+                    3,
+                    vec![
+                        vec![9.0, 0.0, 11.0, 0.0, 11.0, 2.0, 9.0, 2.0],
+                        vec![9.0, 6.0, 11.0, 6.0, 11.0, 8.0, 9.0, 8.0],
+                    ],
+                ),
+            ],
+            categories: vec![cat(3, "cat"), cat(7, "sofa")],
+            ..Default::default()
+        };
+        HashmapDataset::from_dataset(dataset, "N/A").unwrap()
+    }
+
+    #[test]
+    fn label_map_overlap() {
+        let dataset = dataset();
+        let img = dataset.get_img(1).unwrap();
+
+        let last = label_map(&dataset, img, 255, Overlap::Last).unwrap();
+        assert_eq!(last.get_pixel(0, 0)[0], 1);
+        assert_eq!(last.get_pixel(3, 3)[0], 0);
+        assert_eq!(last.get_pixel(10, 1)[0], 0);
+        assert_eq!(last.get_pixel(10, 9)[0], 255);
+
+        let first = label_map(&dataset, img, 255, Overlap::First).unwrap();
+        assert_eq!(first.get_pixel(3, 3)[0], 1);
+
+        let smallest = label_map(&dataset, img, 255, Overlap::Smallest).unwrap();
+        assert_eq!(smallest.get_pixel(3, 3)[0], 0);
+    }
+
+    #[test]
+    fn ignore_value_conflict() {
+        let dataset = dataset();
+        let img = dataset.get_img(1).unwrap();
+        assert!(matches!(
+            label_map(&dataset, img, 1, Overlap::Last),
+            Err(ConversionError::IgnoreValueConflict(2, 1))
+        ));
+    }
+
+    #[test]
+    fn connected_instances() {
+        let mut label_map = GrayImage::from_pixel(4, 3, Luma([255]));
+        for (x, y, value) in [(0, 0, 1), (1, 1, 1), (3, 0, 1), (3, 2, 0)] {
+            label_map.put_pixel(x, y, Luma([value]));
+        }
+        let instances = instances_from_label_map(&label_map, 255);
+        assert_eq!(
+            instances,
+            vec![
+                // The two diagonal pixels are connected.
+                (
+                    1,
+                    Rle {
+                        size: vec![3, 4],
+                        counts: vec![0, 1, 3, 1, 7]
+                    }
+                ),
+                (
+                    1,
+                    Rle {
+                        size: vec![3, 4],
+                        counts: vec![9, 1, 2]
+                    }
+                ),
+                (
+                    0,
+                    Rle {
+                        size: vec![3, 4],
+                        counts: vec![11, 1]
+                    }
+                ),
+            ]
+        );
+        let bboxes: Vec<Bbox> = instances.iter().map(|(_, rle)| Bbox::from(rle)).collect();
+        assert_eq!(
+            bboxes,
+            vec![
+                Bbox {
+                    left: 0.0,
+                    top: 0.0,
+                    width: 2.0,
+                    height: 2.0
+                },
+                Bbox {
+                    left: 3.0,
+                    top: 0.0,
+                    width: 1.0,
+                    height: 1.0
+                },
+                Bbox {
+                    left: 3.0,
+                    top: 2.0,
+                    width: 1.0,
+                    height: 1.0
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn export_import_roundtrip() {
+        let dataset = dataset();
+        let folder = tempfile::tempdir().unwrap();
+        let image_folder = folder.path().join("images");
+        let label_maps_folder = folder.path().join("label_maps");
+        fs::create_dir_all(image_folder.join("images")).unwrap();
+        GrayImage::new(12, 10)
+            .save(image_folder.join("images/a.png"))
+            .unwrap();
+
+        export(&dataset, &label_maps_folder, 255, Overlap::Smallest).unwrap();
+        assert_eq!(
+            fs::read_to_string(label_maps_folder.join("labels.txt")).unwrap(),
+            "cat\nsofa\n"
+        );
+        let class_names = vec!["cat".to_string(), "sofa".to_string()];
+        let imported = import(&image_folder, &label_maps_folder, &class_names, 255).unwrap();
+
+        let img = imported.get_img(1).unwrap();
+        assert_eq!(img.file_name, "images/a.png");
+        let anns = imported.get_img_anns(1).unwrap();
+        let summary: Vec<(u32, f64)> = anns.iter().map(|ann| (ann.category_id, ann.area)).collect();
+        assert_eq!(summary, vec![(2, 60.0), (1, 4.0), (1, 4.0), (1, 4.0)]);
+        assert_eq!(
+            label_map(&imported, img, 255, Overlap::Last).unwrap(),
+            label_map(
+                &dataset,
+                dataset.get_img(1).unwrap(),
+                255,
+                Overlap::Smallest
+            )
+            .unwrap()
+        );
+    }
+}
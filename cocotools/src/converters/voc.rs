@@ -0,0 +1,539 @@
+//! Module to convert a COCO dataset to and from the [Pascal VOC format](http://host.robots.ox.ac.uk/pascal/VOC/).
+//!
+//! A Pascal VOC dataset has the following structure:
+//! - `Annotations/<image id>.xml`: the size of the image and its objects, with their class name and bounding box.
+//! - `JPEGImages/<image file>`: the images.
+//! - `ImageSets/Main/<split>.txt`: the ids of the images of each split (`train`, `val`, etc.), one per line.
+//!
+//! VOC bounding boxes are given by the 1-based indices of their first and last pixels (`xmin`, `ymin`, `xmax`, `ymax`, all included in the box),
+//! while COCO bounding boxes use continuous coordinates starting at 0. Therefore `left = xmin - 1` and `width = xmax - xmin + 1`.
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use roxmltree::{Document, Node};
+
+use crate::coco::object_detection::{
+    Annotation, Bbox, Dataset, ExtraFields, HashmapDataset, Image, PolygonsRS, Segmentation,
+};
+use crate::converters::xml::{child, child_text, escape_xml, parse_number};
+use crate::converters::{
+    bbox_polygon, categories_from_names, category_id, copy_image, create_parent, list_files,
+};
+use crate::errors::ConversionError;
+
+/// Fields of the VOC objects kept in the [`Annotation::extra`] fields.
+const OBJECT_FLAGS: [&str; 3] = ["truncated", "difficult", "occluded"];
+
+/// Object of a VOC annotation file.
+struct VocObject {
+    name: String,
+    bbox: Bbox,
+    extra: ExtraFields,
+}
+
+/// Import a Pascal VOC dataset into a COCO dataset.
+///
+/// The categories are the class names found in the annotation files, sorted alphabetically (which gives the standard order for the VOC classes)
+/// and given the ids 1 to n. The images and annotations are given the ids 1 to n.
+///
+/// The `truncated`, `difficult` and `occluded` flags and the `pose` of the objects are kept in the [`Annotation::extra`] fields,
+/// and are written back when exporting to VOC. The segmentation of the annotations is the rectangle of their bounding box.\
+/// If an annotation file does not give the size of its image, it is read from the image file.
+///
+/// ## Args
+/// - `voc_folder`: The root folder of the dataset, with the `Annotations` and `JPEGImages` folders.
+/// - split: If given, only load the images listed in `ImageSets/Main/<split>.txt`, otherwise load all the annotation files.
+///
+/// # Errors
+///
+/// Will return `Err` if a file cannot be read, or if an annotation file is not valid xml or is missing a required element.
+pub fn import<P: AsRef<Path>>(
+    voc_folder: P,
+    split: Option<&str>,
+) -> Result<HashmapDataset, ConversionError> {
+    let voc_folder = voc_folder.as_ref();
+    let annotations_folder = voc_folder.join("Annotations");
+    let image_folder = voc_folder.join("JPEGImages");
+
+    let xml_paths: Vec<PathBuf> = match split {
+        Some(split) => read_image_set(voc_folder, split)?
+            .into_iter()
+            .map(|id| annotations_folder.join(format!("{id}.xml")))
+            .collect(),
+        None => list_files(&annotations_folder, &["xml"])?
+            .into_iter()
+            .map(|path| annotations_folder.join(path))
+            .collect(),
+    };
+
+    let mut images = Vec::with_capacity(xml_paths.len());
+    for (img_id, xml_path) in (1..).zip(&xml_paths) {
+        images.push(parse_annotation_file(xml_path, img_id, &image_folder)?);
+    }
+
+    let names: BTreeSet<&str> = images
+        .iter()
+        .flat_map(|(_, objects)| objects.iter().map(|object| object.name.as_str()))
+        .collect();
+    let categories = categories_from_names(names);
+
+    let mut annotations = Vec::new();
+    for (img, objects) in &images {
+        for object in objects {
+            let category_id = category_id(&categories, &object.name);
+            annotations.push(Annotation {
+                id: annotations.len() as u64 + 1,
+                image_id: img.id,
+                category_id,
+                segmentation: Segmentation::PolygonsRS(PolygonsRS {
+                    size: vec![img.height, img.width],
+                    counts: vec![bbox_polygon(&object.bbox)],
+                }),
+                area: object.bbox.width * object.bbox.height,
+                bbox: object.bbox.clone(),
+                iscrowd: 0,
+                score: None,
+                keypoints: None,
+                num_keypoints: None,
+                extra: object.extra.clone(),
+            });
+        }
+    }
+
+    let dataset = Dataset {
+        images: images.into_iter().map(|(img, _)| img).collect(),
+        annotations,
+        categories,
+        ..Default::default()
+    };
+    Ok(HashmapDataset::from_dataset(dataset, image_folder)?)
+}
+
+/// Export a COCO dataset to the Pascal VOC format.
+///
+/// One annotation file is written per image in the `Annotations` folder, named after the image file (without its extension),
+/// and the list of these names is written to `ImageSets/Main/<split>.txt`.\
+/// VOC datasets do not have subfolders, the folders of an image file name are therefore joined to it with underscores
+/// (`train/a.jpg` becomes `train_a.jpg`, with the id `train_a`), both for the annotation files and the copied images.\
+/// The bounding boxes are rounded to integer pixel indices. The `difficult` flag is taken from the [`Annotation::extra`] fields if present,
+/// and otherwise from `iscrowd`, since VOC evaluations ignore difficult objects like COCO ignores crowd annotations.
+///
+/// ## Args
+/// - dataset: The COCO dataset to export.
+/// - `output_folder`: The root folder of the VOC dataset.
+/// - split: The name of the image set file, for example "train".
+/// - `copy_images`: If true, copy the images from the dataset's image folder to the `JPEGImages` folder.
+///
+/// # Errors
+///
+/// Will return `Err` if two images have the same VOC id (for example `a.jpg` and `a.png`), or if a file cannot be written or an image cannot be copied.
+pub fn export<P: AsRef<Path>>(
+    dataset: &HashmapDataset,
+    output_folder: P,
+    split: &str,
+    copy_images: bool,
+) -> Result<(), ConversionError> {
+    let output_folder = output_folder.as_ref();
+    // Check the ids before writing anything, two images with the same id would overwrite each other's annotations.
+    let mut ids: HashMap<String, &str> = HashMap::new();
+    for img in dataset.get_imgs() {
+        if let Some(other) = ids.insert(voc_id(&img.file_name), &img.file_name) {
+            return Err(ConversionError::DuplicateVocId(
+                other.to_string(),
+                img.file_name.clone(),
+                voc_id(&img.file_name),
+            ));
+        }
+    }
+
+    let mut image_set = String::new();
+    for img in dataset.get_imgs() {
+        let file_name = flat_file_name(&img.file_name);
+        let id = voc_id(&img.file_name);
+        let xml_path = output_folder.join("Annotations").join(format!("{id}.xml"));
+        create_parent(&xml_path)?;
+        let anns = dataset.get_img_anns(img.id).unwrap_or_default();
+        fs::write(&xml_path, annotation_xml(dataset, img, &file_name, &anns))
+            .map_err(|err| ConversionError::Write(err, xml_path))?;
+
+        if copy_images {
+            copy_image(
+                dataset,
+                img,
+                &output_folder.join("JPEGImages").join(&file_name),
+            )?;
+        }
+        image_set.push_str(&format!("{id}\n"));
+    }
+
+    let image_set_path = output_folder
+        .join("ImageSets")
+        .join("Main")
+        .join(format!("{split}.txt"));
+    create_parent(&image_set_path)?;
+    fs::write(&image_set_path, image_set).map_err(|err| ConversionError::Write(err, image_set_path))
+}
+
+/// Name of an image in the flat `JPEGImages` folder, its folders being joined to its name with underscores.
+fn flat_file_name(file_name: &str) -> String {
+    file_name.replace(['/', '\\'], "_")
+}
+
+/// VOC id of an image, its flat file name without the extension.
+fn voc_id(file_name: &str) -> String {
+    Path::new(&flat_file_name(file_name))
+        .with_extension("")
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Read the ids of the images of a split.
+///
+/// The class specific image sets (`<class>_<split>.txt`) have a second column with a flag, it is ignored.
+fn read_image_set(voc_folder: &Path, split: &str) -> Result<Vec<String>, ConversionError> {
+    let path = voc_folder
+        .join("ImageSets")
+        .join("Main")
+        .join(format!("{split}.txt"));
+    let content = fs::read_to_string(&path).map_err(|err| ConversionError::Read(err, path))?;
+    Ok(content
+        .lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .map(String::from)
+        .collect())
+}
+
+/// Parse a VOC annotation file into an image entry and its objects.
+fn parse_annotation_file(
+    xml_path: &Path,
+    img_id: u64,
+    image_folder: &Path,
+) -> Result<(Image, Vec<VocObject>), ConversionError> {
+    let content = fs::read_to_string(xml_path)
+        .map_err(|err| ConversionError::Read(err, xml_path.to_path_buf()))?;
+    let document = Document::parse(&content)
+        .map_err(|err| ConversionError::Xml(err, xml_path.to_path_buf()))?;
+    let invalid = |reason: String| ConversionError::InvalidContent(xml_path.to_path_buf(), reason);
+    let root = document.root_element();
+
+    let file_name = match child_text(root, "filename") {
+        Some(file_name) => file_name.to_string(),
+        None => format!(
+            "{}.jpg",
+            xml_path.file_stem().unwrap_or_default().to_string_lossy()
+        ),
+    };
+    let size = child(root, "size");
+    let width = size
+        .and_then(|size| child_text(size, "width"))
+        .unwrap_or("0");
+    let height = size
+        .and_then(|size| child_text(size, "height"))
+        .unwrap_or("0");
+    let (mut width, mut height) = (
+        parse_number::<u32>(width, "width").map_err(invalid)?,
+        parse_number::<u32>(height, "height").map_err(invalid)?,
+    );
+    if width == 0 || height == 0 {
+        let img_path = image_folder.join(&file_name);
+        (width, height) = image::image_dimensions(&img_path)
+            .map_err(|err| ConversionError::ImageSize(err, img_path))?;
+    }
+
+    let mut objects = Vec::new();
+    for object in root.children().filter(|node| node.has_tag_name("object")) {
+        objects.push(parse_object(object).map_err(invalid)?);
+    }
+
+    let img = Image {
+        id: img_id,
+        width,
+        height,
+        file_name,
+        ..Default::default()
+    };
+    Ok((img, objects))
+}
+
+fn parse_object(object: Node) -> Result<VocObject, String> {
+    let name = child_text(object, "name")
+        .ok_or_else(|| "an object does not have a name.".to_string())?
+        .to_string();
+    let bndbox = child(object, "bndbox")
+        .ok_or_else(|| format!("the object `{name}` does not have a bndbox."))?;
+    let coordinate = |tag: &str| -> Result<f64, String> {
+        let text = child_text(bndbox, tag)
+            .ok_or_else(|| format!("the bndbox of the object `{name}` does not have a {tag}."))?;
+        parse_number(text, tag)
+    };
+    let (xmin, ymin, xmax, ymax) = (
+        coordinate("xmin")?,
+        coordinate("ymin")?,
+        coordinate("xmax")?,
+        coordinate("ymax")?,
+    );
+
+    let mut extra = ExtraFields::default();
+    for flag in OBJECT_FLAGS {
+        if let Some(value) = child_text(object, flag) {
+            extra.insert(flag.to_string(), parse_number::<u32>(value, flag)?.into());
+        }
+    }
+    if let Some(pose) = child_text(object, "pose") {
+        extra.insert("pose".to_string(), pose.into());
+    }
+
+    Ok(VocObject {
+        name,
+        bbox: Bbox {
+            left: xmin - 1.0,
+            top: ymin - 1.0,
+            width: xmax - xmin + 1.0,
+            height: ymax - ymin + 1.0,
+        },
+        extra,
+    })
+}
+
+/// Content of the VOC annotation file of an image.
+#[allow(clippy::cast_possible_truncation)]
+fn annotation_xml(
+    dataset: &HashmapDataset,
+    img: &Image,
+    file_name: &str,
+    anns: &[&Annotation],
+) -> String {
+    let mut xml = String::from("<annotation>\n\t<folder>JPEGImages</folder>\n");
+    xml.push_str(&format!(
+        "\t<filename>{}</filename>\n",
+        escape_xml(file_name)
+    ));
+    xml.push_str(&format!(
+        "\t<size>\n\t\t<width>{}</width>\n\t\t<height>{}</height>\n\t\t<depth>3</depth>\n\t</size>\n\t<segmented>0</segmented>\n",
+        img.width, img.height
+    ));
+    for ann in anns {
+        // Annotations with a category not in the dataset cannot be given a class name.
+        let cat = match dataset.get_cat(ann.category_id) {
+            Ok(cat) => cat,
+            Err(_) => continue,
+        };
+        let pose = ann
+            .extra
+            .get("pose")
+            .and_then(serde_json::Value::as_str)
+            .unwrap_or("Unspecified");
+        xml.push_str(&format!(
+            "\t<object>\n\t\t<name>{}</name>\n\t\t<pose>{}</pose>\n",
+            escape_xml(&cat.name),
+            escape_xml(pose)
+        ));
+        for flag in OBJECT_FLAGS {
+            let value = ann.extra.get(flag).and_then(flag_value);
+            let value = match (flag, value) {
+                (_, Some(value)) => value,
+                ("difficult", None) => u64::from(ann.iscrowd != 0),
+                ("truncated", None) => 0,
+                // The occluded flag is not part of the original VOC format, it is only written back if it was present.
+                _ => continue,
+            };
+            xml.push_str(&format!("\t\t<{flag}>{value}</{flag}>\n"));
+        }
+        let bbox = &ann.bbox;
+        xml.push_str(&format!(
+            "\t\t<bndbox>\n\t\t\t<xmin>{}</xmin>\n\t\t\t<ymin>{}</ymin>\n\t\t\t<xmax>{}</xmax>\n\t\t\t<ymax>{}</ymax>\n\t\t</bndbox>\n\t</object>\n",
+            bbox.left.round() as i64 + 1,
+            bbox.top.round() as i64 + 1,
+            (bbox.left + bbox.width).round() as i64,
+            (bbox.top + bbox.height).round() as i64,
+        ));
+    }
+    xml.push_str("</annotation>\n");
+    xml
+}
+
+/// Value of a VOC flag stored in the extra fields, either as a number or as a boolean.
+fn flag_value(value: &serde_json::Value) -> Option<u64> {
+    value.as_u64().or_else(|| value.as_bool().map(u64::from))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::float_cmp)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    const VOC_XML: &str = "<annotation>
+	<folder>VOC2007</folder>
+	<filename>000001.jpg</filename>
+	<size><width>353</width><height>500</height><depth>3</depth></size>
+	<object>
+		<name>dog</name>
+		<pose>Left</pose>
+		<truncated>1</truncated>
+		<difficult>0</difficult>
+		<bndbox><xmin>48</xmin><ymin>240</ymin><xmax>195</xmax><ymax>371</ymax></bndbox>
+	</object>
+	<object>
+		<name>person</name>
+		<difficult>1</difficult>
+		<bndbox><xmin>8</xmin><ymin>12.5</ymin><xmax>352</xmax><ymax>498</ymax></bndbox>
+	</object>
+</annotation>";
+
+    fn voc_dataset(files: &[(&str, &str)]) -> tempfile::TempDir {
+        let folder = tempfile::tempdir().unwrap();
+        for (path, content) in files {
+            let path = folder.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        folder
+    }
+
+    #[test]
+    fn import_annotations() {
+        let folder = voc_dataset(&[
+            ("Annotations/000001.xml", VOC_XML),
+            (
+                "Annotations/000002.xml",
+                "<annotation><filename>000002.jpg</filename><size><width>10</width><height>20</height></size></annotation>",
+            ),
+        ]);
+        let dataset = import(folder.path(), None).unwrap();
+
+        let cats: Vec<(u32, &str)> = dataset
+            .get_cats()
+            .iter()
+            .map(|cat| (cat.id, cat.name.as_str()))
+            .collect();
+        assert_eq!(cats, vec![(1, "dog"), (2, "person")]);
+        assert_eq!(dataset.get_imgs().len(), 2);
+        assert_eq!(dataset.get_img(1).unwrap().width, 353);
+
+        let anns = dataset.get_img_anns(1).unwrap();
+        assert_eq!(
+            anns[0].bbox,
+            Bbox {
+                left: 47.0,
+                top: 239.0,
+                width: 148.0,
+                height: 132.0
+            }
+        );
+        assert_eq!(anns[0].area, 148.0 * 132.0);
+        assert_eq!(anns[0].extra["truncated"], 1);
+        assert_eq!(anns[0].extra["difficult"], 0);
+        assert_eq!(anns[0].extra["pose"], "Left");
+        assert_eq!(anns[1].category_id, 2);
+        assert_eq!(anns[1].bbox.top, 11.5);
+        assert_eq!(anns[1].extra["difficult"], 1);
+        assert!(!anns[1].extra.contains_key("pose"));
+    }
+
+    #[test]
+    fn import_split() {
+        let folder = voc_dataset(&[
+            ("Annotations/000001.xml", VOC_XML),
+            (
+                "Annotations/000002.xml",
+                "<annotation><size><width>10</width><height>20</height></size></annotation>",
+            ),
+            ("ImageSets/Main/val.txt", "000002\n"),
+        ]);
+        let dataset = import(folder.path(), Some("val")).unwrap();
+        let imgs = dataset.get_imgs();
+        assert_eq!(imgs.len(), 1);
+        assert_eq!(imgs[0].file_name, "000002.jpg");
+        assert!(dataset.get_cats().is_empty());
+    }
+
+    #[test]
+    fn import_invalid() {
+        let folder = voc_dataset(&[(
+            "Annotations/000001.xml",
+            "<annotation><size><width>10</width><height>20</height></size><object><name>dog</name></object></annotation>",
+        )]);
+        match import(folder.path(), None) {
+            Err(ConversionError::InvalidContent(path, reason)) => {
+                assert_eq!(path, folder.path().join("Annotations/000001.xml"));
+                assert_eq!(reason, "the object `dog` does not have a bndbox.");
+            }
+            _ => panic!("Expected an invalid content error."),
+        }
+    }
+
+    #[test]
+    fn export_import_roundtrip() {
+        let folder = voc_dataset(&[("Annotations/000001.xml", VOC_XML)]);
+        let mut dataset = import(folder.path(), None).unwrap();
+        // Crowd annotations are exported as difficult objects.
+        let mut crowd = dataset.get_ann(1).unwrap().clone();
+        crowd.id = 3;
+        crowd.iscrowd = 1;
+        crowd.extra = ExtraFields::default();
+        let mut coco_dataset = Dataset::from(&dataset);
+        coco_dataset.annotations.push(crowd);
+        dataset = HashmapDataset::from_dataset(coco_dataset, "N/A").unwrap();
+
+        let output_folder = tempfile::tempdir().unwrap();
+        export(&dataset, output_folder.path(), "train", false).unwrap();
+        assert_eq!(
+            fs::read_to_string(output_folder.path().join("ImageSets/Main/train.txt")).unwrap(),
+            "000001\n"
+        );
+
+        let exported = import(output_folder.path(), Some("train")).unwrap();
+        let anns = exported.get_img_anns(1).unwrap();
+        assert_eq!(anns.len(), 3);
+        assert_eq!(anns[0], dataset.get_ann(1).unwrap());
+        assert_eq!(anns[1].bbox.top, 12.0);
+        assert_eq!(anns[1].extra["truncated"], 0);
+        assert_eq!(anns[1].extra["pose"], "Unspecified");
+        assert_eq!(anns[2].extra["difficult"], 1);
+    }
+
+    fn images_dataset(file_names: &[&str]) -> HashmapDataset {
+        let dataset = Dataset {
+            images: (1..)
+                .zip(file_names)
+                .map(|(id, file_name)| Image {
+                    id,
+                    width: 10,
+                    height: 10,
+                    file_name: (*file_name).to_string(),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        };
+        HashmapDataset::from_dataset(dataset, "N/A").unwrap()
+    }
+
+    #[test]
+    fn export_subfolders() {
+        let dataset = images_dataset(&["train/a.jpg", "b.jpg"]);
+        let output_folder = tempfile::tempdir().unwrap();
+        export(&dataset, output_folder.path(), "train", false).unwrap();
+        assert_eq!(
+            fs::read_to_string(output_folder.path().join("ImageSets/Main/train.txt")).unwrap(),
+            "train_a\nb\n"
+        );
+        let xml = fs::read_to_string(output_folder.path().join("Annotations/train_a.xml")).unwrap();
+        assert!(xml.contains("<filename>train_a.jpg</filename>"));
+    }
+
+    #[rstest]
+    #[case::extension(&["a.jpg", "a.png"])]
+    #[case::flattened(&["train/a.jpg", "train_a.jpg"])]
+    fn export_duplicate_ids(#[case] file_names: &[&str]) {
+        let dataset = images_dataset(file_names);
+        let output_folder = tempfile::tempdir().unwrap();
+        assert!(matches!(
+            export(&dataset, output_folder.path(), "train", false),
+            Err(ConversionError::DuplicateVocId(..))
+        ));
+        assert!(!output_folder.path().join("Annotations").exists());
+    }
+}
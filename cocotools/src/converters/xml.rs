@@ -0,0 +1,29 @@
+//! Helpers to read and write the xml annotation files of the CVAT and Pascal VOC formats.
+use roxmltree::Node;
+
+/// Escape the characters that cannot be used as-is in xml text and attribute values.
+pub(crate) fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// First child element of the node with the given tag.
+pub(crate) fn child<'a, 'input>(node: Node<'a, 'input>, tag: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|child| child.has_tag_name(tag))
+}
+
+/// Trimmed text of the first child element of the node with the given tag.
+pub(crate) fn child_text<'a>(node: Node<'a, '_>, tag: &str) -> Option<&'a str> {
+    child(node, tag)
+        .and_then(|child| child.text())
+        .map(str::trim)
+}
+
+/// Parse a number, returning the reason why it is invalid on failure.
+pub(crate) fn parse_number<T: std::str::FromStr>(text: &str, name: &str) -> Result<T, String> {
+    text.parse()
+        .map_err(|_| format!("the {name} `{text}` is not a valid number."))
+}
@@ -0,0 +1,639 @@
+//! Module to convert a COCO dataset to and from the [YOLO (Ultralytics) format](https://docs.ultralytics.com/datasets/).
+//!
+//! A YOLO dataset has one text file per image, with one line per object: `class_index x_center y_center width height` for detection,
+//! or `class_index x1 y1 x2 y2 ...` for segmentation. All the coordinates are normalized by the size of the image.\
+//! The class names are given in a `data.yaml` file, and the class indices go from 0 to the number of classes - 1.
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+use clap::ValueEnum;
+use serde::Deserialize;
+
+use crate::coco::object_detection::{
+    Annotation, Bbox, Dataset, ExtraFields, HashmapDataset, Image, Polygons, PolygonsRS,
+    Segmentation,
+};
+use crate::converters::{
+    ann_polygons, bbox_polygon, categories_from_names, copy_image, create_parent, list_files,
+    IMAGE_EXTENSIONS,
+};
+use crate::errors::ConversionError;
+use crate::mask::conversions::rle_from_poly;
+use crate::mask::utils::Area;
+
+/// Type of labels of the YOLO dataset, named after the Ultralytics tasks.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum YoloTask {
+    /// Bounding boxes.
+    Detect,
+    /// Polygons.
+    Segment,
+}
+
+/// Export a COCO dataset to the YOLO format.
+///
+/// The following files are created in the output folder:
+/// - `data.yaml`, with the class names.
+/// - `labels/<image name>.txt`, with the labels of each image. Images without annotations get an empty file.
+/// - `images/<image name>`, a copy of each image if `copy_images` is true. Ultralytics expects the images in this folder.
+///
+/// The categories are mapped to the class indices 0 to n-1 by increasing category id, see [`class_indices`].\
+/// Crowd annotations (`iscrowd=1`) are skipped, since YOLO has no way to mark them as ignored.
+///
+/// For segmentation, RLE masks are converted to polygons, and annotations without a segmentation use their bounding box.
+/// YOLO only supports one polygon per object, annotations made of several polygons therefore have them joined into a single one.
+///
+/// ## Args
+/// - dataset: The COCO dataset to export.
+/// - `output_folder`: The folder where the YOLO dataset will be written.
+/// - task: Whether to export the bounding boxes or the segmentation masks.
+/// - `copy_images`: If true, copy the images from the dataset's image folder to the output folder.
+///
+/// # Errors
+///
+/// Will return `Err` if a file cannot be written or an image cannot be copied.
+pub fn export<P: AsRef<Path>>(
+    dataset: &HashmapDataset,
+    output_folder: P,
+    task: YoloTask,
+    copy_images: bool,
+) -> Result<(), ConversionError> {
+    let output_folder = output_folder.as_ref();
+    let labels_folder = output_folder.join("labels");
+    let images_folder = output_folder.join("images");
+    let class_indices = class_indices(dataset);
+
+    let mut cats = dataset.get_cats();
+    cats.sort_by_key(|cat| cat.id);
+    let names: Vec<&str> = cats.iter().map(|cat| cat.name.as_str()).collect();
+    let data_yaml_path = output_folder.join("data.yaml");
+    create_parent(&data_yaml_path)?;
+    fs::write(&data_yaml_path, data_yaml(&names))
+        .map_err(|err| ConversionError::Write(err, data_yaml_path))?;
+
+    for img in dataset.get_imgs() {
+        let mut lines = Vec::new();
+        for ann in dataset.get_img_anns(img.id).unwrap_or_default() {
+            if ann.iscrowd != 0 {
+                continue;
+            }
+            // Annotations with a category not in the dataset cannot be given a class index.
+            if let Some(class_index) = class_indices.get(&ann.category_id) {
+                lines.push(label_line(ann, img, *class_index, task));
+            }
+        }
+
+        let label_path = labels_folder.join(&img.file_name).with_extension("txt");
+        create_parent(&label_path)?;
+        let content = lines
+            .iter()
+            .map(|line| format!("{line}\n"))
+            .collect::<String>();
+        fs::write(&label_path, content).map_err(|err| ConversionError::Write(err, label_path))?;
+
+        if copy_images {
+            copy_image(dataset, img, &images_folder.join(&img.file_name))?;
+        }
+    }
+    Ok(())
+}
+
+/// Map each category id to a YOLO class index, from 0 to the number of categories - 1 by increasing category id.
+#[must_use]
+pub fn class_indices(dataset: &HashmapDataset) -> HashMap<u32, usize> {
+    let mut cat_ids: Vec<u32> = dataset.get_cats().iter().map(|cat| cat.id).collect();
+    cat_ids.sort_unstable();
+    cat_ids
+        .into_iter()
+        .enumerate()
+        .map(|(index, cat_id)| (cat_id, index))
+        .collect()
+}
+
+/// Content of the Ultralytics `data.yaml` file, with the images in the `images` folder next to it.
+fn data_yaml(names: &[&str]) -> String {
+    let mut yaml = String::from("path: .\ntrain: images\nval: images\n");
+    yaml.push_str(&format!("nc: {}\nnames:\n", names.len()));
+    for (index, name) in names.iter().enumerate() {
+        // Single quoted yaml strings only need the quotes to be escaped.
+        yaml.push_str(&format!("  {index}: '{}'\n", name.replace('\'', "''")));
+    }
+    yaml
+}
+
+/// Line of the YOLO label file corresponding to the annotation.
+///
+/// The bounding box is clipped to the image before computing its center and size,
+/// so that a box going out of the image keeps the part that is inside it.
+fn label_line(ann: &Annotation, img: &Image, class_index: usize, task: YoloTask) -> String {
+    let width = f64::from(img.width);
+    let height = f64::from(img.height);
+    let coordinates: Vec<f64> = match task {
+        YoloTask::Detect => {
+            let bbox = &ann.bbox;
+            let left = bbox.left.clamp(0.0, width);
+            let top = bbox.top.clamp(0.0, height);
+            let right = (bbox.left + bbox.width).clamp(0.0, width);
+            let bottom = (bbox.top + bbox.height).clamp(0.0, height);
+            vec![
+                (left + right) / 2.0 / width,
+                (top + bottom) / 2.0 / height,
+                (right - left) / width,
+                (bottom - top) / height,
+            ]
+        }
+        YoloTask::Segment => join_polygons(&polygons(ann))
+            .chunks_exact(2)
+            .flat_map(|xy| [xy[0] / width, xy[1] / height])
+            .collect(),
+    };
+
+    let mut line = class_index.to_string();
+    for value in coordinates {
+        line.push_str(&format!(" {:.6}", value.clamp(0.0, 1.0)));
+    }
+    line
+}
+
+/// Valid polygons of the annotation, or the polygon of its bounding box if the annotation has none.
+fn polygons(ann: &Annotation) -> Polygons {
+    let polygons: Polygons = ann_polygons(ann)
+        .into_iter()
+        .filter(|poly| poly.len() >= 6 && poly.len() % 2 == 0)
+        .collect();
+    if polygons.is_empty() {
+        vec![bbox_polygon(&ann.bbox)]
+    } else {
+        polygons
+    }
+}
+
+/// Join several polygons into one, by going back to the first point of each polygon before jumping to the next one.
+///
+/// The segments linking the polygons are traversed once in each direction, they therefore do not add any area to the mask.
+fn join_polygons(polygons: &[Vec<f64>]) -> Vec<f64> {
+    if polygons.len() == 1 {
+        return polygons[0].clone();
+    }
+    let mut joined = Vec::new();
+    for poly in polygons {
+        joined.extend_from_slice(poly);
+        joined.extend_from_slice(&poly[..2]);
+    }
+    joined
+}
+
+/// Import a YOLO dataset into a COCO dataset.
+///
+/// Every image in the image folder (and its subfolders) is added to the dataset, with the label file that has the same
+/// relative path in the labels folder and a `.txt` extension. Images without a label file do not have any annotation.\
+/// Each line of a label file can be:
+/// - `class_index x_center y_center width height`, for a bounding box.
+/// - `class_index x_center y_center width height score`, for a detection with its confidence.
+/// - `class_index x1 y1 x2 y2 x3 y3 ...`, for a polygon with at least 3 points.
+///
+/// The images and annotations are given the ids 1 to n, and the categories the ids 1 to the number of classes (class index + 1).
+/// The segmentation of bounding boxes is their rectangle, and the bounding box of polygons encloses them. The area is the one of the rasterized polygon, as computed by pycocotools.
+///
+/// ## Args
+/// - `image_folder`: The folder with the images, their sizes are read from the files.
+/// - `labels_folder`: The folder with the YOLO label files.
+/// - `class_names`: The names of the classes, indexed by class index (see [`load_class_names`]).
+///
+/// # Errors
+///
+/// Will return `Err` if a folder or a file cannot be read, or if a line of a label file is invalid (the error gives the file and line number).
+pub fn import<P: AsRef<Path>>(
+    image_folder: P,
+    labels_folder: P,
+    class_names: &[String],
+) -> Result<HashmapDataset, ConversionError> {
+    let image_folder = image_folder.as_ref();
+    let labels_folder = labels_folder.as_ref();
+
+    let mut dataset = Dataset {
+        categories: categories_from_names(class_names.iter().map(String::as_str)),
+        ..Default::default()
+    };
+
+    for (img_id, relative_path) in (1..).zip(list_files(image_folder, &IMAGE_EXTENSIONS)?) {
+        let img_path = image_folder.join(&relative_path);
+        let (width, height) = image::image_dimensions(&img_path)
+            .map_err(|err| ConversionError::ImageSize(err, img_path.clone()))?;
+        let img = Image {
+            id: img_id,
+            width,
+            height,
+            file_name: relative_path.to_string_lossy().into_owned(),
+            ..Default::default()
+        };
+
+        let label_path = labels_folder.join(&relative_path).with_extension("txt");
+        if label_path.is_file() {
+            let content = fs::read_to_string(&label_path)
+                .map_err(|err| ConversionError::Read(err, label_path.clone()))?;
+            for (line_idx, line) in content.lines().enumerate() {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let ann_id = dataset.annotations.len() as u64 + 1;
+                let ann = parse_line(line, ann_id, &img, class_names.len()).map_err(|reason| {
+                    ConversionError::InvalidLine(label_path.clone(), line_idx + 1, reason)
+                })?;
+                dataset.annotations.push(ann);
+            }
+        }
+        dataset.images.push(img);
+    }
+
+    Ok(HashmapDataset::from_dataset(dataset, image_folder)?)
+}
+
+/// Load the class names from an Ultralytics `data.yaml` file, or from a text file with one class name per line.
+///
+/// In the yaml file, the names can be given as a list or as a mapping from class index to name, the indices then being `0..n`.
+///
+/// # Errors
+///
+/// Will return `Err` if:
+///   - The file cannot be read or parsed.
+///   - The yaml file does not have a `names` entry.
+///   - The class indices of the mapping are not `0..n` (a missing or extra index would shift the class names).
+pub fn load_class_names<P: AsRef<Path>>(path: P) -> Result<Vec<String>, ConversionError> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Names {
+        List(Vec<String>),
+        Map(BTreeMap<usize, String>),
+    }
+    #[derive(Deserialize)]
+    struct DataYaml {
+        names: Option<Names>,
+    }
+
+    let path = path.as_ref();
+    let content =
+        fs::read_to_string(path).map_err(|err| ConversionError::Read(err, path.to_path_buf()))?;
+    let is_yaml = path
+        .extension()
+        .map_or(false, |ext| ext == "yaml" || ext == "yml");
+    if !is_yaml {
+        return Ok(content
+            .lines()
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(String::from)
+            .collect());
+    }
+
+    let data_yaml: DataYaml = serde_yaml::from_str(&content)
+        .map_err(|err| ConversionError::Yaml(err, path.to_path_buf()))?;
+    match data_yaml.names {
+        Some(Names::List(names)) => Ok(names),
+        Some(Names::Map(names)) => {
+            if let Some((index, _)) = names.keys().enumerate().find(|(i, index)| i != *index) {
+                return Err(ConversionError::InvalidContent(
+                    path.to_path_buf(),
+                    format!(
+                        "the class indices should go from 0 to {}, but the class {index} is missing.",
+                        names.len() - 1
+                    ),
+                ));
+            }
+            Ok(names.into_values().collect())
+        }
+        None => Err(ConversionError::MissingClassNames(path.to_path_buf())),
+    }
+}
+
+/// Parse a line of a label file, returning the reason why the line is invalid on failure.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn parse_line(
+    line: &str,
+    ann_id: u64,
+    img: &Image,
+    nb_classes: usize,
+) -> Result<Annotation, String> {
+    let mut tokens = line.split_whitespace();
+    let class_token = tokens.next().unwrap_or_default();
+    let class_index: usize = class_token
+        .parse()
+        .map_err(|_| format!("the class index `{class_token}` is not a non-negative integer."))?;
+    if class_index >= nb_classes {
+        return Err(format!(
+            "the class index {class_index} is not smaller than the number of classes ({nb_classes})."
+        ));
+    }
+    let values = tokens
+        .map(|token| {
+            token
+                .parse::<f64>()
+                .map_err(|_| format!("`{token}` is not a number."))
+        })
+        .collect::<Result<Vec<f64>, String>>()?;
+
+    let width = f64::from(img.width);
+    let height = f64::from(img.height);
+    let size = vec![img.height, img.width];
+    let (bbox, polygon, score) = match values.len() {
+        4 | 5 => {
+            let bbox = Bbox {
+                left: (values[0] - values[2] / 2.0) * width,
+                top: (values[1] - values[3] / 2.0) * height,
+                width: values[2] * width,
+                height: values[3] * height,
+            };
+            (bbox.clone(), bbox_polygon(&bbox), values.get(4).copied())
+        }
+        n if n >= 6 && n % 2 == 0 => {
+            let polygon: Vec<f64> = values
+                .chunks_exact(2)
+                .flat_map(|xy| [xy[0] * width, xy[1] * height])
+                .collect();
+            let bbox = Bbox::from(&vec![polygon.clone()]);
+            (bbox, polygon, None)
+        }
+        n => {
+            return Err(format!(
+                "expected 4 or 5 values for a bounding box, or an even number of at least 6 values for a polygon, but got {n}."
+            ))
+        }
+    };
+
+    let segmentation = PolygonsRS {
+        size,
+        counts: vec![polygon],
+    };
+    Ok(Annotation {
+        id: ann_id,
+        image_id: img.id,
+        category_id: class_index as u32 + 1,
+        area: f64::from(rle_from_poly(&segmentation).area()),
+        segmentation: Segmentation::PolygonsRS(segmentation),
+        bbox,
+        iscrowd: 0,
+        score,
+        keypoints: None,
+        num_keypoints: None,
+        extra: ExtraFields::default(),
+    })
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::coco::fixtures::{self, cat};
+    use crate::coco::object_detection::Rle;
+    use rstest::rstest;
+
+    fn ann(id: u64, category_id: u32, segmentation: Segmentation, iscrowd: u32) -> Annotation {
+        Annotation {
+            segmentation,
+            area: 0.0,
+            bbox: Bbox {
+                left: 10.0,
+                top: 20.0,
+                width: 20.0,
+                height: 40.0,
+            },
+            iscrowd,
+            ..fixtures::ann(id, 1, category_id)
+        }
+    }
+
+    fn dataset() -> HashmapDataset {
+        let dataset = Dataset {
+            images: vec![
+                Image {
+                    id: 1,
+                    width: 100,
+                    height: 50,
+                    file_name: "1.jpg".to_string(),
+                    ..Default::default()
+                },
+                Image {
+                    id: 2,
+                    width: 100,
+                    height: 50,
+                    file_name: "2.jpg".to_string(),
+                    ..Default::default()
+                },
+            ],
+            annotations: vec![
+                ann(
+                    1,
+                    7,
+                    Segmentation::Polygons(vec![vec![10.0, 20.0, 30.0, 20.0, 30.0, 60.0]]),
+                    0,
+                ),
+                ann(
+                    2,
+                    3,
+                    Segmentation::Polygons(vec![
+                        vec![0.0, 0.0, 10.0, 0.0, 10.0, 10.0],
+                        vec![50.0, 0.0, 60.0, 0.0, 60.0, 10.0],
+                    ]),
+                    0,
+                ),
+                ann(
+                    3,
+                    3,
+                    Segmentation::Rle(Rle {
+                        size: vec![50, 100],
+                        counts: vec![5000],
+                    }),
+                    1,
+                ),
+            ],
+            categories: vec![cat(7, "dog"), cat(3, "it's a cat")],
+            ..Default::default()
+        };
+        HashmapDataset::from_dataset(dataset, "N/A").unwrap()
+    }
+
+    #[test]
+    fn class_indices_are_dense() {
+        assert_eq!(class_indices(&dataset()), HashMap::from([(3, 0), (7, 1)]));
+    }
+
+    #[test]
+    fn export_detect() {
+        let output_folder = tempfile::tempdir().unwrap();
+        export(&dataset(), output_folder.path(), YoloTask::Detect, false).unwrap();
+
+        let labels = fs::read_to_string(output_folder.path().join("labels/1.txt")).unwrap();
+        assert_eq!(
+            labels,
+            "1 0.200000 0.700000 0.200000 0.600000\n0 0.200000 0.700000 0.200000 0.600000\n"
+        );
+        let labels = fs::read_to_string(output_folder.path().join("labels/2.txt")).unwrap();
+        assert_eq!(labels, "");
+        let data_yaml = fs::read_to_string(output_folder.path().join("data.yaml")).unwrap();
+        assert_eq!(
+            data_yaml,
+            "path: .\ntrain: images\nval: images\nnc: 2\nnames:\n  0: 'it''s a cat'\n  1: 'dog'\n"
+        );
+    }
+
+    #[test]
+    fn export_segment() {
+        let output_folder = tempfile::tempdir().unwrap();
+        export(&dataset(), output_folder.path(), YoloTask::Segment, false).unwrap();
+
+        let labels = fs::read_to_string(output_folder.path().join("labels/1.txt")).unwrap();
+        let lines: Vec<&str> = labels.lines().collect();
+        assert_eq!(
+            lines[0],
+            "1 0.100000 0.400000 0.300000 0.400000 0.300000 1.000000"
+        );
+        assert_eq!(
+            lines[1],
+            "0 0.000000 0.000000 0.100000 0.000000 0.100000 0.200000 0.000000 0.000000 \
+             0.500000 0.000000 0.600000 0.000000 0.600000 0.200000 0.500000 0.000000"
+        );
+        assert_eq!(lines.len(), 2);
+    }
+
+    #[rstest]
+    #[case::inside(Bbox { left: 10.0, top: 10.0, width: 20.0, height: 20.0 }, "0 0.200000 0.400000 0.200000 0.400000")]
+    #[case::overflow_bottom_right(Bbox { left: 80.0, top: 40.0, width: 40.0, height: 20.0 }, "0 0.900000 0.900000 0.200000 0.200000")]
+    #[case::overflow_top_left(Bbox { left: -10.0, top: -10.0, width: 30.0, height: 20.0 }, "0 0.100000 0.100000 0.200000 0.200000")]
+    fn label_line_clips_bbox(#[case] bbox: Bbox, #[case] expected: &str) {
+        let img = Image {
+            id: 1,
+            width: 100,
+            height: 50,
+            file_name: "1.jpg".to_string(),
+            ..Default::default()
+        };
+        let ann = Annotation {
+            bbox,
+            ..fixtures::ann(1, 1, 1)
+        };
+        assert_eq!(label_line(&ann, &img, 0, YoloTask::Detect), expected);
+    }
+
+    /// Create a YOLO dataset with a 100x50 image with labels and a 20x10 image without labels.
+    fn yolo_dataset(labels: &str) -> tempfile::TempDir {
+        let folder = tempfile::tempdir().unwrap();
+        fs::create_dir_all(folder.path().join("images/sub")).unwrap();
+        fs::create_dir_all(folder.path().join("labels/sub")).unwrap();
+        image::RgbImage::new(100, 50)
+            .save(folder.path().join("images/sub/a.png"))
+            .unwrap();
+        image::RgbImage::new(20, 10)
+            .save(folder.path().join("images/b.png"))
+            .unwrap();
+        fs::write(folder.path().join("images/notes.txt"), "not an image").unwrap();
+        fs::write(folder.path().join("labels/sub/a.txt"), labels).unwrap();
+        folder
+    }
+
+    #[test]
+    fn import_labels() {
+        let folder =
+            yolo_dataset("1 0.2 0.6 0.2 0.4\n\n0 0.1 0.4 0.3 0.4 0.3 1.0\n0 0.5 0.5 0.1 0.1 0.9\n");
+        let class_names = vec!["cat".to_string(), "dog".to_string()];
+        let dataset = import(
+            folder.path().join("images"),
+            folder.path().join("labels"),
+            &class_names,
+        )
+        .unwrap();
+
+        let imgs = dataset.get_imgs();
+        assert_eq!(imgs.len(), 2);
+        assert_eq!((imgs[0].file_name.as_str(), imgs[0].width), ("b.png", 20));
+        assert_eq!(dataset.get_img_anns(1).unwrap(), Vec::<&Annotation>::new());
+        assert_eq!(dataset.get_cat(2).unwrap().name, "dog");
+
+        let anns = dataset.get_img_anns(2).unwrap();
+        assert_eq!(anns.len(), 3);
+        assert_eq!(anns[0].category_id, 2);
+        assert_eq!(
+            anns[0].bbox,
+            Bbox {
+                left: 10.0,
+                top: 20.0,
+                width: 20.0,
+                height: 20.0
+            }
+        );
+        assert_eq!(anns[0].area, 400.0);
+        assert_eq!(anns[1].category_id, 1);
+        assert_eq!(
+            anns[1].segmentation,
+            Segmentation::PolygonsRS(PolygonsRS {
+                size: vec![50, 100],
+                counts: vec![vec![10.0, 20.0, 30.0, 20.0, 30.0, 50.0]]
+            })
+        );
+        assert_eq!(anns[1].bbox.height, 30.0);
+        assert_eq!(anns[2].score, Some(0.9));
+    }
+
+    #[rstest]
+    #[case::class_index("2 0.5 0.5 0.1 0.1", 1)]
+    #[case::not_a_number("\n0 0.5 0.5 0.1 0.1\n0 0.5 abc 0.1 0.1", 3)]
+    #[case::odd_polygon("0 0.5 0.5 0.1 0.1 0.2 0.2 0.3", 1)]
+    #[case::negative_class("-1 0.5 0.5 0.1 0.1", 1)]
+    fn import_invalid_line(#[case] labels: &str, #[case] expected_line: usize) {
+        let folder = yolo_dataset(labels);
+        let class_names = vec!["cat".to_string(), "dog".to_string()];
+        let result = import(
+            folder.path().join("images"),
+            folder.path().join("labels"),
+            &class_names,
+        );
+        match result {
+            Err(ConversionError::InvalidLine(path, line, _)) => {
+                assert_eq!(path, folder.path().join("labels/sub/a.txt"));
+                assert_eq!(line, expected_line);
+            }
+            _ => panic!("Expected an invalid line error."),
+        }
+    }
+
+    #[rstest]
+    #[case::yaml_list("data.yaml", "path: .\nnames: ['cat', 'dog']\n")]
+    #[case::yaml_map("data.yml", "nc: 2\nnames:\n  1: dog\n  0: cat\n")]
+    #[case::txt("classes.txt", "cat\ndog\n\n")]
+    fn class_names(#[case] file_name: &str, #[case] content: &str) {
+        let folder = tempfile::tempdir().unwrap();
+        let path = folder.path().join(file_name);
+        fs::write(&path, content).unwrap();
+        assert_eq!(load_class_names(&path).unwrap(), vec!["cat", "dog"]);
+    }
+
+    #[rstest]
+    #[case::missing_index("names:\n  0: cat\n  2: dog\n")]
+    #[case::not_from_zero("names:\n  1: cat\n  2: dog\n")]
+    fn class_names_invalid_indices(#[case] content: &str) {
+        let folder = tempfile::tempdir().unwrap();
+        let path = folder.path().join("data.yaml");
+        fs::write(&path, content).unwrap();
+        assert!(matches!(
+            load_class_names(&path),
+            Err(ConversionError::InvalidContent(_, _))
+        ));
+    }
+
+    #[test]
+    fn data_yaml_roundtrip() {
+        let folder = tempfile::tempdir().unwrap();
+        export(&dataset(), folder.path(), YoloTask::Detect, false).unwrap();
+        let class_names = load_class_names(folder.path().join("data.yaml")).unwrap();
+        assert_eq!(class_names, vec!["it's a cat", "dog"]);
+    }
+
+    #[test]
+    fn bbox_fallback() {
+        let ann = ann(1, 1, Segmentation::Polygons(vec![]), 0);
+        assert_eq!(
+            polygons(&ann),
+            vec![vec![10.0, 20.0, 30.0, 20.0, 30.0, 60.0, 10.0, 60.0]]
+        );
+    }
+}
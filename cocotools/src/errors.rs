@@ -25,6 +25,8 @@ pub enum LoadingError {
     Deserialize(#[source] serde_json::Error, PathBuf),
     #[error("Failed to parse the annotation file/dataset. Found an annotation for an image id not in the dataset.")]
     Parsing(#[source] MissingIdError),
+    #[error("The result number {0} has neither a bounding box nor a valid segmentation.")]
+    InvalidResult(usize),
     #[error(transparent)]
     Image(#[from] anyhow::Error),
 }
@@ -39,10 +41,118 @@ pub enum MaskError {
     StrConversion(#[source] std::str::Utf8Error, Vec<u8>),
     #[error("Failed to convert an image mask to an ndarray version of it.")]
     ImageToNDArrayConversion(#[source] ndarray::ShapeError),
+    #[error("The masks do not have the same size: {0:?} and {1:?}.")]
+    SizeMismatch(Vec<u32>, Vec<u32>),
+    #[error("Expected at least one mask, but got none.")]
+    Empty,
+    #[error("The counts of the RLE add up to {0} pixels, but its size {1:?} has {2} pixels.")]
+    InvalidCounts(u64, Vec<u32>, u64),
     #[error(transparent)]
     Other(#[from] anyhow::Error),
 }
 
+/// Error returned when the detections cannot be evaluated.
+#[derive(Debug, Error)]
+pub enum EvaluationError {
+    #[error("The detection for the image `{0}` and category `{1}` does not have a bounding box, which is required for the bounding box evaluation.")]
+    MissingBbox(u64, u32),
+    #[error("The detection for the image `{0}` and category `{1}` does not have a segmentation, which is required for the segmentation evaluation.")]
+    MissingSegmentation(u64, u32),
+    #[error("The detection for the image `{0}` and category `{1}` does not have keypoints, which are required for the keypoints evaluation.")]
+    MissingKeypoints(u64, u32),
+    #[error("There is no panoptic prediction for the image `{0}`.")]
+    MissingPrediction(u64),
+    #[error("The predicted and ground truth panoptic PNG files of the image `{0}` do not have the same size.")]
+    PanopticSizeMismatch(u64),
+    #[error("In the image `{0}`, the segment `{1}` is in the predicted PNG file but not in the json file.")]
+    SegmentNotInJson(u64, u32),
+    #[error("In the image `{0}`, the segments {1:?} are in the predicted json file but not in the PNG file.")]
+    SegmentNotInPng(u64, Vec<u32>),
+    #[error("In the image `{0}`, the predicted segment `{1}` has a category `{2}` which is not in the ground truth.")]
+    UnknownCategory(u64, u32, u32),
+}
+
+/// Error returned when a dataset cannot be converted to or from another dataset format.
+#[derive(Error)]
+pub enum ConversionError {
+    #[error("Failed to read the file {1:?}.")]
+    Read(#[source] std::io::Error, PathBuf),
+    #[error("Failed to write the file {1:?}.")]
+    Write(#[source] std::io::Error, PathBuf),
+    #[error("Failed to read the size of the image {1:?}.")]
+    ImageSize(#[source] image::ImageError, PathBuf),
+    #[error("Failed to read or write the image {1:?}.")]
+    Image(#[source] image::ImageError, PathBuf),
+    #[error("The ignore value {1} is also the index of a category, the label maps of {0} categories need an ignore value of at least {0}.")]
+    IgnoreValueConflict(usize, u8),
+    #[error("Failed to parse the yaml file {1:?}.")]
+    Yaml(#[source] serde_yaml::Error, PathBuf),
+    #[error("The file {0:?} does not have a `names` entry with the class names.")]
+    MissingClassNames(PathBuf),
+    #[error("Failed to parse the json file {1:?}.")]
+    Json(#[source] serde_json::Error, PathBuf),
+    #[error("Failed to parse the csv file {1:?}.")]
+    Csv(#[source] csv::Error, PathBuf),
+    #[error("Failed to parse the xml file {1:?}.")]
+    Xml(#[source] roxmltree::Error, PathBuf),
+    #[error("The images {0:?} and {1:?} both have the VOC id `{2}`.")]
+    DuplicateVocId(String, String, String),
+    #[error("Invalid content in the file {0:?}: {1}")]
+    InvalidContent(PathBuf, String),
+    #[error("Invalid line {1} in the file {0:?}: {2}")]
+    InvalidLine(PathBuf, usize, String),
+    #[error(transparent)]
+    Loading(#[from] LoadingError),
+    #[error(transparent)]
+    Mask(#[from] MaskError),
+}
+
+/// Error returned when a dataset cannot be split.
+#[derive(Debug, Error)]
+pub enum SplitError {
+    #[error("Expected at least one split ratio, and only positive ratios, but got {0:?}.")]
+    InvalidRatios(Vec<f64>),
+    #[error("Expected at least 2 folds, but got {0}.")]
+    InvalidFoldNumber(usize),
+    #[error("Cannot make {0} folds with only {1} image groups.")]
+    NotEnoughGroups(usize, usize),
+    #[error(transparent)]
+    MissingId(#[from] MissingIdError),
+}
+
+/// Error returned when datasets cannot be merged.
+#[derive(Error)]
+pub enum MergeError {
+    #[error("Failed to read the category mapping file {1:?}.")]
+    Read(#[source] std::io::Error, PathBuf),
+    #[error("Failed to parse the category mapping file {1:?}, expected a json object mapping category names to category names.")]
+    Json(#[source] serde_json::Error, PathBuf),
+    #[error("The image `{0}` appears several times in the datasets.")]
+    DuplicateImage(String),
+    #[error("The annotation `{0}` of the dataset number {1} has a category which is not in the dataset.")]
+    MissingCategory(u64, usize),
+    #[error(transparent)]
+    Loading(#[from] LoadingError),
+}
+
+/// Error returned when a filter expression cannot be parsed.
+#[derive(Debug, Error)]
+pub enum FilterError {
+    #[error("Invalid filter expression `{0}`: {1}")]
+    InvalidExpression(String, String),
+}
+
+/// Error returned when a category mapping cannot be loaded or applied.
+#[derive(Error)]
+pub enum RemapError {
+    #[error("Failed to read the category mapping file {1:?}.")]
+    Read(#[source] std::io::Error, PathBuf),
+    #[error("Failed to parse the category mapping file {1:?}.")]
+    Json(#[source] serde_json::Error, PathBuf),
+    #[error("Invalid category mapping: {0}")]
+    Conflict(String),
+}
+
 /// Enum grouping all the error types from the crate.
 #[derive(Debug, Error)]
 pub enum CocoError {
@@ -52,6 +162,10 @@ pub enum CocoError {
     Loading(#[from] LoadingError),
     #[error(transparent)]
     Mask(#[from] MaskError),
+    #[error(transparent)]
+    Evaluation(#[from] EvaluationError),
+    #[error(transparent)]
+    Conversion(#[from] ConversionError),
 }
 
 // From https://www.lpalmieri.com/posts/error-handling-rust/
@@ -66,6 +180,21 @@ impl std::fmt::Debug for LoadingError {
         error_chain_fmt(self, f)
     }
 }
+impl std::fmt::Debug for MergeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+impl std::fmt::Debug for RemapError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+impl std::fmt::Debug for ConversionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
 
 fn error_chain_fmt(
     e: &impl std::error::Error,
@@ -39,6 +39,7 @@
 //! ```
 
 pub mod coco;
+pub mod converters;
 pub mod errors;
 pub mod mask;
 pub(crate) mod utils;
@@ -1,17 +1,24 @@
+use std::collections::HashMap;
 use std::error;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use clap::Parser;
 
+use cocotools::coco::filter::Filter;
+use cocotools::coco::merge;
+use cocotools::coco::object_detection::Dataset;
+use cocotools::coco::remap;
+use cocotools::coco::split::FoldSummary;
+use cocotools::coco::validate::{self, Severity};
+use cocotools::converters::{
+    cityscapes, cvat, kitti, labelme, labelstudio, openimages, semantic, voc, yolo,
+};
+use cocotools::mask;
+use cocotools::visualize::display;
+use cocotools::COCO;
+
 mod argparse;
-mod coco;
-mod errors;
-mod mask;
-mod utils;
-mod visualize;
-use crate::argparse::{Cli, Commands};
-use crate::coco::COCO;
-use crate::visualize::display;
+use crate::argparse::{Cli, Commands, ExportFormat, ImportFormat};
 
 fn main() -> Result<(), Box<dyn error::Error>> {
     let cli = Cli::parse();
@@ -37,14 +44,352 @@ fn main() -> Result<(), Box<dyn error::Error>> {
             annotations_path,
             target_segmentation,
             output_path,
+            sort_by_id,
         } => {
             let mut dataset = COCO::new(annotations_path, &PathBuf::from("N/A"))?;
             mask::conversions::convert_coco_segmentation(&mut dataset, *target_segmentation)?;
-            let output_path = output_path
-                .as_ref()
-                .map_or_else(|| annotations_path, |output_path| output_path);
-            dataset.save_to(output_path)?;
+            let output_path = output_path.as_ref().unwrap_or(annotations_path);
+            save(&mut dataset, output_path, *sort_by_id)?;
+        }
+        Commands::Validate {
+            annotations_file,
+            json,
+            deny_warnings,
+        } => {
+            let report = validate::validate(&Dataset::load(annotations_file)?);
+            if *json {
+                println!("{}", report.json()?);
+            } else {
+                println!("{report}");
+            }
+            if report.has_errors() || (*deny_warnings && report.count(Severity::Warning) > 0) {
+                std::process::exit(1);
+            }
+        }
+        Commands::Export(ExportFormat::Yolo {
+            annotations_file,
+            image_folder,
+            output_folder,
+            task,
+            copy_images,
+        }) => {
+            let dataset = COCO::new(annotations_file, image_folder)?;
+            yolo::export(&dataset, output_folder, *task, *copy_images)?;
+        }
+        Commands::Export(ExportFormat::Voc {
+            annotations_file,
+            image_folder,
+            output_folder,
+            split,
+            copy_images,
+        }) => {
+            let dataset = COCO::new(annotations_file, image_folder)?;
+            voc::export(&dataset, output_folder, split, *copy_images)?;
+        }
+        Commands::Export(ExportFormat::Cvat {
+            annotations_file,
+            output_path,
+        }) => {
+            let dataset = COCO::new(annotations_file, &PathBuf::from("N/A"))?;
+            cvat::export(&dataset, output_path)?;
+        }
+        Commands::Export(ExportFormat::Labelme {
+            annotations_file,
+            image_folder,
+            output_folder,
+            copy_images,
+            embed_images,
+        }) => {
+            let dataset = COCO::new(annotations_file, image_folder)?;
+            labelme::export(&dataset, output_folder, *copy_images, *embed_images)?;
+        }
+        Commands::Export(ExportFormat::Labelstudio {
+            annotations_file,
+            output_path,
+            image_root,
+            model_version,
+        }) => {
+            let dataset = COCO::new(annotations_file, &PathBuf::from("N/A"))?;
+            labelstudio::export(&dataset, output_path, image_root, model_version)?;
+        }
+        Commands::Export(ExportFormat::Semantic {
+            annotations_file,
+            output_folder,
+            ignore_value,
+            overlap,
+        }) => {
+            let dataset = COCO::new(annotations_file, &PathBuf::from("N/A"))?;
+            semantic::export(&dataset, output_folder, *ignore_value, *overlap)?;
+        }
+        Commands::Export(ExportFormat::Kitti {
+            annotations_file,
+            image_folder,
+            output_folder,
+            copy_images,
+        }) => {
+            let dataset = COCO::new(annotations_file, image_folder)?;
+            kitti::export(&dataset, output_folder, *copy_images)?;
+        }
+        Commands::Import(ImportFormat::Yolo {
+            image_folder,
+            labels_folder,
+            class_names,
+            output_path,
+            sort_by_id,
+        }) => {
+            let class_names = yolo::load_class_names(class_names)?;
+            let mut dataset = yolo::import(image_folder, labels_folder, &class_names)?;
+            save(&mut dataset, output_path, *sort_by_id)?;
+        }
+        Commands::Import(ImportFormat::Voc {
+            voc_folder,
+            output_path,
+            split,
+            sort_by_id,
+        }) => {
+            let mut dataset = voc::import(voc_folder, split.as_deref())?;
+            save(&mut dataset, output_path, *sort_by_id)?;
+        }
+        Commands::Import(ImportFormat::Cvat {
+            xml_file,
+            output_path,
+            sort_by_id,
+        }) => {
+            let mut dataset = cvat::import(xml_file, &PathBuf::from("N/A"))?;
+            save(&mut dataset, output_path, *sort_by_id)?;
+        }
+        Commands::Import(ImportFormat::Labelme {
+            labelme_folder,
+            output_path,
+            extract_images,
+            sort_by_id,
+        }) => {
+            let mut dataset = labelme::import(labelme_folder, extract_images.as_ref())?;
+            save(&mut dataset, output_path, *sort_by_id)?;
+        }
+        Commands::Import(ImportFormat::Labelstudio {
+            json_file,
+            image_folder,
+            output_path,
+            predictions,
+            sort_by_id,
+        }) => {
+            let mut dataset = labelstudio::import(json_file, image_folder, *predictions)?;
+            save(&mut dataset, output_path, *sort_by_id)?;
+        }
+        Commands::Import(ImportFormat::Openimages {
+            boxes_csv,
+            class_descriptions_csv,
+            image_folder,
+            output_path,
+            sort_by_id,
+        }) => {
+            let mut dataset = openimages::import(boxes_csv, class_descriptions_csv, image_folder)?;
+            save(&mut dataset, output_path, *sort_by_id)?;
+        }
+        Commands::Import(ImportFormat::Semantic {
+            image_folder,
+            label_maps_folder,
+            class_names,
+            output_path,
+            ignore_value,
+            sort_by_id,
+        }) => {
+            let class_names = yolo::load_class_names(class_names)?;
+            let mut dataset =
+                semantic::import(image_folder, label_maps_folder, &class_names, *ignore_value)?;
+            save(&mut dataset, output_path, *sort_by_id)?;
+        }
+        Commands::Import(ImportFormat::Cityscapes {
+            instances_folder,
+            image_folder,
+            output_path,
+            labels,
+            instances_suffix,
+            image_suffix,
+            include_stuff,
+            sort_by_id,
+        }) => {
+            let labels = match labels {
+                Some(labels_path) => cityscapes::load_labels(labels_path)?,
+                None => cityscapes::cityscapes_labels(),
+            };
+            let mut dataset = cityscapes::import(
+                instances_folder,
+                image_folder,
+                &labels,
+                instances_suffix,
+                image_suffix,
+                *include_stuff,
+            )?;
+            save(&mut dataset, output_path, *sort_by_id)?;
+        }
+        Commands::Import(ImportFormat::Kitti {
+            image_folder,
+            labels_folder,
+            output_path,
+            sort_by_id,
+        }) => {
+            let mut dataset = kitti::import(image_folder, labels_folder)?;
+            save(&mut dataset, output_path, *sort_by_id)?;
+        }
+        Commands::Split {
+            annotations_file,
+            output_folder,
+            ratios,
+            names,
+            seed,
+            stratify,
+            sort_by_id,
+        } => {
+            let names = match names {
+                Some(names) if names.len() != ratios.len() => {
+                    return Err(format!(
+                        "Got {} split names for {} split ratios.",
+                        names.len(),
+                        ratios.len()
+                    )
+                    .into())
+                }
+                Some(names) => names.clone(),
+                None if ratios.len() <= 3 => ["train", "val", "test"][..ratios.len()]
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect(),
+                None => (1..=ratios.len()).map(|i| format!("split_{i}")).collect(),
+            };
+            let dataset = COCO::new(annotations_file, &PathBuf::from("N/A"))?;
+            let splits = dataset.split(ratios, *seed, *stratify)?;
+            std::fs::create_dir_all(output_folder)?;
+            for (name, mut split) in names.iter().zip(splits) {
+                save(
+                    &mut split,
+                    &output_folder.join(format!("{name}.json")),
+                    *sort_by_id,
+                )?;
+                println!(
+                    "{name}: {} images, {} annotations",
+                    split.get_imgs().len(),
+                    split.get_anns().len()
+                );
+            }
+        }
+        Commands::KFold {
+            annotations_file,
+            output_folder,
+            k,
+            seed,
+            stratify,
+            group_by,
+            sort_by_id,
+        } => {
+            let dataset = COCO::new(annotations_file, &PathBuf::from("N/A"))?;
+            let folds = dataset.k_fold(*k, *seed, *stratify, group_by.as_deref())?;
+            let mut summaries = Vec::with_capacity(folds.len());
+            for (i, (mut train, mut val)) in folds.into_iter().enumerate() {
+                let fold_folder = output_folder.join(format!("fold_{}", i + 1));
+                std::fs::create_dir_all(&fold_folder)?;
+                save(&mut train, &fold_folder.join("train.json"), *sort_by_id)?;
+                save(&mut val, &fold_folder.join("val.json"), *sort_by_id)?;
+                let summary = FoldSummary::new(&train, &val);
+                println!("fold {}: {summary}", i + 1);
+                summaries.push(summary);
+            }
+            std::fs::write(
+                output_folder.join("summary.json"),
+                serde_json::to_string_pretty(&summaries)?,
+            )?;
+        }
+        Commands::Merge {
+            annotations_files,
+            output_path,
+            image_folders,
+            mapping,
+            duplicates,
+            sort_by_id,
+        } => {
+            let image_folders = match image_folders {
+                Some(image_folders) if image_folders.len() != annotations_files.len() => {
+                    return Err(format!(
+                        "Got {} image folders for {} annotation files.",
+                        image_folders.len(),
+                        annotations_files.len()
+                    )
+                    .into())
+                }
+                Some(image_folders) => image_folders.clone(),
+                None => vec![PathBuf::from("N/A"); annotations_files.len()],
+            };
+            let datasets = annotations_files
+                .iter()
+                .zip(&image_folders)
+                .map(|(annotations_file, image_folder)| COCO::new(annotations_file, image_folder))
+                .collect::<Result<Vec<COCO>, _>>()?;
+            let synonyms = match mapping {
+                Some(mapping) => merge::load_synonyms(mapping)?,
+                None => HashMap::new(),
+            };
+            let (mut dataset, report) = merge::merge(&datasets, &synonyms, *duplicates)?;
+            save(&mut dataset, output_path, *sort_by_id)?;
+            println!("{report}");
+            if dataset.image_folder != Path::new("N/A") {
+                println!(
+                    "The image paths are relative to {}",
+                    dataset.image_folder.display()
+                );
+            }
+        }
+        Commands::Filter {
+            annotations_file,
+            expression,
+            output_path,
+            drop_empty_images,
+            prune_categories,
+            sort_by_id,
+        } => {
+            let filter = Filter {
+                drop_empty_images: *drop_empty_images,
+                prune_categories: *prune_categories,
+                ..expression.parse()?
+            };
+            let dataset = COCO::new(annotations_file, &PathBuf::from("N/A"))?;
+            let mut filtered = dataset.filter(&filter);
+            save(&mut filtered, output_path, *sort_by_id)?;
+            println!(
+                "Kept {} images, {} annotations and {} categories",
+                filtered.get_imgs().len(),
+                filtered.get_anns().len(),
+                filtered.get_cats().len()
+            );
+        }
+        Commands::Remap {
+            annotations_file,
+            mapping_file,
+            output_path,
+            sort_by_id,
+        } => {
+            let rules = remap::load_mapping(mapping_file)?;
+            let mut dataset = COCO::new(annotations_file, &PathBuf::from("N/A"))?;
+            let report = dataset.remap_categories(&rules)?;
+            save(
+                &mut dataset,
+                output_path.as_ref().unwrap_or(annotations_file),
+                *sort_by_id,
+            )?;
+            println!("{report}");
         }
     }
     Ok(())
 }
+
+/// Save the dataset to the given path, ordering its entries by id first if `sort_by_id` is true.
+fn save(
+    dataset: &mut COCO,
+    output_path: &Path,
+    sort_by_id: bool,
+) -> Result<(), Box<dyn error::Error>> {
+    if sort_by_id {
+        dataset.sort_by_id();
+    }
+    dataset.save_to(output_path)
+}
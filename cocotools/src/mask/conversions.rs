@@ -77,7 +77,7 @@ impl From<&Mask> for object_detection::Rle {
         let mut previous_value = 0;
         let mut count = 0;
         let mut counts = Vec::new();
-        for value in mask.clone().reversed_axes().iter() {
+        for value in &mask.clone().reversed_axes() {
             if *value != previous_value {
                 counts.push(count);
                 previous_value = *value;
@@ -95,11 +95,11 @@ impl From<&Mask> for object_detection::Rle {
 }
 
 /// Decode COCO RLE segmentation information into RLE.
-
+///
 /// See the (hard to read) implementation:
 /// <https://github.com/cocodataset/cocoapi/blob/master/common/maskApi.c#L218>
 /// <https://github.com/cocodataset/cocoapi/blob/8c9bcc3cf640524c4c20a9c40e89cb6a2f2fa0e9/PythonAPI/pycocotools/_mask.pyx#L145>
-
+///
 /// [LEB128 wikipedia article](https://en.wikipedia.org/wiki/LEB128#Decode_signed_integer)
 /// It is similar to LEB128, but here shift is incremented by 5 instead of 7 because the implementation uses
 /// 6 bits per byte instead of 8. (no idea why, I guess it's more efficient for the COCO dataset?)
@@ -192,7 +192,7 @@ impl TryFrom<&object_detection::Rle> for object_detection::CocoRle {
                 };
                 if high_order_bit {
                     byte |= 0x20;
-                };
+                }
                 byte += 48;
                 coco_counts.push(byte);
             }
@@ -242,10 +242,12 @@ impl From<&object_detection::Rle> for object_detection::Polygons {
         let mut prev_prev_y: u32;
         let mut prev_x: u32;
         let mut prev_y: u32;
-        for (i, contour) in contours.iter().enumerate() {
+        for contour in &contours {
             // Valid polygons must have at least 3 points.
             // The case of having less than 3 points is not expected to occur on real data, hence the silent failt if it occurs.
             if contour.points.len() > 3 {
+                // Contours with less than 3 points are skipped, hence the index of the polygon is not the one of the contour.
+                let i = counts.len();
                 counts.push(Vec::with_capacity(2 * contour.points.len()));
 
                 counts[i].push(f64::from(contour.points[0].y));
@@ -404,6 +406,159 @@ pub fn mask_from_poly(
         .map_err(MaskError::ImageToNDArrayConversion)
 }
 
+/// Compute the RLE of a polygon segmentation without decoding it to a dense mask.
+///
+/// This is a port of [maskApi.c's rleFrPoly](https://github.com/cocodataset/cocoapi/blob/master/common/maskApi.c#L162),
+/// the resulting RLE is therefore identical to the one pycocotools would compute. When there are multiple polygons, their union is returned.
+///
+/// ## Args:
+/// - poly: The polygons, with the size of the image they correspond to.
+///
+/// ## Returns:
+/// - The RLE corresponding to the polygons.
+///
+/// ## Example
+///
+/// ```rust
+/// # use cocotools::coco::object_detection::{PolygonsRS, Rle};
+/// use cocotools::mask::conversions::rle_from_poly;
+/// let poly = PolygonsRS { size: vec![7, 7], counts: vec![vec![1.0, 2.0, 1.0, 4.0, 5.0, 4.0, 5.0, 2.0]] };
+/// let rle = rle_from_poly(&poly);
+/// assert_eq!(rle, Rle { size: vec![7, 7], counts: vec![9, 2, 5, 2, 5, 2, 5, 2, 17] });
+/// ```
+///
+/// ## Panics
+///
+/// Will panic if the size of the polygons does not have two elements.
+#[allow(clippy::module_name_repetitions, clippy::expect_used)]
+#[must_use]
+pub fn rle_from_poly(poly: &object_detection::PolygonsRS) -> object_detection::Rle {
+    let height = poly.size[0];
+    let width = poly.size[1];
+    let mut rles: Vec<object_detection::Rle> = poly
+        .counts
+        .iter()
+        .map(|xy| rle_from_single_poly(xy, height, width))
+        .collect();
+    match rles.len() {
+        0 => object_detection::Rle {
+            size: vec![height, width],
+            counts: vec![height * width],
+        },
+        1 => rles.swap_remove(0),
+        _ => super::utils::merge(&rles.iter().collect::<Vec<_>>(), false)
+            .expect("All the RLEs are created with the same size just above."),
+    }
+}
+
+/// Rasterize a single polygon (see [`rle_from_poly`]).
+// The floating point operations are kept as in the reference implementation (no mul_add) to get the same results.
+#[allow(
+    clippy::suboptimal_flops,
+    clippy::float_cmp,
+    clippy::cast_possible_truncation,
+    clippy::cast_possible_wrap,
+    clippy::cast_sign_loss,
+    clippy::many_single_char_names,
+    clippy::similar_names
+)]
+fn rle_from_single_poly(xy: &[f64], height: u32, width: u32) -> object_detection::Rle {
+    // Upsample and get discrete points densely along the entire boundary.
+    let scale = 5.0;
+    let nb_points = xy.len() / 2;
+    let mut x: Vec<i32> = (0..nb_points)
+        .map(|j| (scale * xy[2 * j] + 0.5) as i32)
+        .collect();
+    let mut y: Vec<i32> = (0..nb_points)
+        .map(|j| (scale * xy[2 * j + 1] + 0.5) as i32)
+        .collect();
+    x.push(x.first().copied().unwrap_or(0));
+    y.push(y.first().copied().unwrap_or(0));
+
+    let mut u: Vec<i32> = Vec::new();
+    let mut v: Vec<i32> = Vec::new();
+    for j in 0..nb_points {
+        let (mut xs, mut xe, mut ys, mut ye) = (x[j], x[j + 1], y[j], y[j + 1]);
+        let dx = (xe - xs).abs();
+        let dy = (ys - ye).abs();
+        let flip = (dx >= dy && xs > xe) || (dx < dy && ys > ye);
+        if flip {
+            std::mem::swap(&mut xs, &mut xe);
+            std::mem::swap(&mut ys, &mut ye);
+        }
+        if dx >= dy {
+            let s = if dx == 0 {
+                0.0
+            } else {
+                f64::from(ye - ys) / f64::from(dx)
+            };
+            for d in 0..=dx {
+                let t = if flip { dx - d } else { d };
+                u.push(t + xs);
+                v.push((f64::from(ys) + s * f64::from(t) + 0.5) as i32);
+            }
+        } else {
+            let s = f64::from(xe - xs) / f64::from(dy);
+            for d in 0..=dy {
+                let t = if flip { dy - d } else { d };
+                v.push(t + ys);
+                u.push((f64::from(xs) + s * f64::from(t) + 0.5) as i32);
+            }
+        }
+    }
+
+    // Get the points along the y-boundary and downsample.
+    let mut boundary: Vec<u32> = Vec::new();
+    for j in 1..u.len() {
+        if u[j] == u[j - 1] {
+            continue;
+        }
+        let xd = f64::from(if u[j] < u[j - 1] { u[j] } else { u[j] - 1 });
+        let xd = (xd + 0.5) / scale - 0.5;
+        if xd.floor() != xd || xd < 0.0 || xd > f64::from(width) - 1.0 {
+            continue;
+        }
+        let yd = f64::from(if v[j] < v[j - 1] { v[j] } else { v[j - 1] });
+        let yd = ((yd + 0.5) / scale - 0.5)
+            .clamp(0.0, f64::from(height))
+            .ceil();
+        boundary.push(xd as u32 * height + yd as u32);
+    }
+
+    // Compute the RLE encoding given the y-boundary points.
+    boundary.push(height * width);
+    boundary.sort_unstable();
+    let mut previous = 0;
+    for value in &mut boundary {
+        let current = *value;
+        *value -= previous;
+        previous = current;
+    }
+    let mut counts: Vec<u32> = Vec::with_capacity(boundary.len());
+    let mut j = 0;
+    counts.push(boundary[j]);
+    j += 1;
+    while j < boundary.len() {
+        if boundary[j] > 0 {
+            counts.push(boundary[j]);
+            j += 1;
+        } else {
+            j += 1;
+            if j < boundary.len() {
+                if let Some(last) = counts.last_mut() {
+                    *last += boundary[j];
+                }
+                j += 1;
+            }
+        }
+    }
+
+    object_detection::Rle {
+        size: vec![height, width],
+        counts,
+    }
+}
+
 #[allow(clippy::cast_possible_truncation, clippy::module_name_repetitions)]
 #[must_use]
 pub fn poly_from_mask(mask: &Mask) -> object_detection::Polygons {
@@ -411,7 +566,7 @@ pub fn poly_from_mask(mask: &Mask) -> object_detection::Polygons {
 }
 
 #[cfg(test)]
-#[allow(clippy::unwrap_used)]
+#[allow(clippy::unwrap_used, clippy::unnecessary_fallible_conversions)]
 mod tests {
     use super::object_detection::{CocoRle, Polygons, PolygonsRS, Rle};
     use super::*;
@@ -503,6 +658,10 @@ mod tests {
         &Rle {size: vec![7, 7], counts: vec![15, 5, 2, 5, 2, 5, 15]},
         &PolygonsRS {size: vec![7, 7], counts: vec![vec![2.0, 1.0, 2.0, 5.0, 4.0, 5.0, 4.0, 1.0]]}
     )]
+    #[case::skipped_small_contour(
+        &Rle {size: vec![7, 7], counts: vec![0, 1, 8, 3, 4, 3, 4, 3, 4, 3, 4, 3, 9]},
+        &PolygonsRS {size: vec![7, 7], counts: vec![vec![1.0, 2.0, 1.0, 4.0, 5.0, 4.0, 5.0, 2.0]]}
+    )]
     // There is no method defined for testing the equality of two polygons, the assert_eq is therefore done between PolygonsRS.
     fn rle_to_poly(#[case] rle: &Rle, #[case] expected_polygon: &PolygonsRS) {
         let poly = PolygonsRS {
@@ -512,6 +671,24 @@ mod tests {
         assert_eq!(&poly, expected_polygon);
     }
 
+    #[rstest]
+    #[case::rectangle(
+        &PolygonsRS {size: vec![7, 7], counts: vec![vec![1.0, 2.0, 1.0, 4.0, 5.0, 4.0, 5.0, 2.0]]},
+        &Rle {size: vec![7, 7], counts: vec![9, 2, 5, 2, 5, 2, 5, 2, 17]}
+    )]
+    #[case::two_polygons(
+        &PolygonsRS {size: vec![4, 4], counts: vec![vec![0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0, 1.0], vec![3.0, 3.0, 4.0, 3.0, 4.0, 4.0, 3.0, 4.0]]},
+        &Rle {size: vec![4, 4], counts: vec![0, 1, 14, 1]}
+    )]
+    #[case::no_polygon(
+        &PolygonsRS {size: vec![4, 5], counts: vec![]},
+        &Rle {size: vec![4, 5], counts: vec![20]}
+    )]
+    fn poly_to_rle_without_mask(#[case] poly: &PolygonsRS, #[case] expected_rle: &Rle) {
+        let rle = rle_from_poly(poly);
+        assert_eq!(&rle, expected_rle);
+    }
+
     #[rstest]
     #[case::square(
         &array![[0, 0, 0, 0],
@@ -1,4 +1,5 @@
 use crate::coco::object_detection::{Bbox, CocoRle, Polygons, PolygonsRS, Rle};
+use crate::errors::MaskError;
 use crate::mask::conversions::mask_from_poly;
 use std::cmp;
 
@@ -61,10 +62,198 @@ impl Area for Polygons {
     }
 }
 
+/// Compute the intersection over union of two RLE masks, without decoding them.
+///
+/// If the ground truth is a crowd annotation, the intersection is divided by the area of the detection instead of the union.
+/// This follows [maskApi.c's rleIou](https://github.com/cocodataset/cocoapi/blob/master/common/maskApi.c#L89).
+///
+/// ## Args
+/// - `dt`: The detected mask.
+/// - `gt`: The ground truth mask.
+/// - `iscrowd`: Whether the ground truth is a crowd annotation.
+///
+/// ## Example
+///
+/// ```rust
+/// # use cocotools::coco::object_detection::Rle;
+/// use cocotools::mask::utils::iou;
+/// let dt = Rle { size: vec![4, 4], counts: vec![5, 2, 2, 2, 5] };
+/// let gt = Rle { size: vec![4, 4], counts: vec![5, 2, 9] };
+/// assert_eq!(iou(&dt, &gt, false)?, 0.5);
+/// assert_eq!(iou(&dt, &gt, true)?, 0.5);
+/// assert_eq!(iou(&gt, &dt, true)?, 1.0);
+/// # Ok::<(), cocotools::errors::MaskError>(())
+/// ```
+///
+/// # Errors
+///
+/// Will return `Err` if the two masks do not have the same size.
+pub fn iou(dt: &Rle, gt: &Rle, iscrowd: bool) -> Result<f64, MaskError> {
+    if dt.size != gt.size {
+        return Err(MaskError::SizeMismatch(dt.size.clone(), gt.size.clone()));
+    }
+    let mut intersection: u64 = 0;
+    let mut union: u64 = 0;
+    for (count, dt_value, gt_value) in RunsIter::new(dt, gt) {
+        if dt_value || gt_value {
+            union += u64::from(count);
+            if dt_value && gt_value {
+                intersection += u64::from(count);
+            }
+        }
+    }
+    if intersection == 0 {
+        return Ok(0.0);
+    }
+    if iscrowd {
+        union = u64::from(dt.area());
+    }
+    #[allow(clippy::cast_precision_loss)]
+    Ok(intersection as f64 / union as f64)
+}
+
+/// Compute the union (or intersection) of RLE masks, without decoding them.
+///
+/// This follows [maskApi.c's rleMerge](https://github.com/cocodataset/cocoapi/blob/master/common/maskApi.c#L58).
+///
+/// ## Args
+/// - `rles`: The masks to merge, they must all have the same size.
+/// - `intersect`: If true, compute the intersection of the masks instead of their union.
+///
+/// # Errors
+///
+/// Will return `Err` if `rles` is empty or if the masks do not all have the same size.
+pub fn merge(rles: &[&Rle], intersect: bool) -> Result<Rle, MaskError> {
+    let (first, others) = rles.split_first().ok_or(MaskError::Empty)?;
+    let mut merged = (*first).clone();
+    for rle in others {
+        if rle.size != merged.size {
+            return Err(MaskError::SizeMismatch(merged.size, rle.size.clone()));
+        }
+        let mut counts: Vec<u32> = Vec::new();
+        let mut value = false;
+        let mut count: u32 = 0;
+        for (run, value_a, value_b) in RunsIter::new(&merged, rle) {
+            let new_value = if intersect {
+                value_a && value_b
+            } else {
+                value_a || value_b
+            };
+            if new_value != value {
+                counts.push(count);
+                count = 0;
+                value = new_value;
+            }
+            count += run;
+        }
+        counts.push(count);
+        merged.counts = counts;
+    }
+    Ok(merged)
+}
+
+/// Iterate over two RLEs of the same size at the same time.
+///
+/// Each item is the length of a run during which neither mask changes value, along with the value of each mask.
+struct RunsIter<'a> {
+    counts_a: &'a [u32],
+    counts_b: &'a [u32],
+    /// Index of the next count to read, for each RLE.
+    idx_a: usize,
+    idx_b: usize,
+    /// Number of pixels left in the current run, for each RLE.
+    left_a: u32,
+    left_b: u32,
+    value_a: bool,
+    value_b: bool,
+}
+
+impl<'a> RunsIter<'a> {
+    fn new(rle_a: &'a Rle, rle_b: &'a Rle) -> Self {
+        let mut iter = Self {
+            counts_a: &rle_a.counts,
+            counts_b: &rle_b.counts,
+            idx_a: 0,
+            idx_b: 0,
+            left_a: 0,
+            left_b: 0,
+            // The first count is for the 0 values, the values are flipped when reading it.
+            value_a: true,
+            value_b: true,
+        };
+        iter.refill();
+        iter
+    }
+
+    /// Move to the next non empty run of each RLE if the current one is exhausted.
+    fn refill(&mut self) {
+        while self.left_a == 0 && self.idx_a < self.counts_a.len() {
+            self.left_a = self.counts_a[self.idx_a];
+            self.idx_a += 1;
+            self.value_a = !self.value_a;
+        }
+        while self.left_b == 0 && self.idx_b < self.counts_b.len() {
+            self.left_b = self.counts_b[self.idx_b];
+            self.idx_b += 1;
+            self.value_b = !self.value_b;
+        }
+    }
+}
+
+impl Iterator for RunsIter<'_> {
+    type Item = (u32, bool, bool);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let run = match (self.left_a, self.left_b) {
+            (0, 0) => return None,
+            (0, left) | (left, 0) => left,
+            (left_a, left_b) => cmp::min(left_a, left_b),
+        };
+        // If one of the RLEs is shorter than the other, its missing pixels are considered to be 0.
+        let item = (
+            run,
+            self.value_a && self.left_a > 0,
+            self.value_b && self.left_b > 0,
+        );
+        self.left_a = self.left_a.saturating_sub(run);
+        self.left_b = self.left_b.saturating_sub(run);
+        self.refill();
+        Some(item)
+    }
+}
+
 #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
 impl From<&Rle> for Bbox {
+    /// Port of pycocotools' `rleToBbox`, the empty runs of mask pixels being skipped.
     fn from(rle: &Rle) -> Self {
-        if rle.counts.len() <= 1 {
+        let height = rle.size[0];
+        let (mut left, mut top) = (rle.size[1], height);
+        let (mut right, mut bottom) = (0, 0);
+        let mut is_empty = true;
+        let mut pos: u32 = 0;
+        // A trailing count of background pixels does not change the box.
+        for run in rle.counts.chunks_exact(2) {
+            let start = pos + run[0];
+            pos = start + run[1];
+            if run[1] == 0 {
+                continue;
+            }
+            is_empty = false;
+            // The RLE corresponds to a fortran array, the columns are contiguous.
+            let (start_x, start_y) = (start / height, start % height);
+            let (end_x, end_y) = ((pos - 1) / height, (pos - 1) % height);
+            if start_x < end_x {
+                // The run spans several columns, it therefore covers the full height.
+                top = 0;
+                bottom = height - 1;
+            }
+            left = cmp::min(left, start_x);
+            right = cmp::max(right, end_x);
+            top = cmp::min(top, cmp::min(start_y, end_y));
+            bottom = cmp::max(bottom, cmp::max(start_y, end_y));
+        }
+
+        if is_empty {
             return Self {
                 left: 0.0,
                 top: 0.0,
@@ -72,35 +261,11 @@ impl From<&Rle> for Bbox {
                 height: 0.0,
             };
         }
-        let height = rle.size[0];
-        let width = rle.size[1];
-        let mut pos: u32 = 0;
-        let mut current: u32;
-        let mut left: u32 = 0;
-        let mut right: u32 = 0;
-        let mut top = width;
-        let mut bot: u32 = 0;
-        for (i, count) in rle.counts[..rle.counts.len() - 1].iter().enumerate() {
-            pos += count;
-            current = pos - ((i % 2) as u32); // Do not count "current" pixel when adding mask pixels.
-
-            // In the lines below, left/right and top/bot might seem inverted, that's because the RLE corresponds to a fortran array.
-            if i == 0 {
-                left = current / height;
-            } else if i == rle.counts.len() - 2 {
-                right = current / height;
-            }
-
-            let y = current % height;
-            top = cmp::min(top, y);
-            bot = cmp::max(bot, y);
-        }
-
         Self {
             left: f64::from(left),
             top: f64::from(top),
-            width: f64::from(right - left),
-            height: f64::from(bot - top),
+            width: f64::from(right - left + 1),
+            height: f64::from(bottom - top + 1),
         }
     }
 }
@@ -230,3 +395,27 @@ impl From<&Polygons> for Bbox {
         }
     }
 }
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    // Expected values are the outputs of pycocotools' `mask.toBbox`.
+    #[rstest]
+    #[case::run_over_several_columns(vec![4, 5], vec![6, 5, 9], [1.0, 0.0, 2.0, 4.0])]
+    #[case::run_over_several_columns_no_trailing(vec![4, 5], vec![6, 5], [1.0, 0.0, 2.0, 4.0])]
+    #[case::runs_in_two_columns(vec![4, 4], vec![5, 2, 3, 2, 4], [1.0, 1.0, 2.0, 3.0])]
+    #[case::trailing_background(vec![4, 4], vec![1, 2, 13], [0.0, 1.0, 1.0, 2.0])]
+    #[case::empty(vec![4, 4], vec![], [0.0, 0.0, 0.0, 0.0])]
+    #[case::only_background(vec![4, 4], vec![16], [0.0, 0.0, 0.0, 0.0])]
+    #[case::single_pixel(vec![3, 3], vec![4, 1, 4], [1.0, 1.0, 1.0, 1.0])]
+    #[case::single_pixel_top_left(vec![3, 3], vec![0, 1, 8], [0.0, 0.0, 1.0, 1.0])]
+    #[case::empty_runs(vec![3, 3], vec![0, 0, 4, 1, 2, 0, 2], [1.0, 1.0, 1.0, 1.0])]
+    #[case::only_empty_runs(vec![3, 3], vec![0, 0, 9], [0.0, 0.0, 0.0, 0.0])]
+    fn bbox_from_rle(#[case] size: Vec<u32>, #[case] counts: Vec<u32>, #[case] expected: [f64; 4]) {
+        let bbox = Bbox::from(&Rle { size, counts });
+        assert_eq!([bbox.left, bbox.top, bbox.width, bbox.height], expected);
+    }
+}
@@ -1,10 +1,13 @@
 use std::iter::zip;
 
 use image;
-use imageproc::{drawing::draw_hollow_rect_mut, rect::Rect};
+use imageproc::{
+    drawing::{draw_filled_circle_mut, draw_hollow_rect_mut, draw_line_segment_mut},
+    rect::Rect,
+};
 use rand::Rng;
 
-use crate::coco::object_detection;
+use crate::coco::{object_detection, panoptic};
 use crate::errors::MaskError;
 use crate::mask;
 
@@ -74,6 +77,52 @@ pub fn mask(img: &mut image::RgbImage, mask: &mask::Mask, color: image::Rgb<u8>)
     }
 }
 
+/// Draw the keypoints, and the skeleton linking them, on the image.
+///
+/// Only the labeled keypoints (visibility flag greater than 0) are drawn.
+///
+/// ## Args
+/// - `img`: The image to draw on.
+/// - `keypoints`: The keypoints to draw, as `[x1, y1, v1, x2, y2, v2, ...]`.
+/// - `skeleton`: Pairs of (1-based) indices of the keypoints to link.
+/// - `color`: The color to use for drawing the keypoints.
+///
+/// ## Example
+///
+/// ```rust
+/// # use image::RgbImage;
+/// use cocotools::visualize::draw;
+/// let mut img = RgbImage::new(60, 60);
+/// let keypoints = vec![10.0, 10.0, 2.0, 40.0, 40.0, 1.0, 0.0, 0.0, 0.0];
+/// let color = image::Rgb([255, 0, 0]);
+/// draw::keypoints(&mut img, &keypoints, &[(1, 2), (2, 3)], color);
+/// ```
+#[allow(clippy::cast_possible_truncation)]
+pub fn keypoints(
+    img: &mut image::RgbImage,
+    keypoints: &[f64],
+    skeleton: &[(u32, u32)],
+    color: image::Rgb<u8>,
+) {
+    let point = |idx: u32| {
+        let idx = usize::try_from(idx).ok()?.checked_sub(1)?;
+        match keypoints.get(3 * idx..3 * idx + 3) {
+            Some([x, y, v]) if *v > 0.0 => Some((*x as f32, *y as f32)),
+            _ => None,
+        }
+    };
+    for (start, end) in skeleton {
+        if let (Some(start), Some(end)) = (point(*start), point(*end)) {
+            draw_line_segment_mut(img, start, end, color);
+        }
+    }
+    for keypoint in keypoints.chunks_exact(3) {
+        if keypoint[2] > 0.0 {
+            draw_filled_circle_mut(img, (keypoint[0] as i32, keypoint[1] as i32), 2, color);
+        }
+    }
+}
+
 /// Draw the segmentation masks, and optionnaly the bounding boxes of the annotations on the image.
 ///
 /// ## Args
@@ -106,6 +155,10 @@ pub fn mask(img: &mut image::RgbImage, mask: &mask::Mask, color: image::Rgb<u8>)
 ///             height: 20.0,
 ///         },
 ///         iscrowd: 0,
+///         score: None,
+///         keypoints: None,
+///         num_keypoints: None,
+///         extra: object_detection::ExtraFields::default(),
 ///     },
 ///     object_detection::Annotation {
 ///         id: 2,
@@ -123,6 +176,10 @@ pub fn mask(img: &mut image::RgbImage, mask: &mask::Mask, color: image::Rgb<u8>)
 ///             height: 24.0,
 ///         },
 ///         iscrowd: 0,
+///         score: None,
+///         keypoints: None,
+///         num_keypoints: None,
+///         extra: object_detection::ExtraFields::default(),
 ///     },
 /// ];
 /// draw::anns(&mut img, &anns.iter().collect(), true);
@@ -135,6 +192,26 @@ pub fn anns(
     img: &mut image::ImageBuffer<image::Rgb<u8>, Vec<u8>>,
     anns: &Vec<&object_detection::Annotation>,
     draw_bbox: bool,
+) -> Result<(), MaskError> {
+    anns_with_skeletons(img, anns, &[], draw_bbox)
+}
+
+/// Same as [`anns`], but the keypoints are linked using the skeleton of their category.
+///
+/// ## Args
+/// - `img`: The image to draw on.
+/// - `anns`: The annotations to draw. They are assumed to correspong to the image, or to an image of the same size as `img`.
+/// - `cats`: The categories of the annotations. The keypoints of annotations whose category is missing are drawn without skeleton.
+/// - `draw_bbox`: If true, then also the bounding boxes.
+///
+/// ## Errors
+///
+/// Will return `Err` if the segmentation annotations could not be decompressed.
+pub fn anns_with_skeletons(
+    img: &mut image::ImageBuffer<image::Rgb<u8>, Vec<u8>>,
+    anns: &Vec<&object_detection::Annotation>,
+    cats: &[&object_detection::Category],
+    draw_bbox: bool,
 ) -> Result<(), MaskError> {
     let mut rng = rand::thread_rng();
     for ann in anns {
@@ -144,6 +221,59 @@ pub fn anns(
         }
         let mask = mask::Mask::try_from(&ann.segmentation)?;
         self::mask(img, &mask, color);
+        if let Some(keypoints) = &ann.keypoints {
+            let skeleton = cats
+                .iter()
+                .find(|cat| cat.id == ann.category_id)
+                .and_then(|cat| cat.skeleton.as_deref())
+                .unwrap_or_default();
+            self::keypoints(img, keypoints, skeleton, color);
+        }
+    }
+
+    Ok(())
+}
+
+/// Draw the segments of a panoptic annotation on the image.
+///
+/// Each segment is drawn with the color of its category. For thing categories, the color is slightly changed for each segment so that adjacent instances can be told apart.
+///
+/// ## Args
+/// - `img`: The image to draw on.
+/// - `segments`: The segments to draw along with their masks (see [`panoptic::HashmapPanopticDataset::get_img_segments`]).
+/// - `cats`: The categories of the segments. Segments whose category is missing are drawn with a random color.
+/// - `draw_bbox`: If true, then also draw the bounding boxes of the thing segments.
+///
+/// ## Errors
+///
+/// Will return `Err` if a mask does not have the same size as the image.
+pub fn panoptic_segments(
+    img: &mut image::RgbImage,
+    segments: &[(&panoptic::SegmentInfo, object_detection::Rle)],
+    cats: &[&panoptic::PanopticCategory],
+    draw_bbox: bool,
+) -> Result<(), MaskError> {
+    let mut rng = rand::thread_rng();
+    for (segment, rle) in segments {
+        if rle.size != [img.height(), img.width()] {
+            return Err(MaskError::SizeMismatch(
+                rle.size.clone(),
+                vec![img.height(), img.width()],
+            ));
+        }
+        let cat = cats.iter().find(|cat| cat.id == segment.category_id);
+        let color = match cat {
+            Some(cat) if cat.isthing == 0 => image::Rgb(cat.color),
+            Some(cat) => image::Rgb(cat.color.map(|channel| {
+                let channel = i16::from(channel) + rng.gen_range(-30..=30);
+                u8::try_from(channel.clamp(0, 255)).unwrap_or(u8::MAX)
+            })),
+            None => image::Rgb([rng.gen::<u8>(), rng.gen::<u8>(), rng.gen::<u8>()]),
+        };
+        if draw_bbox && cat.map_or(true, |cat| cat.isthing != 0) {
+            self::bbox(img, &segment.bbox, color);
+        }
+        self::mask(img, &mask::Mask::from(rle), color);
     }
 
     Ok(())
@@ -1,11 +1,13 @@
 __version__ = "0.0.7"  # noqa: Y052, RUF100
 
+from typing import Any
+
 import numpy as np
 import numpy.typing as npt
 from typing_extensions import Self
 
 from . import mask  # noqa: F401  # pyright: ignore[reportUnusedImport]
-from .anns import _AnnotationAny, Category, Image
+from .anns import _AnnotationAny, Category, Image, Info, License
 
 class COCO:
     def __init__(self: Self, annotation_path: str, image_folder_path: str) -> None: ...
@@ -16,10 +18,22 @@ class COCO:
     def get_img(self: Self, img_id: int) -> Image: ...
     def get_imgs(self: Self) -> list[Image]: ...
     def get_img_anns(self: Self, img_id: int) -> list[_AnnotationAny]: ...
+    def get_info(self: Self) -> Info | None: ...
+    def set_info(self: Self, info: Info | None) -> None: ...
+    def get_licenses(self: Self) -> list[License] | None: ...
+    def set_licenses(self: Self, licenses: list[License] | None) -> None: ...
+    def get_extra(self: Self) -> dict[str, Any]: ...
+    def set_extra(self: Self, extra: dict[str, Any]) -> None: ...
     def visualize_img(self: Self, img_id: int) -> None: ...
     def draw_anns(self: Self, img_id: int, draw_bboxes: bool) -> npt.NDArray[np.uint8]:
         """Draw the annotations on the image and returns it as a (RGB) numpy array."""
         ...
+    def load_results(self: Self, results_path: str) -> COCO:
+        """Load a COCO results file, with the images and categories of this dataset."""
+        ...
+    def sort_by_id(self: Self) -> None:
+        """Order the images, annotations and categories by id, instead of insertion order."""
+        ...
     def json(self: Self) -> str:
         """Return the dataset as a json string."""
         ...
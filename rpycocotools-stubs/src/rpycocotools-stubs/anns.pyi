@@ -1,5 +1,5 @@
 from collections.abc import Iterator, Sequence
-from typing import Generic, TypeAlias, TypeVar
+from typing import Any, Generic, TypeAlias, TypeVar
 
 from typing_extensions import Self
 
@@ -15,6 +15,10 @@ class Annotation(Generic[_TSegmentation]):
     area: float
     bbox: BBox
     iscrowd: int
+    score: float | None
+    keypoints: list[float] | None
+    num_keypoints: int | None
+    extra: dict[str, Any]
 
     def __init__(
         self: Self,
@@ -25,6 +29,10 @@ class Annotation(Generic[_TSegmentation]):
         area: float,
         bbox: BBox,
         iscrowd: int,
+        score: float | None = None,
+        keypoints: list[float] | None = None,
+        num_keypoints: int | None = None,
+        extra: dict[str, Any] | None = None,
     ) -> None: ...
 
 _AnnotationAny: TypeAlias = (
@@ -39,7 +47,18 @@ class Category:
     id: int
     name: str
     supercategory: str
-    def __init__(self: Self, id: int, name: str, supercategory: str) -> None: ...
+    keypoints: list[str] | None
+    skeleton: list[tuple[int, int]] | None
+    extra: dict[str, Any]
+    def __init__(
+        self: Self,
+        id: int,
+        name: str,
+        supercategory: str,
+        keypoints: list[str] | None = None,
+        skeleton: list[tuple[int, int]] | None = None,
+        extra: dict[str, Any] | None = None,
+    ) -> None: ...
 
 class BBox:
     left: float
@@ -54,7 +73,40 @@ class Image:
     width: int
     height: int
     file_name: str
-    def __init__(self: Self, id: int, width: int, height: int, file_name: str) -> None: ...
+    extra: dict[str, Any]
+    def __init__(
+        self: Self,
+        id: int,
+        width: int,
+        height: int,
+        file_name: str,
+        extra: dict[str, Any] | None = None,
+    ) -> None: ...
+
+class Info:
+    year: int
+    version: str
+    description: str
+    contributor: str
+    url: str
+    date_created: str
+    extra: dict[str, Any]
+    def __init__(
+        self: Self,
+        year: int,
+        version: str,
+        description: str,
+        contributor: str,
+        url: str,
+        date_created: str,
+        extra: dict[str, Any] | None = None,
+    ) -> None: ...
+
+class License:
+    id: int
+    name: str
+    url: str
+    def __init__(self: Self, id: int, name: str, url: str) -> None: ...
 
 Polygons: TypeAlias = list[list[float]]
 
@@ -1,7 +1,19 @@
 """Module providing COCO annotations classes."""
 from typing import Generic, TypeVar
 
-from _rpycocotools.anns import Annotation, BBox, Category, COCO_RLE, from_dataset, Image, Polygons, PolygonsRS, RLE
+from _rpycocotools.anns import (
+    Annotation,
+    BBox,
+    Category,
+    COCO_RLE,
+    from_dataset,
+    Image,
+    Info,
+    License,
+    Polygons,
+    PolygonsRS,
+    RLE,
+)
 
 _TSegmentation = TypeVar("_TSegmentation", Polygons, PolygonsRS, RLE, COCO_RLE)
 
@@ -66,7 +66,7 @@ impl PyCOCO {
         )
     }
 
-    /// Order is non-deterministic
+    /// The images are in insertion order, or in id order after calling `sort_by_id`.
     fn get_imgs(&self, py: Python<'_>) -> PyResult<Vec<Py<object_detection::Image>>> {
         self.0
             .get_imgs()
@@ -104,6 +104,41 @@ impl PyCOCO {
             .collect()
     }
 
+    fn get_info(&self, py: Python<'_>) -> PyResult<Option<Py<object_detection::Info>>> {
+        self.0
+            .get_info()
+            .map(|info| Py::new(py, info.clone()))
+            .transpose()
+    }
+
+    fn set_info(&mut self, info: Option<object_detection::Info>) {
+        self.0.set_info(info);
+    }
+
+    fn get_licenses(&self, py: Python<'_>) -> PyResult<Option<Vec<Py<object_detection::License>>>> {
+        self.0
+            .get_licenses()
+            .map(|licenses| {
+                licenses
+                    .iter()
+                    .map(|license| Py::new(py, license.clone()))
+                    .collect()
+            })
+            .transpose()
+    }
+
+    fn set_licenses(&mut self, licenses: Option<Vec<object_detection::License>>) {
+        self.0.set_licenses(licenses);
+    }
+
+    fn get_extra(&self) -> object_detection::ExtraFields {
+        self.0.get_extra().clone()
+    }
+
+    fn set_extra(&mut self, extra: object_detection::ExtraFields) {
+        self.0.set_extra(extra);
+    }
+
     /// Visualize an image and its annotations.
     ///
     /// ## Errors
@@ -117,6 +152,8 @@ impl PyCOCO {
                 CocoError::MissingId(err) => PyKeyError::new_err(err.to_string()),
                 CocoError::Mask(err) => PyValueError::new_err(err.to_string()),
                 CocoError::Loading(err) => PyValueError::new_err(err.to_string()),
+                CocoError::Evaluation(err) => PyValueError::new_err(err.to_string()),
+                CocoError::Conversion(err) => PyValueError::new_err(err.to_string()),
             })?;
 
         let file_name = &self
@@ -148,6 +185,8 @@ impl PyCOCO {
                 CocoError::MissingId(err) => PyKeyError::new_err(err.to_string()),
                 CocoError::Mask(err) => PyValueError::new_err(err.to_string()),
                 CocoError::Loading(err) => PyValueError::new_err(err.to_string()),
+                CocoError::Evaluation(err) => PyValueError::new_err(err.to_string()),
+                CocoError::Conversion(err) => PyValueError::new_err(err.to_string()),
             })?;
 
         let img = img
@@ -157,6 +196,25 @@ impl PyCOCO {
         Ok(img)
     }
 
+    /// Load a COCO results file, with the images and categories of this dataset.
+    ///
+    /// ## Errors
+    ///
+    /// Will return `Err` if the results file cannot be read or parsed, or if a detection corresponds to an image not in the dataset.
+    fn load_results(&self, results_path: &PyUnicode) -> PyResult<Self> {
+        let results_path = PathBuf::from(results_path.to_str()?);
+        let dataset = self
+            .0
+            .load_results(results_path)
+            .map_err(PyLoadingError::from)?;
+        Ok(Self(dataset))
+    }
+
+    /// Order the images, annotations and categories by id, instead of insertion order.
+    fn sort_by_id(&mut self) {
+        self.0.sort_by_id();
+    }
+
     fn json(&self) -> PyResult<String> {
         self.0
             .json()
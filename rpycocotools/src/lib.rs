@@ -17,6 +17,8 @@ fn anns(_py: Python<'_>, module: &PyModule) -> PyResult<()> {
     module.add_class::<cocotools::coco::object_detection::Rle>()?;
     module.add_class::<cocotools::coco::object_detection::CocoRle>()?;
     module.add_class::<cocotools::coco::object_detection::Image>()?;
+    module.add_class::<cocotools::coco::object_detection::Info>()?;
+    module.add_class::<cocotools::coco::object_detection::License>()?;
     module.add_function(wrap_pyfunction!(coco::from_dataset, module)?)?;
     Ok(())
 }
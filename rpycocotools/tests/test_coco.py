@@ -2,7 +2,7 @@ from hypothesis import given
 from hypothesis import strategies as st
 
 import rpycocotools
-from rpycocotools.anns import Annotation, BBox, COCO_RLE
+from rpycocotools.anns import Annotation, BBox, COCO_RLE, Info, License
 
 u32_max = 4_294_967_295
 u32_st = st.integers(min_value=0, max_value=u32_max)
@@ -105,3 +105,34 @@ def test_dataset_from_components() -> None:
     ]
     coco_dataset = rpycocotools.anns.from_dataset(imgs, anns, cats, "a")
     assert coco_dataset.get_img_anns(1)[0] == expected_ann
+
+
+def test_extra_fields() -> None:
+    img = rpycocotools.anns.Image(1, 380, 800, "test1", extra={"occluded": False, "attributes": {"track_id": 3}})
+    assert img.extra == {"occluded": False, "attributes": {"track_id": 3}}
+    img.extra = {"ignore": 1}
+    assert img.extra["ignore"] == 1
+    assert rpycocotools.anns.Category(1, "a", "c").extra == {}
+
+
+def test_info_and_licenses(coco_dataset: rpycocotools.COCO) -> None:
+    assert coco_dataset.get_info() is None
+    assert coco_dataset.get_licenses() is None
+    info = Info(2017, "1.0", "COCO 2017 sample", "COCO Consortium", "http://cocodataset.org", "2017/09/01")
+    licenses = [License(1, "Attribution License", "http://creativecommons.org/licenses/by/2.0/")]
+    coco_dataset.set_info(info)
+    coco_dataset.set_licenses(licenses)
+    assert coco_dataset.get_info() == info
+    assert coco_dataset.get_licenses() == licenses
+    assert '"licenses":[{"id":1,"name":"Attribution License"' in coco_dataset.json()
+    coco_dataset.set_info(None)
+    assert '"info"' not in coco_dataset.json()
+
+
+def test_dataset_extra_fields(coco_dataset: rpycocotools.COCO) -> None:
+    assert coco_dataset.get_extra() == {}
+    coco_dataset.set_extra({"type": "instances"})
+    assert coco_dataset.get_extra() == {"type": "instances"}
+    assert '"type":"instances"' in coco_dataset.json()
+    info = Info(2017, "1.0", "", "", "", "", extra={"split": "val"})
+    assert info.extra == {"split": "val"}